@@ -0,0 +1,53 @@
+"""Known answers for the AES enctypes in src/crypto/aes.rs.
+
+aes128- and aes256-cts-hmac-sha1-96 follow RFC 3962 with the keys 00..0f
+and 00..1f. aes128-cts-hmac-sha256-128 and aes256-cts-hmac-sha384-192
+follow RFC 8009 sections 3 and 5 with the appendix A base keys. Both use
+key usage, confounder and plaintext as in the tests.
+
+python3 fixtures/gen/aes.py
+"""
+import hashlib
+import hmac
+
+from rfc3961 import aes_cts_encrypt, aes_sha1_encrypt
+
+PLAINTEXT = b"metaros known-answer test"
+CONFOUNDER = bytes(range(0xa0, 0xb0))
+SHA2_KEYS = [
+    ("aes128-cts-hmac-sha256-128", bytes.fromhex("3705d96080c17728a0e800eab6e0d23c"), hashlib.sha256, 128, 128),
+    ("aes256-cts-hmac-sha384-192", bytes.fromhex("6d404d37faf79f9df0d33568d320669800eb4836472ea8a026d16b7182460c52"),
+     hashlib.sha384, 256, 192),
+]
+
+
+def kdf_hmac_sha2(key, label, bits, digest):
+    """KDF-HMAC-SHA2, RFC 8009 section 3: one round of SP 800-108 counter mode."""
+    message = (1).to_bytes(4, "big") + label + b"\x00" + bits.to_bytes(4, "big")
+    return hmac.new(key, message, digest).digest()[:bits // 8]
+
+
+def aes_sha2_encrypt(key, digest, key_bits, mac_bits, usage, confounder, plaintext):
+    """RFC 8009 section 5, with a zero cipher state."""
+    ke = kdf_hmac_sha2(key, usage.to_bytes(4, "big") + b"\xaa", key_bits, digest)
+    ki = kdf_hmac_sha2(key, usage.to_bytes(4, "big") + b"\x55", mac_bits, digest)
+    ciphertext = aes_cts_encrypt(ke, confounder + plaintext)
+    return ciphertext + hmac.new(ki, bytes(16) + ciphertext, digest).digest()[:mac_bits // 8]
+
+
+if __name__ == "__main__":
+    # RFC 3962 appendix B and RFC 8009 appendix A first
+    assert aes_cts_encrypt(b"chicken teriyaki", b"I would like the ").hex() == \
+        "c6353568f2bf8cb4d8a580362da7ff7f97"
+    _, key128, digest, key_bits, mac_bits = SHA2_KEYS[0]
+    assert kdf_hmac_sha2(key128, bytes.fromhex("00000002aa"), 128, digest).hex() == \
+        "9b197dd1e8c5609d6e67c3e37c62c72e"
+    assert aes_sha2_encrypt(key128, digest, key_bits, mac_bits, 2,
+                            bytes.fromhex("7e5895eaf2672435bad817f545a37148"), b"").hex() == \
+        "ef85fb890bb8472f4dab20394dca781dad877eda39d50c870c0d5a0a8e48c718"
+    for size in (16, 32):
+        print("aes%d-cts-hmac-sha1-96, usage 3:" % (size * 8),
+              aes_sha1_encrypt(bytes(range(size)), 3, CONFOUNDER, PLAINTEXT).hex())
+    for name, key, digest, key_bits, mac_bits in SHA2_KEYS:
+        print("%s, usage 2:" % name,
+              aes_sha2_encrypt(key, digest, key_bits, mac_bits, 2, CONFOUNDER, PLAINTEXT).hex())
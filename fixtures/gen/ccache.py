@@ -0,0 +1,75 @@
+"""The credential caches in fixtures/ccache, for the round trip tests in
+src/kerberos/ccache.rs.
+
+v3.ccache and v4.ccache hold the same credentials for user@EXAMPLE.COM in
+the MIT file formats 0x0503 and 0x0504, following the krb5 ccache file
+format document: a fast_avail config entry, a TGT, and a service ticket
+with an address, authdata and a second ticket. Version 3 repeats each key's
+enctype; version 4 adds a kdc offset header tag of -42s 123456us. The
+tickets' ciphertexts are filler.
+
+python3 fixtures/gen/ccache.py
+"""
+import struct
+
+from rfc4120 import Ticket, encode, opaque, ticket
+
+REALM = "EXAMPLE.COM"
+KRBTGT = ["krbtgt", REALM]
+CIFS = ["cifs", "fs01.example.com"]
+OUT = "fixtures/ccache/"
+
+
+def data(b):
+    return struct.pack(">I", len(b)) + b
+
+
+def principal(name_type, realm, components):
+    return (struct.pack(">II", name_type, len(components)) + data(realm.encode())
+            + b"".join(data(c.encode()) for c in components))
+
+
+def encoded_ticket(sname, etype, kvno, cipher):
+    t = Ticket()
+    ticket(t, REALM, sname, etype, kvno, cipher)
+    return encode(t)
+
+
+def credential(version, client, server, etype, key, times, is_skey, flags, addresses, authdata,
+               tkt, second_ticket=b""):
+    out = client + server + struct.pack(">H", etype)
+    if version == 3:
+        out += struct.pack(">H", etype)
+    out += data(key) + struct.pack(">IIIIBI", *times, is_skey, flags)
+    out += struct.pack(">I", len(addresses)) + b"".join(struct.pack(">H", t) + data(a) for t, a in addresses)
+    out += struct.pack(">I", len(authdata)) + b"".join(struct.pack(">H", t) + data(a) for t, a in authdata)
+    return out + data(tkt) + data(second_ticket)
+
+
+def ccache(version):
+    out = struct.pack(">H", 0x0500 | version)
+    if version == 4:
+        tags = struct.pack(">HHii", 1, 8, -42, 123456)
+        out += struct.pack(">H", len(tags)) + tags
+    user = principal(1, REALM, ["user"])
+    out += user
+    config = principal(0, "X-CACHECONF:", ["krb5_ccache_conf_data", "fast_avail", "krbtgt/EXAMPLE.COM@EXAMPLE.COM"])
+    out += credential(version, user, config, 0, b"", (0, 0, 0, 0), 0, 0, [], [], b"yes")
+    out += credential(version, user, principal(2, REALM, KRBTGT), 18, opaque("ccache tgt key", 32),
+                      (1700000000, 1700000000, 1700036000, 1700604800), 0, 0x40e10000, [], [],
+                      encoded_ticket(KRBTGT, 18, 2, opaque("ccache tgt", 300)))
+    out += credential(version, user, principal(3, REALM, CIFS), 23, opaque("ccache service key", 16),
+                      (1700000000, 0, 1700036000, 0), 1, 0x40250000, [(2, bytes([10, 0, 0, 5]))],
+                      [(1, b"\x30\x00")], encoded_ticket(CIFS, 23, 7, opaque("ccache service ticket", 100)),
+                      b"\x61\x00")
+    return out
+
+
+def main():
+    for version in [3, 4]:
+        with open(OUT + "v%d.ccache" % version, "wb") as f:
+            f.write(ccache(version))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,54 @@
+"""Known answers for src/crypto/checksum.rs that no RFC publishes: the
+DES keyed checksums with a fixed confounder and the RFC 3962
+hmac-sha1-96-aes checksums.
+
+  rsa-md4-des, rsa-md5-des  RFC 3961 section 6.2.5 (RFC 1510 section 6.4.5)
+  des-mac, des-mac-k        RFC 1510 section 6.4.5
+  hmac-sha1-96-aes*         RFC 3961 section 5.4 with RFC 3962's parameters
+
+The DES key is RFC 3961 appendix A.2's and the AES keys are RFC 3962
+appendix B's single iteration keys for "password".
+
+python3 fixtures/gen/checksums.py
+"""
+import hashlib
+import hmac
+
+from rfc3961 import aes_dk, des_cbc, des_cbc_checksum, md4
+
+DES_KEY = bytes.fromhex("cbc22fae235298e3")
+AES128_KEY = bytes.fromhex("42263c6e89f4fc28b8df68ee09799f15")
+AES256_KEY = bytes.fromhex("fe697b52bc0d3ce14432ba036a92e65bbb52280990a2fa27883998d72af30161")
+PLAINTEXT = b"metaros known-answer test"
+CONFOUNDER = bytes(range(0xa0, 0xa8))
+
+
+def variant(key):
+    return bytes(b ^ 0xf0 for b in key)
+
+
+def rsa_des(digest, key, confounder, data):
+    return des_cbc(variant(key), bytes(8), confounder + digest(confounder + data))
+
+
+def des_mac(key, confounder, data):
+    mac = des_cbc_checksum(key, bytes(8), confounder + data)
+    return des_cbc(variant(key), bytes(8), confounder + mac)
+
+
+def des_mac_k(key, data):
+    return des_cbc_checksum(key, key, data)
+
+
+def hmac_sha1_96_aes(key, usage, data):
+    kc = aes_dk(key, usage.to_bytes(4, "big") + b"\x99")
+    return hmac.new(kc, data, hashlib.sha1).digest()[:12]
+
+
+if __name__ == "__main__":
+    print("rsa-md4-des:", rsa_des(md4, DES_KEY, CONFOUNDER, PLAINTEXT).hex())
+    print("rsa-md5-des:", rsa_des(lambda d: hashlib.md5(d).digest(), DES_KEY, CONFOUNDER, PLAINTEXT).hex())
+    print("des-mac:", des_mac(DES_KEY, CONFOUNDER, PLAINTEXT).hex())
+    print("des-mac-k:", des_mac_k(DES_KEY, PLAINTEXT).hex())
+    print("hmac-sha1-96-aes128, usage 17:", hmac_sha1_96_aes(AES128_KEY, 17, PLAINTEXT).hex())
+    print("hmac-sha1-96-aes256, usage 17:", hmac_sha1_96_aes(AES256_KEY, 17, PLAINTEXT).hex())
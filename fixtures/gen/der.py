@@ -0,0 +1,216 @@
+"""The messages in fixtures/der, for the round trip tests in
+src/kerberos/asn1.rs.
+
+One of each message type with the optional fields a KDC and client fill in,
+plus EncKrbCredPart and EncTicketPart. The tickets are large enough to need
+two byte lengths, and the second KrbCredInfo ends after 2106 so its time
+does not fit in 32 bits. Ciphertexts and keys are filler: the tests only
+parse and re-encode them.
+
+python3 fixtures/gen/der.py
+"""
+from pyasn1.type import univ
+
+from rfc4120 import (ADEntry, APREP, APREQ, ASREP, ASREQ, EncKrbCredPart, EncryptedData,
+                     EncTicketPart, EtypeInfo2Entry, HostAddress, KRBCRED, KRBERROR,
+                     KrbCredInfo, PAData, PacReq, TGSREP, TGSREQ, encode,
+                     encrypted_data, flags, opaque, principal, ticket)
+
+REALM = "EXAMPLE.COM"
+KRBTGT = ["krbtgt", REALM]
+CIFS = ["cifs", "fs01.example.com"]
+OUT = "fixtures/der/"
+
+TGT = (REALM, KRBTGT, 18, 2, opaque("der tgt", 1100))
+SERVICE_TICKET = (REALM, CIFS, 23, 7, opaque("der service ticket", 900))
+
+
+def pa_data(padata_type, value):
+    pa = PAData()
+    pa["padata-type"] = padata_type
+    pa["padata-value"] = value
+    return pa
+
+
+def as_req():
+    ts = EncryptedData()
+    encrypted_data(ts, 18, None, opaque("der pa-enc-timestamp", 56))
+    pac = PacReq()
+    pac["include-pac"] = True
+    r = ASREQ()
+    r["pvno"] = 5
+    r["msg-type"] = 10
+    r["padata"][0] = pa_data(2, encode(ts))
+    r["padata"][1] = pa_data(128, encode(pac))
+    b = r["req-body"]
+    b["kdc-options"] = flags(0x40810010)
+    principal(b["cname"], 1, ["user"])
+    b["realm"] = REALM
+    principal(b["sname"], 2, KRBTGT)
+    b["till"] = "20370913024805Z"
+    b["rtime"] = "20370913024805Z"
+    b["nonce"] = 0x7fffffff
+    for i, etype in enumerate([18, 17, 23, -135, 3]):
+        b["etype"][i] = etype
+    address = HostAddress()
+    address["addr-type"] = 20
+    address["address"] = b"WS01           "
+    b["addresses"][0] = address
+    return r
+
+
+def as_rep():
+    info = univ.SequenceOf(componentType=EtypeInfo2Entry())
+    for i, (etype, salt, params) in enumerate([
+            (18, "EXAMPLE.COMuser", None), (23, None, None), (17, "EXAMPLE.COMuser", b"\x00\x00\x10\x00")]):
+        entry = EtypeInfo2Entry()
+        entry["etype"] = etype
+        if salt is not None:
+            entry["salt"] = salt
+        if params is not None:
+            entry["s2kparams"] = params
+        info[i] = entry
+    r = ASREP()
+    r["pvno"] = 5
+    r["msg-type"] = 11
+    r["padata"][0] = pa_data(19, encode(info))
+    r["crealm"] = REALM
+    principal(r["cname"], 1, ["user"])
+    ticket(r["ticket"], *TGT)
+    encrypted_data(r["enc-part"], 18, 1, opaque("der as-rep enc-part", 300))
+    return r
+
+
+def ap_req():
+    r = APREQ()
+    r["pvno"] = 5
+    r["msg-type"] = 14
+    r["ap-options"] = flags(0x20000000)
+    ticket(r["ticket"], *TGT)
+    encrypted_data(r["authenticator"], 18, None, opaque("der authenticator", 180))
+    return r
+
+
+def tgs_req():
+    r = TGSREQ()
+    r["pvno"] = 5
+    r["msg-type"] = 12
+    r["padata"][0] = pa_data(1, encode(ap_req()))
+    b = r["req-body"]
+    b["kdc-options"] = flags(0x40810000)
+    b["realm"] = REALM
+    principal(b["sname"], 2, CIFS)
+    b["from"] = "20231114221320Z"
+    b["till"] = "20231115081320Z"
+    b["nonce"] = 12345
+    for i, etype in enumerate([18, 23]):
+        b["etype"][i] = etype
+    encrypted_data(b["enc-authorization-data"], 18, None, opaque("der enc-authorization-data", 40))
+    ticket(b["additional-tickets"][0], *TGT)
+    return r
+
+
+def tgs_rep():
+    r = TGSREP()
+    r["pvno"] = 5
+    r["msg-type"] = 13
+    r["crealm"] = REALM
+    principal(r["cname"], 1, ["user"])
+    ticket(r["ticket"], *SERVICE_TICKET)
+    encrypted_data(r["enc-part"], 18, None, opaque("der tgs-rep enc-part", 200))
+    return r
+
+
+def ap_rep():
+    r = APREP()
+    r["pvno"] = 5
+    r["msg-type"] = 15
+    encrypted_data(r["enc-part"], 18, None, opaque("der ap-rep enc-part", 60))
+    return r
+
+
+def krb_error():
+    r = KRBERROR()
+    r["pvno"] = 5
+    r["msg-type"] = 30
+    r["stime"] = "20231114221320Z"
+    r["susec"] = 999999
+    r["error-code"] = 25
+    r["crealm"] = REALM
+    principal(r["cname"], 1, ["user"])
+    r["realm"] = REALM
+    principal(r["sname"], 2, KRBTGT)
+    r["e-text"] = "Additional pre-authentication required"
+    r["e-data"] = encode(univ.SequenceOf(componentType=PAData()).clone())
+    return r
+
+
+def enc_krb_cred_part():
+    info = KrbCredInfo()
+    info["key"]["keytype"] = 18
+    info["key"]["keyvalue"] = opaque("der krb-cred tgt key", 32)
+    info["prealm"] = REALM
+    principal(info["pname"], 1, ["user"])
+    info["flags"] = flags(0x40e10000)
+    info["authtime"] = "20231114221320Z"
+    info["starttime"] = "20231114221320Z"
+    info["endtime"] = "20231115081320Z"
+    info["renew-till"] = "20231121221320Z"
+    info["srealm"] = REALM
+    principal(info["sname"], 2, KRBTGT)
+    late = KrbCredInfo()
+    late["key"]["keytype"] = 23
+    late["key"]["keyvalue"] = opaque("der krb-cred service key", 16)
+    late["prealm"] = REALM
+    principal(late["pname"], 1, ["user"])
+    late["endtime"] = "21060207062816Z"
+    p = EncKrbCredPart()
+    p["ticket-info"][0] = info
+    p["ticket-info"][1] = late
+    return p
+
+
+def krb_cred(part):
+    r = KRBCRED()
+    r["pvno"] = 5
+    r["msg-type"] = 22
+    ticket(r["tickets"][0], *TGT)
+    ticket(r["tickets"][1], *SERVICE_TICKET)
+    encrypted_data(r["enc-part"], 0, None, part)
+    return r
+
+
+def enc_ticket_part():
+    p = EncTicketPart()
+    p["flags"] = flags(0x40e10000)
+    p["key"]["keytype"] = 18
+    p["key"]["keyvalue"] = opaque("der ticket session key", 32)
+    p["crealm"] = REALM
+    principal(p["cname"], 1, ["user"])
+    p["transited"]["tr-type"] = 1
+    p["transited"]["contents"] = b""
+    p["authtime"] = "20231114221320Z"
+    p["starttime"] = "20231114221320Z"
+    p["endtime"] = "20231115081320Z"
+    p["renew-till"] = "20231121221320Z"
+    ad = ADEntry()
+    ad["ad-type"] = 1
+    ad["ad-data"] = opaque("der ticket authorization data", 500)
+    p["authorization-data"][0] = ad
+    return p
+
+
+def main():
+    part = encode(enc_krb_cred_part())
+    for name, message in [
+            ("as-req", as_req()), ("as-rep", as_rep()), ("tgs-req", tgs_req()), ("tgs-rep", tgs_rep()),
+            ("ap-req", ap_req()), ("ap-rep", ap_rep()), ("krb-error", krb_error()),
+            ("krb-cred", krb_cred(part)), ("enc-ticket-part", enc_ticket_part())]:
+        with open(OUT + name + ".der", "wb") as f:
+            f.write(encode(message))
+    with open(OUT + "enc-krb-cred-part.der", "wb") as f:
+        f.write(part)
+
+
+if __name__ == "__main__":
+    main()
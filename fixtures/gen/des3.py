@@ -0,0 +1,41 @@
+"""Known answers for des3-cbc-sha1-kd in src/crypto/des.rs, following RFC
+3961 section 6.3 from the appendix A.4 key for "password" and
+"ATHENA.MIT.EDUraeburn".
+
+python3 fixtures/gen/des3.py
+"""
+import hashlib
+import hmac
+
+from rfc3961 import des3_dk, des_cbc, nfold
+
+KEY = bytes.fromhex("850bb51358548cd05e86768c313e3bfef7511937dcf72c3e")
+PLAINTEXT = b"metaros known-answer test"
+CONFOUNDER = bytes(range(0xa0, 0xa8))
+
+
+def usage_key(key, usage, kind):
+    return des3_dk(key, usage.to_bytes(4, "big") + bytes([kind]))
+
+
+def encrypt(key, usage, confounder, plaintext):
+    data = confounder + plaintext
+    data += bytes(-len(data) % 8)
+    ke, ki = usage_key(key, usage, 0xaa), usage_key(key, usage, 0x55)
+    return des_cbc(ke, bytes(8), data) + hmac.new(ki, data, hashlib.sha1).digest()
+
+
+def checksum(key, usage, data):
+    return hmac.new(usage_key(key, usage, 0x99), data, hashlib.sha1).digest()
+
+
+if __name__ == "__main__":
+    # RFC 3961 appendix A.1 and A.3 first, so a wrong fold shows up here
+    assert nfold(b"012345", 8).hex() == "be072631276b1955"
+    assert des3_dk(bytes.fromhex("dce06b1f64c857a11c3db57c51899b2cc1791008ce973b92"),
+                   bytes.fromhex("0000000155")).hex() == \
+        "925179d04591a79b5d3192c4a7e9c289b049c71f6ee604cd"
+    for name, kind in (("Kc", 0x99), ("Ke", 0xaa), ("Ki", 0x55)):
+        print("%s, usage 2:" % name, usage_key(KEY, 2, kind).hex())
+    print("encrypt, usage 3:", encrypt(KEY, 3, CONFOUNDER, PLAINTEXT).hex())
+    print("checksum, usage 17:", checksum(KEY, 17, PLAINTEXT).hex())
@@ -0,0 +1,61 @@
+"""The KRB-CRED in fixtures/kirbi, for the tests in src/kerberos/kirbi.rs.
+
+user.kirbi is a KRB-CRED as Mimikatz and Rubeus write it, with the
+EncKrbCredPart in the clear under etype 0, and user.kirbi.b64 the same
+bytes as the single line of base64 Rubeus prints. It holds a TGT whose
+KrbCredInfo has every field a ccache keeps, and a service ticket whose
+KrbCredInfo has only the key and client. Ciphertexts and keys are filler.
+
+python3 fixtures/gen/kirbi.py
+"""
+import base64
+
+from rfc4120 import (EncKrbCredPart, KRBCRED, KrbCredInfo, encode, encrypted_data, flags, opaque,
+                     principal, ticket)
+
+REALM = "EXAMPLE.COM"
+KRBTGT = ["krbtgt", REALM]
+CIFS = ["cifs", "fs01.example.com"]
+OUT = "fixtures/kirbi/"
+
+
+def enc_krb_cred_part():
+    tgt = KrbCredInfo()
+    tgt["key"]["keytype"] = 18
+    tgt["key"]["keyvalue"] = opaque("kirbi tgt key", 32)
+    tgt["prealm"] = REALM
+    principal(tgt["pname"], 1, ["user"])
+    tgt["flags"] = flags(0x40e10000)
+    tgt["authtime"] = "20231114221320Z"
+    tgt["starttime"] = "20231114221320Z"
+    tgt["endtime"] = "20231115081320Z"
+    tgt["renew-till"] = "20231121221320Z"
+    tgt["srealm"] = REALM
+    principal(tgt["sname"], 2, KRBTGT)
+    service = KrbCredInfo()
+    service["key"]["keytype"] = 23
+    service["key"]["keyvalue"] = opaque("kirbi service key", 16)
+    service["prealm"] = REALM
+    principal(service["pname"], 1, ["user"])
+    p = EncKrbCredPart()
+    p["ticket-info"][0] = tgt
+    p["ticket-info"][1] = service
+    return p
+
+
+def main():
+    r = KRBCRED()
+    r["pvno"] = 5
+    r["msg-type"] = 22
+    ticket(r["tickets"][0], REALM, KRBTGT, 18, 2, opaque("kirbi tgt", 1100))
+    ticket(r["tickets"][1], REALM, CIFS, 23, 7, opaque("kirbi service ticket", 900))
+    encrypted_data(r["enc-part"], 0, None, encode(enc_krb_cred_part()))
+    der = encode(r)
+    with open(OUT + "user.kirbi", "wb") as f:
+        f.write(der)
+    with open(OUT + "user.kirbi.b64", "wb") as f:
+        f.write(base64.b64encode(der))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,66 @@
+"""Known answers for src/crypto/rc4.rs, following RFC 4757 section 5 (with
+the errata: usage 9 is not mapped to 8) and section 4 for the checksum.
+
+The base key is NTOWFv1("Password") from [MS-NLMP] section 4.2.2.1.1.
+Standard library only: python3 fixtures/gen/rc4_hmac.py
+"""
+import hashlib
+import hmac
+
+KEY = bytes.fromhex("a4f49c406510bdcab6824ee7c30fd852")
+PLAINTEXT = b"metaros known-answer test"
+CONFOUNDER = bytes(range(0xa0, 0xa8))
+
+
+def rc4(key, data):
+    s = list(range(256))
+    j = 0
+    for i in range(256):
+        j = (j + s[i] + key[i % len(key)]) & 0xff
+        s[i], s[j] = s[j], s[i]
+    i = j = 0
+    out = bytearray()
+    for b in data:
+        i = (i + 1) & 0xff
+        j = (j + s[i]) & 0xff
+        s[i], s[j] = s[j], s[i]
+        out.append(b ^ s[(s[i] + s[j]) & 0xff])
+    return bytes(out)
+
+
+def hmac_md5(key, data):
+    return hmac.new(key, data, hashlib.md5).digest()
+
+
+def translate(usage):
+    return {3: 8, 23: 13}.get(usage, usage)
+
+
+def message_keys(key, usage, export):
+    t = translate(usage).to_bytes(4, "little")
+    k2 = hmac_md5(key, b"fortybits\0" + t if export else t)
+    k1 = k2[:7] + b"\xab" * 9 if export else k2
+    return k1, k2
+
+
+def encrypt(key, usage, export, confounder, plaintext):
+    k1, k2 = message_keys(key, usage, export)
+    data = confounder + plaintext
+    checksum = hmac_md5(k2, data)
+    return checksum + rc4(hmac_md5(k1, checksum), data)
+
+
+def checksum(key, usage, data):
+    ksign = hmac_md5(key, b"signaturekey\0")
+    return hmac_md5(ksign, hashlib.md5(translate(usage).to_bytes(4, "little") + data).digest())
+
+
+if __name__ == "__main__":
+    for usage in (3, 8, 9):
+        print("message key, usage %d:" % usage, message_keys(KEY, usage, False)[0].hex())
+    for usage in (3, 23, 7):
+        k1, k2 = message_keys(KEY, usage, True)
+        print("export keys, usage %d:" % usage, k1.hex(), k2.hex())
+    print("checksum, usage 17:", checksum(KEY, 17, PLAINTEXT).hex())
+    print("encrypt, usage 9:", encrypt(KEY, 9, False, CONFOUNDER, PLAINTEXT).hex())
+    print("encrypt export, usage 3:", encrypt(KEY, 3, True, CONFOUNDER, PLAINTEXT).hex())
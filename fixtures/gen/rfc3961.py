@@ -0,0 +1,161 @@
+"""RFC 3961 building blocks shared by the generators in this directory.
+
+Ciphers come from the cryptography package (48.0.0); everything specific
+to Kerberos is written out here from the RFC text.
+"""
+import hashlib
+import hmac
+import zlib
+
+from cryptography.hazmat.decrepit.ciphers.algorithms import TripleDES
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+
+
+def nfold(data, nbytes):
+    """n-fold from RFC 3961 section 5.1, over whole bytes."""
+    def rotate_right(block, bits):
+        width = len(block) * 8
+        value = int.from_bytes(block, "big")
+        bits %= width
+        value = ((value >> bits) | (value << (width - bits))) & ((1 << width) - 1)
+        return value.to_bytes(len(block), "big")
+
+    a, b = len(data), nbytes
+    while b:
+        a, b = b, a % b
+    lcm = len(data) * nbytes // a
+    buf = b""
+    block = data
+    while len(buf) < lcm:
+        buf += block
+        block = rotate_right(block, 13)
+    total = 0
+    for i in range(0, lcm, nbytes):
+        total += int.from_bytes(buf[i:i + nbytes], "big")
+    mask = (1 << (nbytes * 8)) - 1
+    while total > mask:
+        total = (total & mask) + (total >> (nbytes * 8))
+    return total.to_bytes(nbytes, "big")
+
+
+def odd_parity(data):
+    """Sets the low bit of each byte so it has an odd number of ones."""
+    out = bytearray()
+    for b in data:
+        b &= 0xfe
+        out.append(b | (bin(b).count("1") % 2 == 0))
+    return bytes(out)
+
+
+def des_cbc(key, iv, data, decrypt=False):
+    """Single DES (as EDE with one key) or triple DES in CBC mode."""
+    if len(key) == 8:
+        key = key * 3
+    cipher = Cipher(TripleDES(key), modes.CBC(iv))
+    ctx = cipher.decryptor() if decrypt else cipher.encryptor()
+    return ctx.update(data) + ctx.finalize()
+
+
+def des_cbc_checksum(key, iv, data):
+    """The last block of the zero padded DES CBC encryption; the IV itself
+    for an empty message, as MIT's mit_des_cbc_cksum returns it."""
+    if not data:
+        return iv
+    return des_cbc(key, iv, data + bytes(-len(data) % 8))[-8:]
+
+
+def des3_random_to_key(bits):
+    """random-to-key for des3-cbc-sha1-kd, RFC 3961 section 6.3.1."""
+    key = b""
+    for i in range(0, 21, 7):
+        chunk = bits[i:i + 7]
+        last = 0
+        for j, b in enumerate(chunk):
+            last |= (b & 1) << (j + 1)
+        key += odd_parity(chunk + bytes([last]))
+    return key
+
+
+def des3_dk(key, constant):
+    """DK from RFC 3961 section 5.1 over triple DES."""
+    block = nfold(constant, 8)
+    out = b""
+    while len(out) < 21:
+        block = des_cbc(key, bytes(8), block)
+        out += block
+    return des3_random_to_key(out[:21])
+
+
+def aes_dk(key, constant):
+    """DK from RFC 3961 section 5.1 over AES, as RFC 3962 uses it."""
+    block = nfold(constant, 16)
+    out = b""
+    while len(out) < len(key):
+        encryptor = Cipher(algorithms.AES(key), modes.ECB()).encryptor()
+        block = encryptor.update(block) + encryptor.finalize()
+        out += block
+    return out[:len(key)]
+
+
+def crc32(data):
+    """The CRC-32 of RFC 3961 section 6.1.3: no initial or final inversion,
+    least significant byte first."""
+    return (zlib.crc32(data, 0xffffffff) ^ 0xffffffff).to_bytes(4, "little")
+
+
+def md4(data):
+    """MD4 from RFC 1320; OpenSSL 3 no longer gives hashlib one."""
+    def f(x, y, z): return (x & y) | (~x & z)
+    def g(x, y, z): return (x & y) | (x & z) | (y & z)
+    def h(x, y, z): return x ^ y ^ z
+
+    def rotl(v, s):
+        v &= 0xffffffff
+        return ((v << s) | (v >> (32 - s))) & 0xffffffff
+
+    message = data + b"\x80" + bytes(-(len(data) + 9) % 64) + (len(data) * 8).to_bytes(8, "little")
+    state = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476]
+    for offset in range(0, len(message), 64):
+        x = [int.from_bytes(message[offset + i:offset + i + 4], "little") for i in range(0, 64, 4)]
+        a, b, c, d = state
+        for i in range(16):
+            k, s = i, (3, 7, 11, 19)[i % 4]
+            a, b, c, d = d, rotl(a + f(b, c, d) + x[k], s), b, c
+        for i in range(16):
+            k, s = (i % 4) * 4 + i // 4, (3, 5, 9, 13)[i % 4]
+            a, b, c, d = d, rotl(a + g(b, c, d) + x[k] + 0x5a827999, s), b, c
+        for i in range(16):
+            k, s = (0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15)[i], (3, 9, 11, 15)[i % 4]
+            a, b, c, d = d, rotl(a + h(b, c, d) + x[k] + 0x6ed9eba1, s), b, c
+        state = [(v + w) & 0xffffffff for v, w in zip(state, (a, b, c, d))]
+    return b"".join(v.to_bytes(4, "little") for v in state)
+
+
+def des3_string_to_key(password, salt):
+    """RFC 3961 section 6.3.1."""
+    return des3_dk(des3_random_to_key(nfold(password + salt, 21)), b"kerberos")
+
+
+def aes_string_to_key(password, salt, size, iterations=4096):
+    """RFC 3962 section 4."""
+    tkey = hashlib.pbkdf2_hmac("sha1", password, salt, iterations, size)
+    return aes_dk(tkey, b"kerberos")
+
+
+def aes_cts_encrypt(key, data):
+    """AES CBC from a zero IV with the last two blocks swapped and the
+    final one truncated, RFC 3962 section 5."""
+    padded = data + bytes(-len(data) % 16)
+    encryptor = Cipher(algorithms.AES(key), modes.CBC(bytes(16))).encryptor()
+    out = encryptor.update(padded) + encryptor.finalize()
+    if len(out) > 16:
+        out = out[:-32] + out[-16:] + out[-32:-16]
+    return out[:len(data)]
+
+
+def aes_sha1_encrypt(key, usage, confounder, plaintext):
+    """aes*-cts-hmac-sha1-96 encryption, RFC 3962 with RFC 3961 section 5.3."""
+    ke = aes_dk(key, usage.to_bytes(4, "big") + b"\xaa")
+    ki = aes_dk(key, usage.to_bytes(4, "big") + b"\x55")
+    data = confounder + plaintext
+    return aes_cts_encrypt(ke, data) + hmac.new(ki, data, hashlib.sha1).digest()[:12]
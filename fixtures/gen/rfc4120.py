@@ -0,0 +1,297 @@
+"""The parts of the RFC 4120 ASN.1 module (section 5 and appendix A) that
+the fixtures use, for pyasn1 0.6.3, plus a source of filler bytes.
+
+Only DER encoding is used, so every type is given the tags the module
+assigns it and nothing more.
+"""
+import hashlib
+
+from pyasn1.codec.der import encoder
+from pyasn1.type import char, namedtype, tag, univ, useful
+
+def _context(n):
+    return tag.Tag(tag.tagClassContext, tag.tagFormatConstructed, n)
+
+
+def _application(n):
+    return univ.Sequence.tagSet.tagExplicitly(tag.Tag(tag.tagClassApplication, tag.tagFormatConstructed, n))
+
+
+def field(name, kind, n):
+    return namedtype.NamedType(name, kind.subtype(explicitTag=_context(n)))
+
+
+def optional(name, kind, n):
+    return namedtype.OptionalNamedType(name, kind.subtype(explicitTag=_context(n)))
+
+
+class KerberosString(char.GeneralString):
+    pass
+
+
+class KerberosTime(useful.GeneralizedTime):
+    pass
+
+
+class Int32(univ.Integer):
+    pass
+
+
+class KerberosFlags(univ.BitString):
+    pass
+
+
+class PrincipalName(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("name-type", Int32(), 0),
+        field("name-string", univ.SequenceOf(componentType=KerberosString()), 1))
+
+
+class EncryptedData(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("etype", Int32(), 0),
+        optional("kvno", univ.Integer(), 1),
+        field("cipher", univ.OctetString(), 2))
+
+
+class EncryptionKey(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("keytype", Int32(), 0),
+        field("keyvalue", univ.OctetString(), 1))
+
+
+class HostAddress(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("addr-type", Int32(), 0),
+        field("address", univ.OctetString(), 1))
+
+
+class HostAddresses(univ.SequenceOf):
+    componentType = HostAddress()
+
+
+class ADEntry(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("ad-type", Int32(), 0),
+        field("ad-data", univ.OctetString(), 1))
+
+
+class AuthorizationData(univ.SequenceOf):
+    componentType = ADEntry()
+
+
+class Ticket(univ.Sequence):
+    tagSet = _application(1)
+    componentType = namedtype.NamedTypes(
+        field("tkt-vno", univ.Integer(), 0),
+        field("realm", KerberosString(), 1),
+        field("sname", PrincipalName(), 2),
+        field("enc-part", EncryptedData(), 3))
+
+
+class Tickets(univ.SequenceOf):
+    componentType = Ticket()
+
+
+class TransitedEncoding(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("tr-type", Int32(), 0),
+        field("contents", univ.OctetString(), 1))
+
+
+class EncTicketPart(univ.Sequence):
+    tagSet = _application(3)
+    componentType = namedtype.NamedTypes(
+        field("flags", KerberosFlags(), 0),
+        field("key", EncryptionKey(), 1),
+        field("crealm", KerberosString(), 2),
+        field("cname", PrincipalName(), 3),
+        field("transited", TransitedEncoding(), 4),
+        field("authtime", KerberosTime(), 5),
+        optional("starttime", KerberosTime(), 6),
+        field("endtime", KerberosTime(), 7),
+        optional("renew-till", KerberosTime(), 8),
+        optional("caddr", HostAddresses(), 9),
+        optional("authorization-data", AuthorizationData(), 10))
+
+
+class PAData(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("padata-type", Int32(), 1),
+        field("padata-value", univ.OctetString(), 2))
+
+
+class PADatas(univ.SequenceOf):
+    componentType = PAData()
+
+
+class KDCReqBody(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("kdc-options", KerberosFlags(), 0),
+        optional("cname", PrincipalName(), 1),
+        field("realm", KerberosString(), 2),
+        optional("sname", PrincipalName(), 3),
+        optional("from", KerberosTime(), 4),
+        field("till", KerberosTime(), 5),
+        optional("rtime", KerberosTime(), 6),
+        field("nonce", univ.Integer(), 7),
+        field("etype", univ.SequenceOf(componentType=Int32()), 8),
+        optional("addresses", HostAddresses(), 9),
+        optional("enc-authorization-data", EncryptedData(), 10),
+        optional("additional-tickets", Tickets(), 11))
+
+
+def _kdc_req(n):
+    class KDCReq(univ.Sequence):
+        tagSet = _application(n)
+        componentType = namedtype.NamedTypes(
+            field("pvno", univ.Integer(), 1),
+            field("msg-type", univ.Integer(), 2),
+            optional("padata", PADatas(), 3),
+            field("req-body", KDCReqBody(), 4))
+    return KDCReq
+
+
+def _kdc_rep(n):
+    class KDCRep(univ.Sequence):
+        tagSet = _application(n)
+        componentType = namedtype.NamedTypes(
+            field("pvno", univ.Integer(), 0),
+            field("msg-type", univ.Integer(), 1),
+            optional("padata", PADatas(), 2),
+            field("crealm", KerberosString(), 3),
+            field("cname", PrincipalName(), 4),
+            field("ticket", Ticket(), 5),
+            field("enc-part", EncryptedData(), 6))
+    return KDCRep
+
+
+ASREQ, TGSREQ, ASREP, TGSREP = _kdc_req(10), _kdc_req(12), _kdc_rep(11), _kdc_rep(13)
+
+
+class APREQ(univ.Sequence):
+    tagSet = _application(14)
+    componentType = namedtype.NamedTypes(
+        field("pvno", univ.Integer(), 0),
+        field("msg-type", univ.Integer(), 1),
+        field("ap-options", KerberosFlags(), 2),
+        field("ticket", Ticket(), 3),
+        field("authenticator", EncryptedData(), 4))
+
+
+class APREP(univ.Sequence):
+    tagSet = _application(15)
+    componentType = namedtype.NamedTypes(
+        field("pvno", univ.Integer(), 0),
+        field("msg-type", univ.Integer(), 1),
+        field("enc-part", EncryptedData(), 2))
+
+
+class KRBERROR(univ.Sequence):
+    tagSet = _application(30)
+    componentType = namedtype.NamedTypes(
+        field("pvno", univ.Integer(), 0),
+        field("msg-type", univ.Integer(), 1),
+        optional("ctime", KerberosTime(), 2),
+        optional("cusec", univ.Integer(), 3),
+        field("stime", KerberosTime(), 4),
+        field("susec", univ.Integer(), 5),
+        field("error-code", Int32(), 6),
+        optional("crealm", KerberosString(), 7),
+        optional("cname", PrincipalName(), 8),
+        field("realm", KerberosString(), 9),
+        field("sname", PrincipalName(), 10),
+        optional("e-text", KerberosString(), 11),
+        optional("e-data", univ.OctetString(), 12))
+
+
+class KRBCRED(univ.Sequence):
+    tagSet = _application(22)
+    componentType = namedtype.NamedTypes(
+        field("pvno", univ.Integer(), 0),
+        field("msg-type", univ.Integer(), 1),
+        field("tickets", Tickets(), 2),
+        field("enc-part", EncryptedData(), 3))
+
+
+class KrbCredInfo(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("key", EncryptionKey(), 0),
+        optional("prealm", KerberosString(), 1),
+        optional("pname", PrincipalName(), 2),
+        optional("flags", KerberosFlags(), 3),
+        optional("authtime", KerberosTime(), 4),
+        optional("starttime", KerberosTime(), 5),
+        optional("endtime", KerberosTime(), 6),
+        optional("renew-till", KerberosTime(), 7),
+        optional("srealm", KerberosString(), 8),
+        optional("sname", PrincipalName(), 9),
+        optional("caddr", HostAddresses(), 10))
+
+
+class EncKrbCredPart(univ.Sequence):
+    tagSet = _application(29)
+    componentType = namedtype.NamedTypes(
+        field("ticket-info", univ.SequenceOf(componentType=KrbCredInfo()), 0),
+        optional("nonce", univ.Integer(), 1),
+        optional("timestamp", KerberosTime(), 2),
+        optional("usec", univ.Integer(), 3),
+        optional("s-address", HostAddress(), 4),
+        optional("r-address", HostAddress(), 5))
+
+
+class EtypeInfo2Entry(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("etype", Int32(), 0),
+        optional("salt", KerberosString(), 1),
+        optional("s2kparams", univ.OctetString(), 2))
+
+
+class PaEncTsEnc(univ.Sequence):
+    componentType = namedtype.NamedTypes(
+        field("patimestamp", KerberosTime(), 0),
+        optional("pausec", univ.Integer(), 1))
+
+
+class PacReq(univ.Sequence):
+    # [MS-KILE] 2.2.3 KERB-PA-PAC-REQUEST
+    componentType = namedtype.NamedTypes(field("include-pac", univ.Boolean(), 0))
+
+
+def encode(value):
+    return encoder.encode(value)
+
+
+def opaque(label, n):
+    """Filler for ciphertexts and keys the tests never decrypt: SHA-256 in
+    counter mode over the label, so every run writes the same bytes."""
+    out = b""
+    counter = 0
+    while len(out) < n:
+        out += hashlib.sha256(label.encode() + counter.to_bytes(4, "big")).digest()
+        counter += 1
+    return out[:n]
+
+
+def flags(value):
+    return "'%08X'H" % value
+
+
+def principal(p, name_type, components):
+    p["name-type"] = name_type
+    for i, c in enumerate(components):
+        p["name-string"][i] = c
+
+
+def encrypted_data(e, etype, kvno, cipher):
+    e["etype"] = etype
+    if kvno is not None:
+        e["kvno"] = kvno
+    e["cipher"] = cipher
+
+
+def ticket(t, realm, sname, etype, kvno, cipher):
+    t["tkt-vno"] = 5
+    t["realm"] = realm
+    principal(t["sname"], 2, sname)
+    encrypted_data(t["enc-part"], etype, kvno, cipher)
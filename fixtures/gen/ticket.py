@@ -0,0 +1,99 @@
+"""The service keytab and tickets in fixtures/ticket, for the decryption
+tests in src/kerberos/ticket.rs.
+
+http.keytab holds the aes256, rc4 and des3 keys of HTTP/web.example.com
+at kvno 3 from the password "Secret123" and the MIT salt, written in the
+0x0502 keytab format with the 32-bit kvno trailer as MIT writes it. Both
+tickets carry enc-ticket-part.der: ap-req.hex is an AP-REQ whose ticket
+is encrypted as aes256 at kvno 3, and rc4.ticket a Ticket encrypted as
+rc4-hmac without a kvno. The encryptions use key usage 2 and follow RFC
+3962 and RFC 4757.
+
+python3 fixtures/gen/ticket.py
+"""
+import struct
+
+from rc4_hmac import encrypt as rc4_encrypt
+from rfc3961 import aes_sha1_encrypt, aes_string_to_key, des3_string_to_key, md4
+from rfc4120 import (ADEntry, APREQ, EncTicketPart, Ticket, encode, encrypted_data, flags,
+                     opaque, principal, ticket)
+
+REALM = "EXAMPLE.COM"
+SERVICE = ["HTTP", "web.example.com"]
+PASSWORD = b"Secret123"
+SALT = (REALM + "".join(SERVICE)).encode()
+KVNO = 3
+TIMESTAMP = 1700000000
+OUT = "fixtures/ticket/"
+
+KEYS = [
+    (18, aes_string_to_key(PASSWORD, SALT, 32)),
+    # the NT hash: MD4 over the UTF-16LE password
+    (23, md4(PASSWORD.decode().encode("utf-16-le"))),
+    (16, des3_string_to_key(PASSWORD, SALT)),
+]
+
+
+def counted(data):
+    return struct.pack(">H", len(data)) + data
+
+
+def keytab():
+    out = struct.pack(">H", 0x0502)
+    for etype, key in KEYS:
+        entry = struct.pack(">H", len(SERVICE)) + counted(REALM.encode())
+        entry += b"".join(counted(c.encode()) for c in SERVICE)
+        entry += struct.pack(">IIBH", 2, TIMESTAMP, KVNO, etype) + counted(key)
+        entry += struct.pack(">I", KVNO)
+        out += struct.pack(">i", len(entry)) + entry
+    return out
+
+
+def enc_ticket_part():
+    p = EncTicketPart()
+    p["flags"] = flags(0x40a50000)
+    p["key"]["keytype"] = 18
+    p["key"]["keyvalue"] = opaque("ticket session key", 32)
+    p["crealm"] = REALM
+    principal(p["cname"], 1, ["user"])
+    p["transited"]["tr-type"] = 1
+    p["transited"]["contents"] = b""
+    p["authtime"] = "20231114221320Z"
+    p["starttime"] = "20231114221320Z"
+    p["endtime"] = "20231115081320Z"
+    p["renew-till"] = "20231121221320Z"
+    ad = ADEntry()
+    ad["ad-type"] = 1
+    ad["ad-data"] = opaque("ticket authorization data", 64)
+    p["authorization-data"][0] = ad
+    return encode(p)
+
+
+def main():
+    keys = dict(KEYS)
+    part = enc_ticket_part()
+    ap = APREQ()
+    ap["pvno"] = 5
+    ap["msg-type"] = 14
+    ap["ap-options"] = flags(0x20000000)
+    ticket(ap["ticket"], REALM, SERVICE, 18, KVNO,
+           aes_sha1_encrypt(keys[18], 2, opaque("ticket aes256 confounder", 16), part))
+    encrypted_data(ap["authenticator"], 18, None, opaque("ticket authenticator", 120))
+    ap_req = encode(ap).hex()
+
+    rc4 = Ticket()
+    ticket(rc4, REALM, SERVICE, 23, None,
+           rc4_encrypt(keys[23], 2, False, opaque("ticket rc4 confounder", 8), part))
+
+    with open(OUT + "http.keytab", "wb") as f:
+        f.write(keytab())
+    with open(OUT + "enc-ticket-part.der", "wb") as f:
+        f.write(part)
+    with open(OUT + "ap-req.hex", "w") as f:
+        f.write("".join(ap_req[i:i + 64] + "\n" for i in range(0, len(ap_req), 64)))
+    with open(OUT + "rc4.ticket", "wb") as f:
+        f.write(encode(rc4))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,16 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use metaros::kerberos::keytab::{Keytab, KeytabEntry, KeytabVersion};
+
+// fuzz/corpus/keytab holds malformed keytabs that used to panic or hang
+// the parser, replay them as a regression check with:
+//   cargo fuzz run keytab -- -runs=0
+// `cargo test` also walks the corpus, see the tests in kerberos::keytab
+fuzz_target!(|data: &[u8]| {
+    // anything we can parse we must be able to write back out and parse again
+    if let Ok(keytab) = Keytab::from_bytes(data) {
+        Keytab::from_bytes(&keytab.to_bytes()).unwrap();
+    }
+    let _ = KeytabEntry::from_bytes(data, KeytabVersion::V1);
+    let _ = KeytabEntry::from_bytes(data, KeytabVersion::V2);
+});
@@ -0,0 +1,375 @@
+use std::path::PathBuf;
+
+use clap::{ArgEnum, ArgGroup, AppSettings, Parser, Subcommand, Args};
+
+#[derive(Debug, Parser)]
+#[clap(author, version, about)]
+#[clap(global_setting(AppSettings::PropagateVersion))]
+#[clap(global_setting(AppSettings::UseLongFormatForHelpSubcommand))]
+#[clap(global_setting(AppSettings::DeriveDisplayOrder))]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Commands,
+    #[clap(short, long,
+        global(true), 
+        parse(from_occurrences))]
+    pub verbose: u64,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    #[clap(subcommand)]
+    /// For working with Keytabs
+    Keytab (KeytabCommands),
+    #[clap(subcommand)]
+    /// For working with keys & hashes
+    Key (KeyCommands),
+    #[clap(subcommand)]
+    /// For working with Kerberos checksums
+    Checksum (ChecksumCommands),
+    #[clap(subcommand)]
+    /// For working with credential caches
+    Ccache (CcacheCommands),
+    #[clap(subcommand)]
+    /// For working with Kerberos tickets
+    Ticket (TicketCommands),
+}
+
+
+#[derive(Debug, Subcommand)]
+pub enum KeytabCommands {
+    /// Create Keytab file from principal name and key (AES/NTLM/*)
+    Create (KeytabCreate),
+    /// Display parsed contents of a Keytab file
+    Read (KeytabRead),
+    /// Remove the holes left by deleted entries from a Keytab file
+    Compact (KeytabCompact),
+    /// Merge Keytab files, skipping duplicate principal/kvno/etype entries
+    Merge (KeytabMerge),
+    /// Remove the entries matching a principal, kvno and/or etype
+    Remove (KeytabRemove),
+    /// Rename the entries of a principal
+    Rename (KeytabRename),
+    /// Set the kvno of the matching entries
+    SetKvno (KeytabSetKvno),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CcacheCommands {
+    /// Display parsed contents of a FILE credential cache
+    Read (CcacheRead),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TicketCommands {
+    /// Convert KRB-CRED (.kirbi, raw or base64) to a credential cache and back
+    Convert (TicketConvert),
+    /// Decrypt captured tickets (ccache, KRB-CRED or AP-REQ hex) with a service keytab
+    Decrypt (TicketDecrypt),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeyCommands {
+    /// Derive keys and hashes used in Windows authentication
+    Derive (KeyDerive),
+    /// Derive the checksum (Kc), encryption (Ke) and integrity (Ki) keys for a key usage
+    Usage (KeyUsage),
+}
+
+
+#[derive(Debug, Subcommand)]
+pub enum ChecksumCommands {
+    /// Compute a checksum over some data
+    Compute (ChecksumCompute),
+    /// Check a checksum against some data
+    Verify (ChecksumVerify),
+}
+
+
+#[derive(Debug, Args)]
+pub struct KeytabCreate {
+    #[clap(
+        short, long,
+        required_unless_present("infile"))]
+    pub principal: Option<String>,
+
+    #[clap(
+        short, long,
+        required_unless_present_any(&["infile", "password"]))]
+    pub etype: Option<String>,
+
+    #[clap(
+        short, long,
+        required_unless_present_any(&["infile", "password"]))]
+    pub key: Option<String>,
+
+    #[clap(
+        long,
+        conflicts_with_all(&["key", "infile"]))]
+    pub password: Option<String>,
+
+    #[clap(
+        long,
+        requires("password"),
+        conflicts_with("etype"))]
+    pub des: bool,
+
+    #[clap(
+        short, long,
+        requires("password"))]
+    pub salt: Option<String>,
+
+    #[clap(arg_enum, long, default_value = "mit")]
+    pub salt_mode: SaltModes,
+
+    #[clap(
+        short, long,
+        required_unless_present("infile"))]
+    pub name_type: Option<String>,
+
+    #[clap(short, long)]
+    pub timestamp: Option<u32>,
+
+    #[clap(long)]
+    pub version: Option<u32>,
+
+    #[clap(
+        short, long,
+        parse(from_os_str)
+    )]
+    pub infile: Option<PathBuf>,
+
+    #[clap(
+        short, long,
+        parse(from_os_str)
+    )]
+    pub outfile: PathBuf,
+
+    #[clap(arg_enum, long, default_value = "v2")]
+    pub format_version: KeytabFormat,
+}
+
+
+#[derive(Debug, Args)]
+pub struct KeytabRead {
+    #[clap(short, long, parse(from_os_str))]
+    pub infile: PathBuf,
+
+    /// csv output feeds `keytab create --infile`, which keeps the principal, etype, key, name
+    /// type, timestamp and kvno of each entry. It does not keep the file format version (pass
+    /// --format-version again), holes, entry padding, or whether the kvno was stored in 8 or 32
+    /// bits, so the rewritten file is not byte-identical to the original
+    #[clap(arg_enum, short, long, default_value = "text")]
+    pub format: ReadFormat,
+}
+
+
+#[derive(Debug, Args)]
+pub struct CcacheRead {
+    #[clap(short, long, parse(from_os_str))]
+    pub infile: PathBuf,
+
+    #[clap(arg_enum, short, long, default_value = "text")]
+    pub format: ReadFormat,
+}
+
+
+#[derive(Debug, Args)]
+pub struct TicketConvert {
+    #[clap(short, long, parse(from_os_str))]
+    pub infile: PathBuf,
+
+    #[clap(short, long, parse(from_os_str))]
+    pub outfile: PathBuf,
+
+    /// Write a KRB-CRED as base64 rather than DER
+    #[clap(long)]
+    pub base64: bool,
+}
+
+
+#[derive(Debug, Args)]
+pub struct TicketDecrypt {
+    #[clap(short, long, parse(from_os_str))]
+    pub infile: PathBuf,
+
+    #[clap(short, long, parse(from_os_str))]
+    pub keytab: PathBuf,
+}
+
+
+#[derive(Debug, Args)]
+pub struct KeytabCompact {
+    #[clap(short, long, parse(from_os_str))]
+    pub infile: PathBuf,
+
+    #[clap(short, long, parse(from_os_str))]
+    pub outfile: PathBuf,
+}
+
+
+#[derive(Debug, Args)]
+pub struct KeytabMerge {
+    #[clap(required = true, min_values = 2, parse(from_os_str))]
+    pub infiles: Vec<PathBuf>,
+
+    #[clap(short, long, parse(from_os_str))]
+    pub outfile: PathBuf,
+}
+
+
+#[derive(Debug, Args)]
+pub struct EntrySelect {
+    #[clap(short, long)]
+    pub principal: Option<String>,
+
+    #[clap(short, long)]
+    pub kvno: Option<u32>,
+
+    #[clap(short, long)]
+    pub etype: Option<String>,
+}
+
+
+#[derive(Debug, Args)]
+#[clap(group(ArgGroup::new("select").args(&["principal", "kvno", "etype"]).required(true).multiple(true)))]
+pub struct KeytabRemove {
+    #[clap(short, long, parse(from_os_str))]
+    pub infile: PathBuf,
+
+    #[clap(short, long, parse(from_os_str))]
+    pub outfile: PathBuf,
+
+    #[clap(flatten)]
+    pub select: EntrySelect,
+}
+
+
+#[derive(Debug, Args)]
+pub struct KeytabRename {
+    #[clap(short, long, parse(from_os_str))]
+    pub infile: PathBuf,
+
+    #[clap(short, long, parse(from_os_str))]
+    pub outfile: PathBuf,
+
+    #[clap(short, long)]
+    pub principal: String,
+
+    #[clap(long)]
+    pub to: String,
+}
+
+
+#[derive(Debug, Args)]
+pub struct KeytabSetKvno {
+    #[clap(short, long, parse(from_os_str))]
+    pub infile: PathBuf,
+
+    #[clap(short, long, parse(from_os_str))]
+    pub outfile: PathBuf,
+
+    #[clap(flatten)]
+    pub select: EntrySelect,
+
+    #[clap(long)]
+    pub to: u32,
+}
+
+
+#[derive(Debug, Args)]
+#[clap(group(ArgGroup::new("salt_source").args(&["salt", "principal"])))]
+pub struct KeyDerive {
+    /// Etype name or number, e.g. aes256 or 18, or one of the hashes ntlm and lm
+    #[clap(short, long, required_unless_present("all"))]
+    pub etype: Option<String>,
+    #[clap(short, long)]
+    pub salt: Option<String>,
+    #[clap(long)]
+    pub principal: Option<String>,
+    #[clap(arg_enum, long, default_value = "mit")]
+    pub salt_mode: SaltModes,
+    #[clap(short, long)]
+    pub password: String,
+    /// PBKDF2 iteration count for the AES and Camellia etypes [default: the etype's own]
+    #[clap(long)]
+    pub iterations: Option<u32>,
+    /// Use the AFS string-to-key for DES, salted with the principal's cell
+    #[clap(long, conflicts_with_all(&["all", "iterations"]))]
+    pub afs3: bool,
+    #[clap(short, long,
+        requires("salt_source")
+    )]
+    pub all: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct KeyUsage {
+    #[clap(short, long)]
+    pub etype: String,
+
+    /// Base key in hex
+    #[clap(short, long)]
+    pub key: String,
+
+    /// Key usage number or name, e.g. 2 or "ticket"
+    #[clap(short, long)]
+    pub usage: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ChecksumInput {
+    /// Checksum type name or number, e.g. hmac-sha1-96-aes256 or 16
+    #[clap(short = 't', long = "type")]
+    pub cksumtype: String,
+
+    /// Key in hex, for the keyed checksum types
+    #[clap(short, long, requires("usage"))]
+    pub key: Option<String>,
+
+    /// Key usage number or name, for the keyed checksum types
+    #[clap(short, long, requires("key"))]
+    pub usage: Option<String>,
+
+    /// Data in hex
+    #[clap(short, long)]
+    pub data: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ChecksumCompute {
+    #[clap(flatten)]
+    pub input: ChecksumInput,
+}
+
+#[derive(Debug, Args)]
+pub struct ChecksumVerify {
+    #[clap(flatten)]
+    pub input: ChecksumInput,
+
+    /// Expected checksum in hex
+    #[clap(short, long)]
+    pub checksum: String,
+}
+
+#[derive(Debug, ArgEnum, Clone)]
+pub enum SaltModes {
+    Mit,
+    AdUser,
+    AdComputer,
+}
+
+#[derive(Debug, ArgEnum, Clone)]
+pub enum ReadFormat {
+    Text,
+    Json,
+    Csv,
+    Table,
+    Klist,
+}
+
+#[derive(Debug, ArgEnum, Clone)]
+pub enum KeytabFormat {
+    V1,
+    V2,
+}
@@ -1,5 +1,17 @@
+#[allow(clippy::module_inception)]
 pub mod crypto;
 pub mod lanman;
 pub mod aes;
 pub mod des;
 pub mod rc4;
+pub mod camellia;
+pub mod etype;
+pub mod derive;
+pub mod checksum;
+mod cts;
+pub mod nfold;
+
+pub use self::crypto::{KrbCipher, UsageKeys, WinHash};
+pub use self::etype::KrbEtype;
+pub use self::checksum::KrbChecksum;
+pub use self::nfold::nfold;
@@ -1,108 +1,347 @@
-use bitvec::prelude::*;
 use std::str;
 //use crate::utils::des::Etype;
 
 use pbkdf2::pbkdf2;
-use hmac::Hmac;
+use hmac::{Hmac, Mac};
 use sha1::Sha1;
+use sha2::{Sha256, Sha384};
+use rand::RngCore;
 use aes::{Aes128, Aes256};
-use block_modes::{BlockMode, Cbc};
-use block_modes::block_padding::NoPadding;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, NewBlockCipher};
+use aes::cipher::generic_array::GenericArray;
 use crate::crypto::crypto::*;
+use crate::crypto::cts::{self, BlockCipher, BLOCK_SIZE};
+use crate::crypto::derive;
 use crate::utils::utils::*;
+use crate::error::{Error, Result};
 
-type Aes256Cbc = Cbc<Aes256, NoPadding>;
-type Aes128Cbc = Cbc<Aes128, NoPadding>;
+// HMAC-SHA1 truncated to 96 bits
+const HMAC_SIZE: usize = 12;
+
+// RFC 3962 section 4: the PBKDF2 iteration count when the KDC sends no
+// s2kparams
+pub const AES_DEFAULT_ITERATIONS: u32 = 4096;
 
 
 pub struct KrbAes;
 
 impl KrbAes {
-    pub fn pbkdf2_from_bytes(key: &[u8], salt: &[u8], keysize: usize) -> Vec<u8> {
+    pub fn pbkdf2_from_bytes(key: &[u8], salt: &[u8], keysize: usize, iterations: u32) -> Vec<u8> {
         let mut out = vec![0u8; keysize / 8];
-        pbkdf2::<Hmac<Sha1>>(&key, &salt, 1, &mut out);
-        return out
+        pbkdf2::<Hmac<Sha1>>(key, salt, iterations, &mut out);
+        out
     }
 
-    fn nfold(constant: &str, keysize: usize) -> Vec<u8> {
-        let mut constant = BitVec::<_, Msb0>::from_vec(constant.as_bytes().to_vec());
-        let mut offset = 13;
-        if offset > constant.len() {
-            offset = offset % &constant.len();
+    fn check_key(key: &[u8], keysize: usize) -> Result<()> {
+        if key.len() != keysize {
+            return Err(Error::BadKeyLength { expected: keysize, actual: key.len() });
         }
-    
-        let mut long_constant = constant.clone();
-        for _ in 1..(lcm(&keysize, &constant.len()) / &constant.len()) {
-            constant.rotate_right(offset);
-            long_constant.extend(constant.clone());
-        };
-    
-        let mut keybits = long_constant[0..keysize].to_bitvec();
-        for i in 1 ..(long_constant.len()/keysize) {
-            keybits = KrbAes::add_chunks_ones_comp(
-                &keybits,
-                &long_constant[i * keysize..((i + 1) * keysize)],
-                &keysize);
+        Ok(())
+    }
+
+    // DK from RFC 3961. AES keys are random bytes, so random-to-key is the
+    // identity and DK is DR.
+    fn dk(key: &[u8], keysize: usize, constant: &[u8]) -> Result<Vec<u8>> {
+        Ok(derive::dr(&AesCipher::new(key, keysize)?, constant, keysize))
+    }
+
+    fn usage_key(key: &[u8], keysize: usize, usage: u32, kind: u8) -> Result<Vec<u8>> {
+        Ok(derive::derive_random(&AesCipher::new(key, keysize)?, usage, kind, keysize))
+    }
+
+    fn usage_keys(key: &[u8], keysize: usize, usage: u32) -> Result<UsageKeys> {
+        KrbAes::check_key(key, keysize)?;
+        Ok(UsageKeys {
+            kc: KrbAes::usage_key(key, keysize, usage, 0x99)?,
+            ke: KrbAes::usage_key(key, keysize, usage, 0xAA)?,
+            ki: KrbAes::usage_key(key, keysize, usage, 0x55)?,
+        })
+    }
+
+    fn checksum(key: &[u8], keysize: usize, usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::check_key(key, keysize)?;
+        let kc = KrbAes::usage_key(key, keysize, usage, 0x99)?;
+        let mut mac = Hmac::<Sha1>::new_from_slice(&kc)
+            .map_err(|_| Error::BadKeyLength { expected: keysize, actual: kc.len() })?;
+        mac.update(data);
+        Ok(mac.finalize().into_bytes()[..HMAC_SIZE].to_vec())
+    }
+
+    fn verify_checksum(key: &[u8], keysize: usize, usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        if !constant_time_eq(&KrbAes::checksum(key, keysize, usage, data)?, checksum) {
+            return Err(Error::IntegrityCheckFailed);
         }
-        return keybits.to_bitvec().into_vec();
-    }
-
-    
-    fn add_chunks_ones_comp(first: &BitSlice<u8, Msb0>, second: &BitSlice<u8, Msb0>, keysize: &usize) -> BitVec::<u8, Msb0> {
-        let mut result = first.clone().to_bitvec();
-        let mut carry = false;
-        let mut set;
-        for b in (0 ..*keysize).rev(){
-            if result[b] & second[b] {
-                set = carry;
-                carry = true;
-            } else if result[b] ^ second[b] {
-                set = !carry;
-            } else {
-                set = carry;
-                carry = false;
-            }
-            result.set(b, set);
-    
+        Ok(())
+    }
+
+    fn encrypt(key: &[u8], keysize: usize, usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut confounder = [0u8; BLOCK_SIZE];
+        rand::thread_rng().fill_bytes(&mut confounder);
+        KrbAes::encrypt_with_confounder(key, keysize, usage, &confounder, plaintext)
+    }
+
+    fn encrypt_with_confounder(key: &[u8], keysize: usize, usage: u32,
+            confounder: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::check_key(key, keysize)?;
+        let ke = KrbAes::usage_key(key, keysize, usage, 0xAA)?;
+        let ki = KrbAes::usage_key(key, keysize, usage, 0x55)?;
+        let mut data = confounder.to_vec();
+        data.extend(plaintext);
+        let mut ciphertext = cts::encrypt(&AesCipher::new(&ke, keysize)?, &data);
+        let mut mac = Hmac::<Sha1>::new_from_slice(&ki)
+            .map_err(|_| Error::BadKeyLength { expected: keysize, actual: ki.len() })?;
+        mac.update(&data);
+        ciphertext.extend(&mac.finalize().into_bytes()[..HMAC_SIZE]);
+        Ok(ciphertext)
+    }
+
+    fn decrypt(key: &[u8], keysize: usize, usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::check_key(key, keysize)?;
+        if ciphertext.len() < BLOCK_SIZE + HMAC_SIZE {
+            return Err(Error::BadCiphertext(format!(
+                "{} bytes cannot hold a confounder and checksum", ciphertext.len())));
         }
-        if carry {
-            let mut carrybits = bitvec![u8, Msb0; 0; *keysize];
-            carrybits.set(*keysize -1, carry);
-            result = KrbAes::add_chunks_ones_comp(&result, &carrybits, keysize);
+        let ke = KrbAes::usage_key(key, keysize, usage, 0xAA)?;
+        let ki = KrbAes::usage_key(key, keysize, usage, 0x55)?;
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - HMAC_SIZE);
+        let data = cts::decrypt(&AesCipher::new(&ke, keysize)?, ciphertext);
+        let mut mac = Hmac::<Sha1>::new_from_slice(&ki)
+            .map_err(|_| Error::BadKeyLength { expected: keysize, actual: ki.len() })?;
+        mac.update(&data);
+        mac.verify_truncated_left(tag)
+            .map_err(|_| Error::IntegrityCheckFailed)?;
+        Ok(data[BLOCK_SIZE..].to_vec())
+    }
+}
+
+
+// The RFC 8009 profile: PBKDF2 and a counter mode KDF over HMAC-SHA2 instead
+// of n-fold, and an encrypt-then-MAC tag computed over the IV and ciphertext.
+struct AesSha2 {
+    name: &'static str,
+    keysize: usize,
+    hash: Sha2,
+    // truncated HMAC length, also the Kc and Ki size
+    macsize: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Sha2 {
+    Sha256,
+    Sha384,
+}
+
+const AES128_SHA256: AesSha2 = AesSha2 {
+    name: "aes128-cts-hmac-sha256-128",
+    keysize: 16,
+    hash: Sha2::Sha256,
+    macsize: 16,
+};
+
+const AES256_SHA384: AesSha2 = AesSha2 {
+    name: "aes256-cts-hmac-sha384-192",
+    keysize: 32,
+    hash: Sha2::Sha384,
+    macsize: 24,
+};
+
+// RFC 8009 section 4
+pub const AES_SHA2_DEFAULT_ITERATIONS: u32 = 32768;
+
+impl AesSha2 {
+    fn hmac(&self, key: &[u8], data: &[&[u8]]) -> Result<Vec<u8>> {
+        let bad_key = |_| Error::BadKeyLength { expected: self.keysize, actual: key.len() };
+        Ok(match self.hash {
+            Sha2::Sha256 => {
+                let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).map_err(bad_key)?;
+                data.iter().for_each(|d| mac.update(d));
+                mac.finalize().into_bytes().to_vec()
+            },
+            Sha2::Sha384 => {
+                let mut mac = <Hmac<Sha384> as Mac>::new_from_slice(key).map_err(bad_key)?;
+                data.iter().for_each(|d| mac.update(d));
+                mac.finalize().into_bytes().to_vec()
+            },
+        })
+    }
+
+    fn bytes_to_key(&self, secret: &[u8], salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+        // the salt is prefixed with the etype name and a zero byte
+        let mut saltp = self.name.as_bytes().to_vec();
+        saltp.push(0);
+        saltp.extend(salt);
+        let mut tkey = vec![0u8; self.keysize];
+        match self.hash {
+            Sha2::Sha256 => pbkdf2::<Hmac<Sha256>>(secret, &saltp, iterations, &mut tkey),
+            Sha2::Sha384 => pbkdf2::<Hmac<Sha384>>(secret, &saltp, iterations, &mut tkey),
         }
-        return result;
+        info!("[{}] pbkdf2: {}", self.name, hex::encode_upper(&tkey));
+        self.kdf(&tkey, b"kerberos", self.keysize)
+    }
+
+    // KDF-HMAC-SHA2 with a single counter block, which covers every key
+    // these etypes need.
+    fn kdf(&self, key: &[u8], label: &[u8], size: usize) -> Result<Vec<u8>> {
+        let bits = (size as u32 * 8).to_be_bytes();
+        let mut out = self.hmac(key, &[&1u32.to_be_bytes(), label, &[0], &bits])?;
+        out.truncate(size);
+        Ok(out)
+    }
+
+    fn usage_key(&self, key: &[u8], usage: u32, kind: u8, size: usize) -> Result<Vec<u8>> {
+        let mut label = usage.to_be_bytes().to_vec();
+        label.push(kind);
+        self.kdf(key, &label, size)
+    }
+
+    // Kc and Ki are cut to the MAC length, Ke is a full key
+    fn usage_keys(&self, key: &[u8], usage: u32) -> Result<UsageKeys> {
+        KrbAes::check_key(key, self.keysize)?;
+        Ok(UsageKeys {
+            kc: self.usage_key(key, usage, 0x99, self.macsize)?,
+            ke: self.usage_key(key, usage, 0xAA, self.keysize)?,
+            ki: self.usage_key(key, usage, 0x55, self.macsize)?,
+        })
+    }
+
+    fn encrypt(&self, key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut confounder = [0u8; BLOCK_SIZE];
+        rand::thread_rng().fill_bytes(&mut confounder);
+        self.encrypt_with_confounder(key, usage, &confounder, plaintext)
+    }
+
+    fn encrypt_with_confounder(&self, key: &[u8], usage: u32,
+            confounder: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::check_key(key, self.keysize)?;
+        let ke = self.usage_key(key, usage, 0xAA, self.keysize)?;
+        let ki = self.usage_key(key, usage, 0x55, self.macsize)?;
+        let mut data = confounder.to_vec();
+        data.extend(plaintext);
+        let mut ciphertext = cts::encrypt(&AesCipher::new(&ke, self.keysize)?, &data);
+        let mut tag = self.hmac(&ki, &[&[0; BLOCK_SIZE], &ciphertext])?;
+        tag.truncate(self.macsize);
+        ciphertext.extend(tag);
+        Ok(ciphertext)
     }
 
+    fn decrypt(&self, key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::check_key(key, self.keysize)?;
+        if ciphertext.len() < BLOCK_SIZE + self.macsize {
+            return Err(Error::BadCiphertext(format!(
+                "{} bytes cannot hold a confounder and checksum", ciphertext.len())));
+        }
+        let ke = self.usage_key(key, usage, 0xAA, self.keysize)?;
+        let ki = self.usage_key(key, usage, 0x55, self.macsize)?;
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - self.macsize);
+        let expected = self.hmac(&ki, &[&[0; BLOCK_SIZE], ciphertext])?;
+        if !constant_time_eq(&expected[..self.macsize], tag) {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        let data = cts::decrypt(&AesCipher::new(&ke, self.keysize)?, ciphertext);
+        Ok(data[BLOCK_SIZE..].to_vec())
+    }
+
+    fn checksum(&self, key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::check_key(key, self.keysize)?;
+        let kc = self.usage_key(key, usage, 0x99, self.macsize)?;
+        let mut checksum = self.hmac(&kc, &[data])?;
+        checksum.truncate(self.macsize);
+        Ok(checksum)
+    }
+
+    fn verify_checksum(&self, key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        if !constant_time_eq(&self.checksum(key, usage, data)?, checksum) {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        Ok(())
+    }
 }
 
 
 
-pub struct KrbAes128;
+enum AesCipher {
+    Aes128(Box<Aes128>),
+    Aes256(Box<Aes256>),
+}
 
-impl KrbEncType for KrbAes128 {
-    fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8>  {
-        let cipher = Aes128Cbc::new_from_slices(&key, &iv).unwrap();
-        let ciphertext = cipher.encrypt_vec(plaintext);
-        return ciphertext
+impl AesCipher {
+    // keysize is what the etype expects, so a bad key is reported against it
+    fn new(key: &[u8], keysize: usize) -> Result<AesCipher> {
+        match (key.len(), keysize) {
+            (16, 16) => Ok(AesCipher::Aes128(Box::new(Aes128::new(GenericArray::from_slice(key))))),
+            (32, 32) => Ok(AesCipher::Aes256(Box::new(Aes256::new(GenericArray::from_slice(key))))),
+            (n, _) => Err(Error::BadKeyLength { expected: keysize, actual: n }),
+        }
+    }
+}
+
+impl derive::DeriveCipher for AesCipher {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        match self {
+            AesCipher::Aes128(c) => derive::DeriveCipher::encrypt_block(c.as_ref(), block),
+            AesCipher::Aes256(c) => derive::DeriveCipher::encrypt_block(c.as_ref(), block),
+        }
     }
-    fn bytes_to_key(secret: &[u8], salt: &[u8]) -> Vec<u8> {
-        let tkey = KrbAes::pbkdf2_from_bytes(&secret, &salt, 128);
-        info!("[AES128] pbkdf2: {}", hex::encode_upper(&tkey));
-        let ikey = KrbAes128::dk("kerberos", 128, &tkey);
-        return ikey;
+}
+
+impl BlockCipher for AesCipher {
+    fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            AesCipher::Aes128(c) => c.encrypt_block(block),
+            AesCipher::Aes256(c) => c.encrypt_block(block),
+        }
     }
 
-    fn string_to_key(secret: &str, salt: &str) -> Vec<u8> {
-        KrbAes128::bytes_to_key(&secret.as_bytes(), &salt.as_bytes())
+    fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            AesCipher::Aes128(c) => c.decrypt_block(block),
+            AesCipher::Aes256(c) => c.decrypt_block(block),
+        }
     }
 }
 
+
+
+pub struct KrbAes128;
+
 impl KrbAes128 {
-    fn dk(constant: &str, keysize: usize, tkey: &Vec<u8>) -> Vec<u8> {
-        let folded = KrbAes::nfold(constant, keysize);
-        info!("[AES128] nfolded: {}", hex::encode_upper(&folded));
-        let key = KrbAes128::encrypt(&tkey, &vec![0; 16], &folded);
-        return key;
+    // hmac-sha1-96-aes128 (checksum type 15)
+    pub fn checksum(key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::checksum(key, 16, usage, data)
+    }
+
+    pub fn verify_checksum(key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbAes::verify_checksum(key, 16, usage, data, checksum)
+    }
+
+    pub fn usage_keys(key: &[u8], usage: u32) -> Result<UsageKeys> {
+        KrbAes::usage_keys(key, 16, usage)
+    }
+
+    pub fn bytes_to_key_with_iterations(secret: &[u8], salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+        let tkey = KrbAes::pbkdf2_from_bytes(secret, salt, 128, iterations);
+        info!("[AES128] pbkdf2 ({} iterations): {}", iterations, hex::encode_upper(&tkey));
+        KrbAes::dk(&tkey, 16, b"kerberos")
+    }
+
+    pub fn string_to_key_with_iterations(secret: &str, salt: &str, iterations: u32) -> Result<Vec<u8>> {
+        KrbAes128::bytes_to_key_with_iterations(secret.as_bytes(), salt.as_bytes(), iterations)
+    }
+}
+
+impl KrbCipher for KrbAes128 {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::encrypt(key, 16, usage, plaintext)
+    }
+
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::decrypt(key, 16, usage, ciphertext)
     }
 }
 
@@ -110,33 +349,282 @@ impl KrbAes128 {
 
 pub struct KrbAes256;
 
-impl KrbEncType for KrbAes256 {
-    fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8>  {
-        let cipher = Aes256Cbc::new_from_slices(&key, &iv).unwrap();
-        let ciphertext = cipher.encrypt_vec(plaintext);
-        return ciphertext
+impl KrbAes256 {
+    // hmac-sha1-96-aes256 (checksum type 16)
+    pub fn checksum(key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::checksum(key, 32, usage, data)
     }
-    fn bytes_to_key(secret: &[u8], salt: &[u8]) -> Vec<u8> {
-        let tkey = KrbAes::pbkdf2_from_bytes(&secret, &salt, 256);
-        info!("[AES256] pbkdf2: {}", hex::encode_upper(&tkey));
-        let ikey = KrbAes256::dk("kerberos", 256, &tkey);
-        return ikey;
+
+    pub fn verify_checksum(key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbAes::verify_checksum(key, 32, usage, data, checksum)
     }
 
-    fn string_to_key(secret: &str, salt: &str) -> Vec<u8> {
-        KrbAes256::bytes_to_key(&secret.as_bytes(), &salt.as_bytes())
+    pub fn usage_keys(key: &[u8], usage: u32) -> Result<UsageKeys> {
+        KrbAes::usage_keys(key, 32, usage)
+    }
+
+    pub fn bytes_to_key_with_iterations(secret: &[u8], salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+        let tkey = KrbAes::pbkdf2_from_bytes(secret, salt, 256, iterations);
+        info!("[AES256] pbkdf2 ({} iterations): {}", iterations, hex::encode_upper(&tkey));
+        KrbAes::dk(&tkey, 32, b"kerberos")
+    }
+
+    pub fn string_to_key_with_iterations(secret: &str, salt: &str, iterations: u32) -> Result<Vec<u8>> {
+        KrbAes256::bytes_to_key_with_iterations(secret.as_bytes(), salt.as_bytes(), iterations)
     }
 }
 
-impl KrbAes256 {
-    fn dk(constant: &str, keysize: usize, tkey: &Vec<u8>) -> Vec<u8> {
-        let folded = KrbAes::nfold(constant, keysize);
-        info!("[AES256] nfolded: {}", hex::encode_upper(&folded));
-        let mut key = KrbAes256::encrypt(&tkey, &vec![0; 16], &folded);
-        key.truncate(&keysize / 16);
-        key.extend(&KrbAes256::encrypt(&tkey, &vec![0; 16], &key)[0..&keysize / 16]); 
-        return key;
+impl KrbCipher for KrbAes256 {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::encrypt(key, 32, usage, plaintext)
+    }
+
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbAes::decrypt(key, 32, usage, ciphertext)
+    }
+}
+
+
+
+pub struct KrbAes128Sha256;
+
+impl KrbCipher for KrbAes128Sha256 {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        AES128_SHA256.encrypt(key, usage, plaintext)
+    }
+
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        AES128_SHA256.decrypt(key, usage, ciphertext)
+    }
+}
+
+impl KrbAes128Sha256 {
+    pub fn usage_keys(key: &[u8], usage: u32) -> Result<UsageKeys> {
+        AES128_SHA256.usage_keys(key, usage)
+    }
+
+    pub fn bytes_to_key_with_iterations(secret: &[u8], salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+        AES128_SHA256.bytes_to_key(secret, salt, iterations)
+    }
+
+    pub fn string_to_key_with_iterations(secret: &str, salt: &str, iterations: u32) -> Result<Vec<u8>> {
+        KrbAes128Sha256::bytes_to_key_with_iterations(secret.as_bytes(), salt.as_bytes(), iterations)
+    }
+
+    // hmac-sha256-128-aes128 (checksum type 19)
+    pub fn checksum(key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        AES128_SHA256.checksum(key, usage, data)
+    }
+
+    pub fn verify_checksum(key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        AES128_SHA256.verify_checksum(key, usage, data, checksum)
+    }
+}
+
+
+
+pub struct KrbAes256Sha384;
+
+impl KrbCipher for KrbAes256Sha384 {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        AES256_SHA384.encrypt(key, usage, plaintext)
+    }
+
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        AES256_SHA384.decrypt(key, usage, ciphertext)
+    }
+}
+
+impl KrbAes256Sha384 {
+    pub fn usage_keys(key: &[u8], usage: u32) -> Result<UsageKeys> {
+        AES256_SHA384.usage_keys(key, usage)
+    }
+
+    pub fn bytes_to_key_with_iterations(secret: &[u8], salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+        AES256_SHA384.bytes_to_key(secret, salt, iterations)
+    }
+
+    pub fn string_to_key_with_iterations(secret: &str, salt: &str, iterations: u32) -> Result<Vec<u8>> {
+        KrbAes256Sha384::bytes_to_key_with_iterations(secret.as_bytes(), salt.as_bytes(), iterations)
+    }
+
+    // hmac-sha384-192-aes256 (checksum type 20)
+    pub fn checksum(key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        AES256_SHA384.checksum(key, usage, data)
+    }
+
+    pub fn verify_checksum(key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        AES256_SHA384.verify_checksum(key, usage, data, checksum)
     }
 }
-    
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(data: &str) -> Vec<u8> {
+        hex::decode(data.replace(' ', "")).unwrap()
+    }
+
+    // (iterations, pass phrase, salt, AES128 key, AES256 key)
+    type S2kVector = (u32, &'static [u8], &'static [u8], &'static str, &'static str);
+
+    // RFC 3962 appendix B
+    const S2K: [S2kVector; 7] = [
+        (1, b"password", b"ATHENA.MIT.EDUraeburn",
+            "42263c6e89f4fc28b8df68ee09799f15",
+            "fe697b52bc0d3ce14432ba036a92e65bbb52280990a2fa27883998d72af30161"),
+        (2, b"password", b"ATHENA.MIT.EDUraeburn",
+            "c651bf29e2300ac27fa469d693bdda13",
+            "a2e16d16b36069c135d5e9d2e25f896102685618b95914b467c67622225824ff"),
+        (1200, b"password", b"ATHENA.MIT.EDUraeburn",
+            "4c01cd46d632d01e6dbe230a01ed642a",
+            "55a6ac740ad17b4846941051e1e8b0a7548d93b0ab30a8bc3ff16280382b8c2a"),
+        (5, b"password", &[0x12, 0x34, 0x56, 0x78, 0x78, 0x56, 0x34, 0x12],
+            "e9b23d52273747dd5c35cb55be619d8e",
+            "97a4e786be20d81a382d5ebc96d5909cabcdadc87ca48f574504159f16c36e31"),
+        (1200, b"XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX",
+            b"pass phrase equals block size",
+            "59d1bb789a828b1aa54ef9c2883f69ed",
+            "89adee3608db8bc71f1bfbfe459486b05618b70cbae22092534e56c553ba4b34"),
+        (1200, b"XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX",
+            b"pass phrase exceeds block size",
+            "cb8005dc5f90179a7f02104c0018751d",
+            "d78c5c9cb872a8c9dad4697f0bb5b2d21496c82beb2caeda2112fceea057401b"),
+        (50, "\u{1d11e}".as_bytes(), b"EXAMPLE.COMpianist",
+            "f149c1f2e154a73452d43e7fe62a56e5",
+            "4b6d9839f84406df1f09cc166db4b83c571848b784a3d6bdc346589a3e393f9e"),
+    ];
+
+    #[test]
+    fn rfc3962_string_to_key() {
+        for (iterations, password, salt, key128, key256) in S2K {
+            assert_eq!(KrbAes128::bytes_to_key_with_iterations(password, salt, iterations).unwrap(), h(key128));
+            assert_eq!(KrbAes256::bytes_to_key_with_iterations(password, salt, iterations).unwrap(), h(key256));
+            // the five iteration vector's salt is not UTF-8
+            if let (Ok(password), Ok(salt)) = (std::str::from_utf8(password), std::str::from_utf8(salt)) {
+                assert_eq!(KrbAes128::string_to_key_with_iterations(password, salt, iterations).unwrap(), h(key128));
+                assert_eq!(KrbAes256::string_to_key_with_iterations(password, salt, iterations).unwrap(), h(key256));
+            }
+        }
+    }
+
+    // RFC 3962 appendix B, AES128 with the key "chicken teriyaki" and a zero IV
+    const CTS: [(usize, &str); 6] = [
+        (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+        (31, "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5"),
+        (32, "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584"),
+        (47, "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e39312523a78662d5be7fcbcc98ebf5"),
+        (48, "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd839312523a78662d5be7fcbcc98ebf5a8"),
+        (64, "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a84807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8"),
+    ];
+
+    #[test]
+    fn rfc3962_cts() {
+        let cipher = AesCipher::new(b"chicken teriyaki", 16).unwrap();
+        let plaintext = b"I would like the General Gau's Chicken, please, and wonton soup.";
+        for (length, expected) in CTS {
+            let ciphertext = cts::encrypt(&cipher, &plaintext[..length]);
+            assert_eq!(ciphertext, h(expected), "{} bytes", length);
+            assert_eq!(cts::decrypt(&cipher, &ciphertext), &plaintext[..length]);
+        }
+    }
+
+    // usage 3 with a fixed confounder, from fixtures/gen/aes.py
+    const PLAINTEXT: &[u8] = b"metaros known-answer test";
+
+    fn confounder() -> Vec<u8> {
+        (0xa0..0xb0).collect()
+    }
+
+    #[test]
+    fn encrypt_known_answer() {
+        let key128: Vec<u8> = (0..16).collect();
+        let key256: Vec<u8> = (0..32).collect();
+        let expected128 = h("8c708ff100af6d42bfa2e4886842575eeb63e79aeab60755f606ac5ff1b225df937095234fb456b742ace2f9b65ba5b9818331c944");
+        let expected256 = h("f658f1533456ca22e38cbbc1264e93e5c76c6b7a8836d83f7fac49aa5a49996e4b7f9c80f46abf8bc8fe81a90071e94939b632a22b");
+        assert_eq!(KrbAes::encrypt_with_confounder(&key128, 16, 3, &confounder(), PLAINTEXT).unwrap(), expected128);
+        assert_eq!(KrbAes::encrypt_with_confounder(&key256, 32, 3, &confounder(), PLAINTEXT).unwrap(), expected256);
+        assert_eq!(<KrbAes128 as KrbCipher>::decrypt(&key128, 3, &expected128).unwrap(), PLAINTEXT);
+        assert_eq!(<KrbAes256 as KrbCipher>::decrypt(&key256, 3, &expected256).unwrap(), PLAINTEXT);
+    }
+
+    #[test]
+    fn decrypt_rejects_tampering() {
+        let key: Vec<u8> = (0..32).collect();
+        let mut ciphertext = <KrbAes256 as KrbCipher>::encrypt(&key, 3, PLAINTEXT).unwrap();
+        ciphertext[20] ^= 1;
+        assert!(matches!(<KrbAes256 as KrbCipher>::decrypt(&key, 3, &ciphertext), Err(Error::IntegrityCheckFailed)));
+        assert!(matches!(<KrbAes256 as KrbCipher>::decrypt(&key, 4, &ciphertext), Err(Error::IntegrityCheckFailed)));
+    }
+
+    // RFC 8009 appendix A
+    const SHA2_KEY128: &str = "3705d96080c17728a0e800eab6e0d23c";
+    const SHA2_KEY256: &str = "6d404d37faf79f9df0d33568d320669800eb4836472ea8a026d16b7182460c52";
+
+    #[test]
+    fn rfc8009_string_to_key() {
+        let mut salt = h("10df9dd783e5bc8acea1730e74355f61");
+        salt.extend(b"ATHENA.MIT.EDUraeburn");
+        assert_eq!(KrbAes128Sha256::bytes_to_key_with_iterations(b"password", &salt, AES_SHA2_DEFAULT_ITERATIONS).unwrap(),
+            h("089bca48b105ea6ea77ca5d2f39dc5e7"));
+        assert_eq!(KrbAes256Sha384::bytes_to_key_with_iterations(b"password", &salt, AES_SHA2_DEFAULT_ITERATIONS).unwrap(),
+            h("45bd806dbf6a833a9cffc1c94589a222367a79bc21c413718906e9f578a78467"));
+    }
+
+    #[test]
+    fn rfc8009_usage_keys() {
+        let keys = KrbAes128Sha256::usage_keys(&h(SHA2_KEY128), 2).unwrap();
+        assert_eq!(keys.kc, h("b31a018a48f54776f403e9a396325dc3"));
+        assert_eq!(keys.ke, h("9b197dd1e8c5609d6e67c3e37c62c72e"));
+        assert_eq!(keys.ki, h("9fda0e56ab2d85e1569a688696c26a6c"));
+        let keys = KrbAes256Sha384::usage_keys(&h(SHA2_KEY256), 2).unwrap();
+        assert_eq!(keys.kc, h("ef5718be86cc84963d8bbb5031e9f5c4ba41f28faf69e73d"));
+        assert_eq!(keys.ke, h("56ab22bee63d82d7bc5227f6773f8ea7a5eb1c825160c38312980c442e5c7e49"));
+        assert_eq!(keys.ki, h("69b16514e3cd8e56b82010d5c73012b622c4d00ffc23ed1f"));
+    }
+
+    #[test]
+    fn rfc8009_encrypt() {
+        let confounder = h("7e5895eaf2672435bad817f545a37148");
+        let expected = h("ef85fb890bb8472f4dab20394dca781dad877eda39d50c870c0d5a0a8e48c718");
+        assert_eq!(AES128_SHA256.encrypt_with_confounder(&h(SHA2_KEY128), 2, &confounder, b"").unwrap(), expected);
+        assert_eq!(<KrbAes128Sha256 as KrbCipher>::decrypt(&h(SHA2_KEY128), 2, &expected).unwrap(), b"");
+    }
+
+    // with the confounder above, from fixtures/gen/aes.py
+    #[test]
+    fn aes_sha2_encrypt_known_answer() {
+        let expected128 = h("42ee5fa747edebc61d560ea4d19ff0b9c47957bdf023e4e5dec0ef877909674d6235477ff7a96de4f824563faf1e4cc190c9c8d5aa25f1a139");
+        let expected256 = h("e03493b2a645143e60954ab9df1d0cb7da1bce817daa926fc8fe610ff5c8809a6c959657499b5718b9bbb18f08b3892953bdf352853e73e9df9d31575f92a96261");
+        assert_eq!(AES128_SHA256.encrypt_with_confounder(&h(SHA2_KEY128), 2, &confounder(), PLAINTEXT).unwrap(), expected128);
+        assert_eq!(AES256_SHA384.encrypt_with_confounder(&h(SHA2_KEY256), 2, &confounder(), PLAINTEXT).unwrap(), expected256);
+        assert_eq!(<KrbAes128Sha256 as KrbCipher>::decrypt(&h(SHA2_KEY128), 2, &expected128).unwrap(), PLAINTEXT);
+        assert_eq!(<KrbAes256Sha384 as KrbCipher>::decrypt(&h(SHA2_KEY256), 2, &expected256).unwrap(), PLAINTEXT);
+    }
+
+    #[test]
+    fn rfc8009_checksum() {
+        let data: Vec<u8> = (0..21).collect();
+        let checksum = h("d78367186643d67b411cba9139fc1dee");
+        assert_eq!(KrbAes128Sha256::checksum(&h(SHA2_KEY128), 2, &data).unwrap(), checksum);
+        KrbAes128Sha256::verify_checksum(&h(SHA2_KEY128), 2, &data, &checksum).unwrap();
+        let checksum = h("45ee791567eefca37f4ac1e0222de80d43c3bfa06699672a");
+        assert_eq!(KrbAes256Sha384::checksum(&h(SHA2_KEY256), 2, &data).unwrap(), checksum);
+        KrbAes256Sha384::verify_checksum(&h(SHA2_KEY256), 2, &data, &checksum).unwrap();
+    }
+
+    #[test]
+    fn bad_key_length_names_the_etype_size() {
+        let key = [0u8; 20];
+        for (result, expected) in [
+            (<KrbAes128 as KrbCipher>::encrypt(&key, 2, b""), 16),
+            (<KrbAes256 as KrbCipher>::encrypt(&key, 2, b""), 32),
+            (<KrbAes128Sha256 as KrbCipher>::encrypt(&key, 2, b""), 16),
+            (AesCipher::new(&key, 16).map(|_| Vec::new()), 16),
+        ] {
+            assert!(matches!(result, Err(Error::BadKeyLength { expected: e, actual: 20 }) if e == expected));
+        }
+    }
+}
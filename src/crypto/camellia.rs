@@ -0,0 +1,316 @@
+use pbkdf2::pbkdf2;
+use hmac::Hmac;
+use sha1::Sha1;
+use rand::RngCore;
+use camellia::{Camellia128, Camellia256};
+use camellia::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use camellia::cipher::generic_array::GenericArray;
+use cmac::{Cmac, Mac};
+use crate::crypto::crypto::*;
+use crate::crypto::cts::{self, BlockCipher, BLOCK_SIZE};
+use crate::utils::utils::constant_time_eq;
+use crate::error::{Error, Result};
+
+// RFC 6803 section 3
+pub const CAMELLIA_DEFAULT_ITERATIONS: u32 = 32768;
+
+
+// Camellia-CTS-CMAC from RFC 6803: the RFC 3961 simplified profile with
+// Camellia in place of AES and CMAC as both the KDF PRF and integrity tag.
+pub struct KrbCamellia;
+
+impl KrbCamellia {
+    fn check_key(key: &[u8], keysize: usize) -> Result<()> {
+        if key.len() != keysize {
+            return Err(Error::BadKeyLength { expected: keysize, actual: key.len() });
+        }
+        Ok(())
+    }
+
+    fn cmac(key: &[u8], keysize: usize, data: &[&[u8]]) -> Result<Vec<u8>> {
+        let bad_key = |_| Error::BadKeyLength { expected: keysize, actual: key.len() };
+        Ok(match keysize {
+            16 => {
+                let mut mac = <Cmac<Camellia128> as Mac>::new_from_slice(key).map_err(bad_key)?;
+                data.iter().for_each(|d| mac.update(d));
+                mac.finalize().into_bytes().to_vec()
+            },
+            _ => {
+                let mut mac = <Cmac<Camellia256> as Mac>::new_from_slice(key).map_err(bad_key)?;
+                data.iter().for_each(|d| mac.update(d));
+                mac.finalize().into_bytes().to_vec()
+            },
+        })
+    }
+
+    fn bytes_to_key(name: &str, keysize: usize, secret: &[u8], salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+        // the salt is prefixed with the etype name and a zero byte
+        let mut saltp = name.as_bytes().to_vec();
+        saltp.push(0);
+        saltp.extend(salt);
+        let mut tkey = vec![0u8; keysize];
+        pbkdf2::<Hmac<Sha1>>(secret, &saltp, iterations, &mut tkey);
+        info!("[{}] pbkdf2: {}", name, hex::encode_upper(&tkey));
+        KrbCamellia::kdf(&tkey, keysize, b"kerberos")
+    }
+
+    // KDF-FEEDBACK-CMAC: each block feeds the previous output, a counter,
+    // the label and the output length in bits through CMAC. Camellia keys
+    // are random bytes, so the result is used as is.
+    fn kdf(key: &[u8], keysize: usize, label: &[u8]) -> Result<Vec<u8>> {
+        let bits = (keysize as u32 * 8).to_be_bytes();
+        let mut block = vec![0u8; BLOCK_SIZE];
+        let mut derived = Vec::new();
+        let mut counter: u32 = 1;
+        while derived.len() < keysize {
+            block = KrbCamellia::cmac(key, keysize, &[&block, &counter.to_be_bytes(), label, &[0], &bits])?;
+            derived.extend(&block);
+            counter += 1;
+        }
+        derived.truncate(keysize);
+        Ok(derived)
+    }
+
+    fn usage_key(key: &[u8], keysize: usize, usage: u32, kind: u8) -> Result<Vec<u8>> {
+        let mut label = usage.to_be_bytes().to_vec();
+        label.push(kind);
+        KrbCamellia::kdf(key, keysize, &label)
+    }
+
+    fn usage_keys(key: &[u8], keysize: usize, usage: u32) -> Result<UsageKeys> {
+        KrbCamellia::check_key(key, keysize)?;
+        Ok(UsageKeys {
+            kc: KrbCamellia::usage_key(key, keysize, usage, 0x99)?,
+            ke: KrbCamellia::usage_key(key, keysize, usage, 0xAA)?,
+            ki: KrbCamellia::usage_key(key, keysize, usage, 0x55)?,
+        })
+    }
+
+    fn encrypt(key: &[u8], keysize: usize, usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut confounder = [0u8; BLOCK_SIZE];
+        rand::thread_rng().fill_bytes(&mut confounder);
+        KrbCamellia::encrypt_with_confounder(key, keysize, usage, &confounder, plaintext)
+    }
+
+    fn encrypt_with_confounder(key: &[u8], keysize: usize, usage: u32,
+            confounder: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbCamellia::check_key(key, keysize)?;
+        let ke = KrbCamellia::usage_key(key, keysize, usage, 0xAA)?;
+        let ki = KrbCamellia::usage_key(key, keysize, usage, 0x55)?;
+        let mut data = confounder.to_vec();
+        data.extend(plaintext);
+        let mut ciphertext = cts::encrypt(&CamelliaCipher::new(&ke, keysize)?, &data);
+        ciphertext.extend(KrbCamellia::cmac(&ki, keysize, &[&data])?);
+        Ok(ciphertext)
+    }
+
+    fn decrypt(key: &[u8], keysize: usize, usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbCamellia::check_key(key, keysize)?;
+        if ciphertext.len() < 2 * BLOCK_SIZE {
+            return Err(Error::BadCiphertext(format!(
+                "{} bytes cannot hold a confounder and checksum", ciphertext.len())));
+        }
+        let ke = KrbCamellia::usage_key(key, keysize, usage, 0xAA)?;
+        let ki = KrbCamellia::usage_key(key, keysize, usage, 0x55)?;
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - BLOCK_SIZE);
+        let data = cts::decrypt(&CamelliaCipher::new(&ke, keysize)?, ciphertext);
+        if !constant_time_eq(&KrbCamellia::cmac(&ki, keysize, &[&data])?, tag) {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        Ok(data[BLOCK_SIZE..].to_vec())
+    }
+
+    fn checksum(key: &[u8], keysize: usize, usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbCamellia::check_key(key, keysize)?;
+        let kc = KrbCamellia::usage_key(key, keysize, usage, 0x99)?;
+        KrbCamellia::cmac(&kc, keysize, &[data])
+    }
+
+    fn verify_checksum(key: &[u8], keysize: usize, usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        if !constant_time_eq(&KrbCamellia::checksum(key, keysize, usage, data)?, checksum) {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        Ok(())
+    }
+}
+
+
+enum CamelliaCipher {
+    Camellia128(Box<Camellia128>),
+    Camellia256(Box<Camellia256>),
+}
+
+impl CamelliaCipher {
+    // keysize is what the etype expects, so a bad key is reported against it
+    fn new(key: &[u8], keysize: usize) -> Result<CamelliaCipher> {
+        match (key.len(), keysize) {
+            (16, 16) => Ok(CamelliaCipher::Camellia128(Box::new(Camellia128::new(GenericArray::from_slice(key))))),
+            (32, 32) => Ok(CamelliaCipher::Camellia256(Box::new(Camellia256::new(GenericArray::from_slice(key))))),
+            (n, _) => Err(Error::BadKeyLength { expected: keysize, actual: n }),
+        }
+    }
+}
+
+impl BlockCipher for CamelliaCipher {
+    fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            CamelliaCipher::Camellia128(c) => c.encrypt_block(block),
+            CamelliaCipher::Camellia256(c) => c.encrypt_block(block),
+        }
+    }
+
+    fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            CamelliaCipher::Camellia128(c) => c.decrypt_block(block),
+            CamelliaCipher::Camellia256(c) => c.decrypt_block(block),
+        }
+    }
+}
+
+
+
+pub struct KrbCamellia128;
+
+impl KrbCipher for KrbCamellia128 {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbCamellia::encrypt(key, 16, usage, plaintext)
+    }
+
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbCamellia::decrypt(key, 16, usage, ciphertext)
+    }
+}
+
+impl KrbCamellia128 {
+    pub fn usage_keys(key: &[u8], usage: u32) -> Result<UsageKeys> {
+        KrbCamellia::usage_keys(key, 16, usage)
+    }
+
+    pub fn bytes_to_key_with_iterations(secret: &[u8], salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+        KrbCamellia::bytes_to_key("camellia128-cts-cmac", 16, secret, salt, iterations)
+    }
+
+    pub fn string_to_key_with_iterations(secret: &str, salt: &str, iterations: u32) -> Result<Vec<u8>> {
+        KrbCamellia128::bytes_to_key_with_iterations(secret.as_bytes(), salt.as_bytes(), iterations)
+    }
+
+    // cmac-camellia128 (checksum type 17)
+    pub fn checksum(key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbCamellia::checksum(key, 16, usage, data)
+    }
+
+    pub fn verify_checksum(key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbCamellia::verify_checksum(key, 16, usage, data, checksum)
+    }
+}
+
+
+
+pub struct KrbCamellia256;
+
+impl KrbCipher for KrbCamellia256 {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbCamellia::encrypt(key, 32, usage, plaintext)
+    }
+
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbCamellia::decrypt(key, 32, usage, ciphertext)
+    }
+}
+
+impl KrbCamellia256 {
+    pub fn usage_keys(key: &[u8], usage: u32) -> Result<UsageKeys> {
+        KrbCamellia::usage_keys(key, 32, usage)
+    }
+
+    pub fn bytes_to_key_with_iterations(secret: &[u8], salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+        KrbCamellia::bytes_to_key("camellia256-cts-cmac", 32, secret, salt, iterations)
+    }
+
+    pub fn string_to_key_with_iterations(secret: &str, salt: &str, iterations: u32) -> Result<Vec<u8>> {
+        KrbCamellia256::bytes_to_key_with_iterations(secret.as_bytes(), salt.as_bytes(), iterations)
+    }
+
+    // cmac-camellia256 (checksum type 18)
+    pub fn checksum(key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbCamellia::checksum(key, 32, usage, data)
+    }
+
+    pub fn verify_checksum(key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbCamellia::verify_checksum(key, 32, usage, data, checksum)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(data: &str) -> Vec<u8> {
+        hex::decode(data).unwrap()
+    }
+
+    // RFC 6803 section 10. The key derivation base keys are the
+    // string-to-key of "password" with one iteration.
+    const KEY128: &str = "57d0297298ffd9d35de5a47fb4bde24b";
+    const KEY256: &str = "b9d6828b2056b7be656d88a123b1fac68214ac2b727ecf5f69afe0c4df2a6d2c";
+
+    #[test]
+    fn string_to_key() {
+        assert_eq!(KrbCamellia128::string_to_key_with_iterations("password", "ATHENA.MIT.EDUraeburn", 1).unwrap(), h(KEY128));
+        assert_eq!(KrbCamellia256::string_to_key_with_iterations("password", "ATHENA.MIT.EDUraeburn", 1).unwrap(), h(KEY256));
+    }
+
+    #[test]
+    fn usage_keys() {
+        let keys = KrbCamellia128::usage_keys(&h(KEY128), 2).unwrap();
+        assert_eq!(keys.kc, h("d155775a209d05f02b38d42a389e5a56"));
+        assert_eq!(keys.ke, h("64df83f85a532f17577d8c37035796ab"));
+        assert_eq!(keys.ki, h("3e4fbdf30fb8259c425cb6c96f1f4635"));
+        let keys = KrbCamellia256::usage_keys(&h(KEY256), 2).unwrap();
+        assert_eq!(keys.kc, h("e467f9a9552bc7d3155a6220af9c19220eeed4ff78b0d1e6a1544991461a9e50"));
+        assert_eq!(keys.ke, h("412aefc362a7285fc3966c6a5181e7605ae675235b6d549fbfc9ab6630a4c604"));
+        assert_eq!(keys.ki, h("fa624fa0e523993fa388aefdc67e67ebcd8c08e8a0246b1d73b0d1dd9fc582b0"));
+    }
+
+    #[test]
+    fn encrypt() {
+        // (key, usage, confounder, plaintext, ciphertext)
+        for (key, usage, confounder, plaintext, expected) in [
+            ("1dc46a8d763f4f93742bcba3387576c3", 0, "b69822a19a6b09c0ebc8557d1f1b6c0a", "",
+                "c466f1871069921edb7c6fde244a52db0ba10edc197bdb8006658ca3ccce6eb8"),
+            ("5027bc231d0f3a9d23333f1ca6fdbe7c", 1, "6f2fc3c2a166fd8898967a83de9596d9", "31",
+                "842d21fd950311c0dd464a3f4be8d6da88a56d559c9b47d3f9a85067af661559b8"),
+        ] {
+            let ciphertext = KrbCamellia::encrypt_with_confounder(&h(key), 16, usage, &h(confounder), &h(plaintext)).unwrap();
+            assert_eq!(ciphertext, h(expected));
+            assert_eq!(<KrbCamellia128 as KrbCipher>::decrypt(&h(key), usage, &ciphertext).unwrap(), h(plaintext));
+        }
+    }
+
+    #[test]
+    fn checksum() {
+        let key = h("1dc46a8d763f4f93742bcba3387576c3");
+        let checksum = h("1178e6c5c47a8c1ae0c4b9c7d4eb7b6b");
+        assert_eq!(KrbCamellia128::checksum(&key, 7, b"abcdefghijk").unwrap(), checksum);
+        KrbCamellia128::verify_checksum(&key, 7, b"abcdefghijk", &checksum).unwrap();
+        for (key, usage, data, checksum) in [
+            ("b61c86cc4e5d2757545ad423399fb7031ecab913cbb900bd7a3c6dd8bf92015b", 9,
+                "123456789", "87a12cfd2b96214810f01c826e7744b1"),
+            ("32164c5b434d1d1538e4cfd9be8040fe8c4ac7acc4b93d3314d2133668147a05", 10,
+                "!@#$%^&*()!@#$%^&*()!@#$%^&*()", "3fa0b42355e52b189187294aa252ab64"),
+        ] {
+            assert_eq!(KrbCamellia256::checksum(&h(key), usage, data.as_bytes()).unwrap(), h(checksum));
+        }
+    }
+
+    #[test]
+    fn bad_key_length_names_the_etype_size() {
+        let key = [0u8; 20];
+        assert!(matches!(<KrbCamellia128 as KrbCipher>::encrypt(&key, 2, b"0123456789abcdef"),
+            Err(Error::BadKeyLength { expected: 16, actual: 20 })));
+        assert!(matches!(KrbCamellia::cmac(&key, 32, &[]), Err(Error::BadKeyLength { expected: 32, actual: 20 })));
+    }
+}
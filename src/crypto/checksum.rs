@@ -0,0 +1,218 @@
+use crate::crypto::aes::{KrbAes128, KrbAes256, KrbAes128Sha256, KrbAes256Sha384};
+use crate::crypto::camellia::{KrbCamellia128, KrbCamellia256};
+use crate::crypto::des::{KrbDes, KrbDes3, KrbDesCbcMd4, KrbDesCbcMd5};
+use crate::crypto::rc4::KrbRc4;
+use crate::kerberos::reference;
+use crate::utils::utils::constant_time_eq;
+use crate::error::{Error, Result};
+
+
+// What callers need to know about a checksum type without naming it.
+// Unkeyed checksums ignore the key and usage they are given.
+pub struct ChecksumInfo {
+    pub id: i32,
+    pub size: usize,
+    pub keyed: bool,
+}
+
+
+// The checksum types of RFC 3961 section 8 and its successors, chosen at
+// runtime by number like etype::KrbEtype.
+pub trait KrbChecksum: Sync {
+    fn info(&self) -> &'static ChecksumInfo;
+    fn compute(&self, key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>>;
+
+    // Checksums with a random confounder cannot simply be recomputed and
+    // compared, so they override this.
+    fn verify(&self, key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        if !constant_time_eq(&self.compute(key, usage, data)?, checksum) {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> i32 {
+        self.info().id
+    }
+
+    fn name(&self) -> &'static str {
+        reference::CHECKSUM_TYPES.name(self.id())
+    }
+
+    fn size(&self) -> usize {
+        self.info().size
+    }
+
+    fn keyed(&self) -> bool {
+        self.info().keyed
+    }
+}
+
+
+// The registry and the KrbChecksum impls behind it, in the manner of
+// etype::etypes!. Each checksum's ChecksumInfo becomes a static of the
+// given name.
+macro_rules! checksums {
+    ($($cksum:ident => $info:ident { $($field:ident: $value:expr),* $(,)? },
+            $kind:ident($($f:path),+);)*) => {
+        $(
+            pub struct $cksum;
+            static $info: ChecksumInfo = ChecksumInfo { $($field: $value),* };
+            checksum_impl!($cksum, $info, $kind($($f),+));
+        )*
+
+        static CHECKSUMS: &[&dyn KrbChecksum] = &[$(&$cksum),*];
+    };
+}
+
+// The kinds are:
+//   unkeyed(digest)              a plain digest of the data
+//   keyed(checksum)              a keyed function of key, usage and data
+//   confounded(checksum, verify) keyed with a random confounder, so that
+//                                verifying cannot simply recompute
+macro_rules! checksum_impl {
+    ($cksum:ident, $info:ident, $kind:ident($($f:path),+)) => {
+        impl KrbChecksum for $cksum {
+            fn info(&self) -> &'static ChecksumInfo {
+                &$info
+            }
+
+            checksum_impl!(@methods $kind($($f),+));
+        }
+    };
+    (@methods unkeyed($digest:path)) => {
+        fn compute(&self, _key: &[u8], _usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+            Ok($digest(data))
+        }
+    };
+    (@methods keyed($checksum:path)) => {
+        fn compute(&self, key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+            $checksum(key, usage, data)
+        }
+    };
+    (@methods confounded($checksum:path, $verify:path)) => {
+        fn compute(&self, key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+            $checksum(key, usage, data)
+        }
+
+        fn verify(&self, key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+            $verify(key, usage, data, checksum)
+        }
+    };
+}
+
+
+// Every checksum type metaros implements, one per line: the type, its
+// ChecksumInfo and how it is computed (see checksum_impl!). Adding one is
+// a matter of listing it here.
+checksums! {
+    Crc32 => CRC32 { id: 1, size: 4, keyed: false }, unkeyed(KrbDes::crc32);
+    RsaMd4 => RSA_MD4 { id: 2, size: 16, keyed: false }, unkeyed(KrbDes::md4);
+    RsaMd4Des => RSA_MD4_DES { id: 3, size: 24, keyed: true },
+        confounded(KrbDesCbcMd4::checksum, KrbDesCbcMd4::verify_checksum);
+    DesMac => DES_MAC { id: 4, size: 16, keyed: true }, confounded(KrbDes::des_mac, KrbDes::verify_des_mac);
+    DesMacK => DES_MAC_K { id: 5, size: 8, keyed: true }, keyed(KrbDes::des_mac_k);
+    RsaMd5 => RSA_MD5 { id: 7, size: 16, keyed: false }, unkeyed(KrbDes::md5);
+    RsaMd5Des => RSA_MD5_DES { id: 8, size: 24, keyed: true },
+        confounded(KrbDesCbcMd5::checksum, KrbDesCbcMd5::verify_checksum);
+    HmacSha1Des3Kd => HMAC_SHA1_DES3_KD { id: 12, size: 20, keyed: true }, keyed(KrbDes3::checksum);
+    HmacSha1Aes128 => HMAC_SHA1_96_AES128 { id: 15, size: 12, keyed: true }, keyed(KrbAes128::checksum);
+    HmacSha1Aes256 => HMAC_SHA1_96_AES256 { id: 16, size: 12, keyed: true }, keyed(KrbAes256::checksum);
+    CmacCamellia128 => CMAC_CAMELLIA128 { id: 17, size: 16, keyed: true }, keyed(KrbCamellia128::checksum);
+    CmacCamellia256 => CMAC_CAMELLIA256 { id: 18, size: 16, keyed: true }, keyed(KrbCamellia256::checksum);
+    HmacSha256Aes128 => HMAC_SHA256_128_AES128 { id: 19, size: 16, keyed: true }, keyed(KrbAes128Sha256::checksum);
+    HmacSha384Aes256 => HMAC_SHA384_192_AES256 { id: 20, size: 24, keyed: true }, keyed(KrbAes256Sha384::checksum);
+    // the RC4-HMAC checksum, which Windows also uses for PAC signatures
+    HmacMd5 => HMAC_MD5 { id: -138, size: 16, keyed: true }, keyed(KrbRc4::checksum);
+}
+
+pub fn all() -> impl Iterator<Item = &'static dyn KrbChecksum> {
+    CHECKSUMS.iter().copied()
+}
+
+pub fn by_id(id: i32) -> Option<&'static dyn KrbChecksum> {
+    all().find(|c| c.id() == id)
+}
+
+// Accepts anything reference::CHECKSUM_TYPES knows: a name, a shortname or
+// a number.
+pub fn lookup(query: &str) -> Result<&'static dyn KrbChecksum> {
+    let id = reference::CHECKSUM_TYPES.lookup(query)
+        .ok_or_else(|| Error::UnknownChecksumType(query.to_string()))?
+        .id;
+    by_id(id).ok_or_else(|| Error::UnsupportedChecksumType(query.to_string()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(data: &str) -> Vec<u8> {
+        hex::decode(data).unwrap()
+    }
+
+    const PLAINTEXT: &[u8] = b"metaros known-answer test";
+
+    // (checksum type, key, usage, data, checksum)
+    type Vector = (&'static str, &'static str, u32, &'static [u8], Option<&'static str>);
+
+    // One per entry in CHECKSUMS.
+    // CRC-32 is from RFC 3961 A.5, MD4 and MD5 from RFC 1320 and 1321,
+    // Camellia from RFC 6803 and the SHA-2 ones from RFC 8009. des-mac-k
+    // and the SHA-1 AES ones are from fixtures/gen/checksums.py, des3 from
+    // fixtures/gen/des3.py and hmac-md5 from fixtures/gen/rc4_hmac.py. The
+    // confounded DES checksums only round trip here; des.rs checks them
+    // with a fixed confounder.
+    const KNOWN: [Vector; 15] = [
+        ("crc32", "", 0, b"foo", Some("33bc3273")),
+        ("rsa-md4", "", 0, b"abc", Some("a448017aaf21d8525fc10ae87aa6729d")),
+        ("rsa-md4-des", "cbc22fae235298e3", 0, PLAINTEXT, None),
+        ("des-mac", "cbc22fae235298e3", 0, PLAINTEXT, None),
+        ("des-mac-k", "cbc22fae235298e3", 0, PLAINTEXT, Some("d1b1d72e836ff4ad")),
+        ("rsa-md5", "", 0, b"abc", Some("900150983cd24fb0d6963f7d28e17f72")),
+        ("rsa-md5-des", "cbc22fae235298e3", 0, PLAINTEXT, None),
+        ("hmac-sha1-des3-kd", "850bb51358548cd05e86768c313e3bfef7511937dcf72c3e", 17, PLAINTEXT,
+            Some("d8543ea6c19bc347e3c4329c9ce1bbbfc5da0cf4")),
+        ("hmac-sha1-96-aes128", "42263c6e89f4fc28b8df68ee09799f15", 17, PLAINTEXT,
+            Some("3d15e6298a6118bef97431fb")),
+        ("hmac-sha1-96-aes256", "fe697b52bc0d3ce14432ba036a92e65bbb52280990a2fa27883998d72af30161", 17, PLAINTEXT,
+            Some("10553918304c174ee145b013")),
+        ("cmac-camellia128", "1dc46a8d763f4f93742bcba3387576c3", 7, b"abcdefghijk",
+            Some("1178e6c5c47a8c1ae0c4b9c7d4eb7b6b")),
+        ("cmac-camellia256", "b61c86cc4e5d2757545ad423399fb7031ecab913cbb900bd7a3c6dd8bf92015b", 9, b"123456789",
+            Some("87a12cfd2b96214810f01c826e7744b1")),
+        ("hmac-sha256-128-aes128", "3705d96080c17728a0e800eab6e0d23c", 2,
+            &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20],
+            Some("d78367186643d67b411cba9139fc1dee")),
+        ("hmac-sha384-192-aes256", "6d404d37faf79f9df0d33568d320669800eb4836472ea8a026d16b7182460c52", 2,
+            &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20],
+            Some("45ee791567eefca37f4ac1e0222de80d43c3bfa06699672a")),
+        ("hmac-md5", "a4f49c406510bdcab6824ee7c30fd852", 17, PLAINTEXT,
+            Some("ebba73c7a68f329b6eda3d88ba317867")),
+    ];
+
+    #[test]
+    fn known_answers() {
+        for (name, key, usage, data, expected) in KNOWN {
+            let cksum = lookup(name).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let key = h(key);
+            let checksum = cksum.compute(&key, usage, data).unwrap();
+            assert_eq!(checksum.len(), cksum.size(), "{}", name);
+            if let Some(expected) = expected {
+                assert_eq!(checksum, h(expected), "{}", name);
+            }
+            cksum.verify(&key, usage, data, &checksum).unwrap();
+            assert!(matches!(cksum.verify(&key, usage, b"tampered", &checksum), Err(Error::IntegrityCheckFailed)),
+                "{}", name);
+        }
+    }
+
+    #[test]
+    fn every_checksum_is_covered() {
+        for cksum in all() {
+            assert!(KNOWN.iter().any(|(name, ..)| lookup(name).unwrap().id() == cksum.id()), "{}", cksum.name());
+            assert_eq!(by_id(cksum.id()).unwrap().name(), cksum.name());
+        }
+    }
+}
@@ -1,19 +1,23 @@
-pub trait KrbEncType {
-    fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8>;
+use crate::error::Result;
 
-    fn string_to_key(secret: &str, salt: &str) -> Vec<u8>;
-//    fn bytes_to_key(bytes: Vec<u8>);
-    fn bytes_to_key(secret: &[u8], salt: &[u8]) -> Vec<u8>;
+// Kerberos message encryption (RFC 3961): a confounder, the etype's cipher
+// and an integrity tag, all keyed from the base key and the key usage.
+pub trait KrbCipher {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>>;
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>>;
 }
 
 
-pub trait WinHash {
-    fn from_bytes(plaintext: &[u8]) -> Vec<u8>;
-    fn from_string(plaintext: &str) -> Vec<u8>;
+// The keys the simplified profile derives from a base key for one key
+// usage: Kc for checksums, Ke for encryption and Ki for integrity.
+pub struct UsageKeys {
+    pub kc: Vec<u8>,
+    pub ke: Vec<u8>,
+    pub ki: Vec<u8>,
 }
 
 
-pub trait EncType {
-    fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8>;
+pub trait WinHash {
+    fn from_bytes(plaintext: &[u8]) -> Result<Vec<u8>>;
+    fn from_string(plaintext: &str) -> Result<Vec<u8>>;
 }
-
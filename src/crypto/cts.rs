@@ -0,0 +1,70 @@
+pub(crate) const BLOCK_SIZE: usize = 16;
+
+
+// The 128 bit block ciphers the Kerberos CTS profiles are built on.
+pub(crate) trait BlockCipher {
+    fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]);
+    fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]);
+}
+
+
+fn xor_into(block: &mut [u8; BLOCK_SIZE], other: &[u8]) {
+    for (b, o) in block.iter_mut().zip(other) {
+        *b ^= o;
+    }
+}
+
+// CBC with ciphertext stealing as in RFC 3962: the last two blocks are
+// always swapped and the final one truncated to the input length. The
+// initial cipher state is all zeros. Input is at least one block long.
+pub(crate) fn encrypt(cipher: &impl BlockCipher, data: &[u8]) -> Vec<u8> {
+    let mut blocks: Vec<[u8; BLOCK_SIZE]> = Vec::new();
+    let mut prev = [0u8; BLOCK_SIZE];
+    for chunk in data.chunks(BLOCK_SIZE) {
+        // short final chunks are zero padded, which leaves prev as is
+        xor_into(&mut prev, chunk);
+        cipher.encrypt_block(&mut prev);
+        blocks.push(prev);
+    }
+    let n = blocks.len();
+    if n > 1 {
+        blocks.swap(n - 2, n - 1);
+    }
+    let mut out = blocks.concat();
+    out.truncate(data.len());
+    out
+}
+
+pub(crate) fn decrypt(cipher: &impl BlockCipher, data: &[u8]) -> Vec<u8> {
+    let n = (data.len() + BLOCK_SIZE - 1) / BLOCK_SIZE;
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev = [0u8; BLOCK_SIZE];
+    for chunk in data.chunks(BLOCK_SIZE).take(n.saturating_sub(2)) {
+        let mut block = [0u8; BLOCK_SIZE];
+        block.copy_from_slice(chunk);
+        cipher.decrypt_block(&mut block);
+        xor_into(&mut block, &prev);
+        out.extend(block);
+        prev.copy_from_slice(chunk);
+    }
+    if n == 1 {
+        let mut block = [0u8; BLOCK_SIZE];
+        block.copy_from_slice(data);
+        cipher.decrypt_block(&mut block);
+        out.extend(block);
+        return out;
+    }
+    let tail = &data[(n - 1) * BLOCK_SIZE..];
+    let mut last = [0u8; BLOCK_SIZE];
+    last.copy_from_slice(&data[(n - 2) * BLOCK_SIZE..(n - 1) * BLOCK_SIZE]);
+    cipher.decrypt_block(&mut last);
+    // the stolen bytes complete the second to last ciphertext block
+    let mut second = last;
+    second[..tail.len()].copy_from_slice(tail);
+    xor_into(&mut last, tail);
+    cipher.decrypt_block(&mut second);
+    xor_into(&mut second, &prev);
+    out.extend(second);
+    out.extend(&last[..tail.len()]);
+    out
+}
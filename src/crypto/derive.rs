@@ -0,0 +1,108 @@
+use cipher::{BlockEncrypt, generic_array::GenericArray};
+use cipher::generic_array::typenum::Unsigned;
+use crate::crypto::nfold::nfold;
+
+
+// The block cipher an RFC 3961 simplified profile derives its keys with,
+// used in ECB mode one block at a time.
+pub trait DeriveCipher {
+    fn block_size(&self) -> usize;
+    fn encrypt_block(&self, block: &mut [u8]);
+}
+
+
+// DR from RFC 3961 section 5.1: the constant is n-folded to one block, which
+// is encrypted over and over, and the first `len` bytes of the chained
+// output are the result.
+pub fn dr<C: DeriveCipher + ?Sized>(cipher: &C, constant: &[u8], len: usize) -> Vec<u8> {
+    let mut block = nfold(constant, cipher.block_size() * 8);
+    let mut derived = Vec::with_capacity(len + block.len());
+    while derived.len() < len {
+        cipher.encrypt_block(&mut block);
+        derived.extend(&block);
+    }
+    derived.truncate(len);
+    derived
+}
+
+// DK: random-to-key over DR's output. `seed_len` is the etype's key
+// generation seed length, e.g. 21 bytes for the 168 bits of triple DES.
+pub fn dk<C, F>(cipher: &C, constant: &[u8], seed_len: usize, random_to_key: F) -> Vec<u8>
+where
+    C: DeriveCipher + ?Sized,
+    F: Fn(&[u8]) -> Vec<u8>,
+{
+    random_to_key(&dr(cipher, constant, seed_len))
+}
+
+// DR over the constant for a key usage: the usage number followed by 0x99
+// for Kc, 0xAA for Ke or 0x55 for Ki.
+pub fn derive_random<C: DeriveCipher + ?Sized>(cipher: &C, usage: u32, kind: u8, len: usize) -> Vec<u8> {
+    let mut constant = usage.to_be_bytes().to_vec();
+    constant.push(kind);
+    dr(cipher, &constant, len)
+}
+
+// Any RustCrypto block cipher, e.g. aes::Aes128 or des::TdesEde3
+impl<T: BlockEncrypt> DeriveCipher for T {
+    fn block_size(&self) -> usize {
+        T::BlockSize::USIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        BlockEncrypt::encrypt_block(self, GenericArray::from_mut_slice(block));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cipher::NewBlockCipher;
+    use des::TdesEde3;
+    use crate::crypto::des::KrbDes3;
+
+    fn h(data: &str) -> Vec<u8> {
+        hex::decode(data).unwrap()
+    }
+
+    // RFC 3961 appendix A.3: (key, constant, DR, DK)
+    const DES3: [(&str, &str, &str, &str); 9] = [
+        ("dce06b1f64c857a11c3db57c51899b2cc1791008ce973b92", "0000000155",
+            "935079d14490a75c3093c4a6e8c3b049c71e6ee705", "925179d04591a79b5d3192c4a7e9c289b049c71f6ee604cd"),
+        ("5e13d31c70ef765746578531cb51c15bf11ca82c97cee9f2", "00000001aa",
+            "9f58e5a047d894101c469845d67ae3c5249ed812f2", "9e58e5a146d9942a101c469845d67a20e3c4259ed913f207"),
+        ("98e6fd8a04a4b6859b75a176540b9752bad3ecd610a252bc", "0000000155",
+            "12fff90c773f956d13fc2ca0d0840349dbd39908eb", "13fef80d763e94ec6d13fd2ca1d085070249dad39808eabf"),
+        ("622aec25a2fe2cad7094680b7c64940280084c1a7cec92b5", "00000001aa",
+            "f8debf05b097e7dc0603686aca35d91fd9a5516a70", "f8dfbf04b097e6d9dc0702686bcb3489d91fd9a4516b703e"),
+        // the constant is "kerberos"
+        ("d3f8298ccb166438dcb9b93ee5a7629286a491f838f802fb", "6b65726265726f73",
+            "2270db565d2a3d64cfbfdc5305d4f778a6de42d9da", "2370da575d2a3da864cebfdc5204d56df779a7df43d9da43"),
+        ("c1081649ada74362e6a1459d01dfd30d67c2234c940704da", "0000000155",
+            "348056ec98fcc517171d2b4d7a9493af482d999175", "348057ec98fdc48016161c2a4c7a943e92ae492c989175f7"),
+        ("5d154af238f46713155719d55e2f1f790dd661f279a7917c", "00000001aa",
+            "a8818bc367dadacbe9a6c84627fb60c294b01215e5", "a8808ac267dada3dcbe9a7c84626fbc761c294b01315e5c1"),
+        ("798562e049852f57dc8c343ba17f2ca1d97394efc8adc443", "0000000155",
+            "c813f88b3be2b2f75424ce9175fbc8483b88c8713a", "c813f88a3be3b334f75425ce9175fbe3c8493b89c8703b49"),
+        ("26dce334b545292f2feab9a8701a89a4b99eb9942cecd016", "00000001aa",
+            "f58efc6f83f93e55e695fd252cf8fe59f7d5ba37ec", "f48ffd6e83f83e7354e694fd252cf83bfe58f7d5ba37ec5d"),
+    ];
+
+    #[test]
+    fn rfc3961_des3_dr_dk() {
+        for (key, constant, random, derived) in DES3 {
+            let cipher = TdesEde3::new_from_slice(&h(key)).unwrap();
+            assert_eq!(dr(&cipher, &h(constant), 21), h(random));
+            assert_eq!(dk(&cipher, &h(constant), 21, KrbDes3::random_to_key), h(derived));
+        }
+    }
+
+    #[test]
+    fn derive_random_matches_dr() {
+        let (key, constant, random, _) = DES3[0];
+        let cipher = TdesEde3::new_from_slice(&h(key)).unwrap();
+        assert_eq!(derive_random(&cipher, 1, 0x55, 21), h(random));
+        assert_eq!(derive_random(&cipher, 1, 0x55, 21), dr(&cipher, &h(constant), 21));
+    }
+}
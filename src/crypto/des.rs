@@ -1,8 +1,21 @@
 use crate::crypto::crypto::*;
+use crate::error::{Error, Result};
+use crate::crypto::nfold::nfold;
+use crate::crypto::derive;
+use cipher::NewBlockCipher;
 use bitvec::prelude::*;
-use des::Des;
+use des::{Des, TdesEde3};
 use block_modes::{BlockMode, Cbc, Ecb};
 use block_modes::block_padding::{ZeroPadding, NoPadding};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use md4::Md4;
+use md5::{Md5, Digest};
+use rand::RngCore;
+use crate::utils::utils::constant_time_eq;
+
+type DesCbcRaw = Cbc<Des, NoPadding>;
+type Des3Cbc = Cbc<TdesEde3, NoPadding>;
 
 
 const WEAK_KEYS: [&str; 16] = [
@@ -24,23 +37,23 @@ const WEAK_KEYS: [&str; 16] = [
     "FEE0FEE0FEF1FEF1",
 ];
 
-const WEAK_CORRECT: &str = "00000000000000F0";
+const WEAK_CORRECT: [u8; 8] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0];
 
 pub struct DesEcb;
-impl EncType for DesEcb {
-    fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
-        let cipher: Ecb<Des, NoPadding> = Ecb::new_from_slices(&key, &iv).unwrap();
-        let ciphertext = cipher.encrypt_vec(plaintext);
-        return ciphertext;
+impl DesEcb {
+    pub fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let cipher: Ecb<Des, NoPadding> = Ecb::new_from_slices(key, iv)
+            .map_err(|_| Error::BadKeyLength { expected: 8, actual: key.len() })?;
+        Ok(cipher.encrypt_vec(plaintext))
     }
 }
 
 pub struct DesCbc;
-impl EncType for DesCbc {
-    fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
-        let cipher: Cbc<Des, ZeroPadding> = Cbc::new_from_slices(&key, &iv).unwrap();
-        let ciphertext = cipher.encrypt_vec(plaintext);
-        return ciphertext;
+impl DesCbc {
+    pub fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let cipher: Cbc<Des, ZeroPadding> = Cbc::new_from_slices(key, iv)
+            .map_err(|_| Error::BadKeyLength { expected: 8, actual: key.len() })?;
+        Ok(cipher.encrypt_vec(plaintext))
     }
 }
 
@@ -48,48 +61,45 @@ impl EncType for DesCbc {
 
 pub struct KrbDes; 
 
-impl KrbEncType for KrbDes {
-
-    fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
-        DesCbc::encrypt(key, iv, plaintext)
-    }
-    fn string_to_key(secret: &str, salt: &str) -> Vec<u8>{
-        KrbDes::bytes_to_key(&secret.as_bytes(), &salt.as_bytes())
+impl KrbDes {
+    pub fn string_to_key(secret: &str, salt: &str) -> Result<Vec<u8>> {
+        KrbDes::bytes_to_key(secret.as_bytes(), salt.as_bytes())
     }
-    fn bytes_to_key(secret: &[u8], salt: &[u8]) -> Vec<u8> {
+
+    pub fn bytes_to_key(secret: &[u8], salt: &[u8]) -> Result<Vec<u8>> {
         let mut bytes = secret.to_vec();
         bytes.extend(salt);
         let mut bitstring = BitVec::<u8, Msb0>::from_vec(bytes.clone());
         // pad to multiple of 64
         bitstring.extend(bitvec![u8, Msb0; 0; 64 - (bitstring.len() % 64)]);
         let mut bitstring = KrbDes::fan_fold(bitstring);
-        info!("[DES] Folded: {}", hex::encode_upper(&bitstring.clone().into_vec()));
+        info!("[DES] Folded: {}", hex::encode_upper(bitstring.clone().into_vec()));
         KrbDes::key_correction(&mut bitstring);
-        info!("[DES] Parity Set: {}", hex::encode_upper(&bitstring.clone().into_vec()));
-        let key = KrbDes::encrypt(&bitstring.clone().into_vec(), &bitstring.into_vec(), &bytes);
+        info!("[DES] Parity Set: {}", hex::encode_upper(bitstring.clone().into_vec()));
+        let key = DesCbc::encrypt(&bitstring.clone().into_vec(), &bitstring.into_vec(), &bytes)?;
         let mut key = BitVec::<u8, Msb0>::from_slice(&key[&key.len() - 8 .. key.len()]);
         KrbDes::key_correction(&mut key);
-        return key.into_vec();
+        Ok(key.into_vec())
     }
 }
 
 impl KrbDes {
-    fn key_correction(mut key: &mut BitVec::<u8, Msb0>) {
-        KrbDes::add_parity_bits(&mut key);
-        if KrbDes::is_weak(&key) {
-            KrbDes::correct_weak(&mut key);
-            println!("corrected:{:02X?}", &key.clone().into_vec());
+    fn key_correction(key: &mut BitVec::<u8, Msb0>) {
+        KrbDes::add_parity_bits(key);
+        if KrbDes::is_weak(key) {
+            KrbDes::correct_weak(key);
+            info!("[DES] Corrected weak key: {}", hex::encode_upper(key.clone().into_vec()));
         };
     }
     
     
     fn is_weak(key: &BitVec::<u8, Msb0>) -> bool {
-        let lookup = hex::encode(&key.clone().into_vec()).to_uppercase();
-        return WEAK_KEYS.iter().any(|&x| x == lookup)
+        let lookup = hex::encode(key.clone().into_vec()).to_uppercase();
+        WEAK_KEYS.iter().any(|&x| x == lookup)
     }
     
     fn correct_weak(key: &mut BitVec::<u8, Msb0>) {
-        let correct = BitVec::<u8, Msb0>::from_vec(hex::decode(WEAK_CORRECT).unwrap());
+        let correct = BitVec::<u8, Msb0>::from_vec(WEAK_CORRECT.to_vec());
         for bit in 0..key.len() {
             let current = key[bit];
             key.set(bit, current ^ correct[bit]);
@@ -122,7 +132,607 @@ impl KrbDes {
         for bit in (7..64).step_by(8) {
             folded.insert(bit, false);
         }
-        return folded;
+        folded
+    }
+}
+
+
+impl KrbDes {
+    // The AFS string-to-key (the "afs3" salt type), keyed by the cell name
+    // rather than the usual salt. Passwords of up to eight characters go
+    // through Unix crypt(3), longer ones through two DES CBC checksums.
+    pub fn afs3_string_to_key(secret: &str, cell: &str) -> Result<Vec<u8>> {
+        let cell = cell.to_lowercase();
+        let key = if secret.len() <= DES_BLOCK_SIZE {
+            let mut password = [0u8; DES_BLOCK_SIZE];
+            for (p, c) in password.iter_mut().zip(cell.bytes()) {
+                *p = c;
+            }
+            for (p, s) in password.iter_mut().zip(secret.bytes()) {
+                *p ^= s;
+            }
+            for p in password.iter_mut().filter(|p| **p == 0) {
+                *p = b'X';
+            }
+            // AFS salts crypt() with "#~", which decodes to the same bits
+            // as "p1". pwhash deprecates DES crypt for new passwords, but
+            // here it is the algorithm being reproduced.
+            #[allow(deprecated)]
+            let hash = pwhash::unix_crypt::hash_with("p1", password)?;
+            hash.bytes().skip(2).take(DES_BLOCK_SIZE).map(|b| b << 1).collect()
+        } else {
+            let mut password = secret.as_bytes().to_vec();
+            password.extend(cell.bytes());
+            // each pass keys DES with the parity fixed input block and
+            // chains from the block as it was: "kerberos" first, then the
+            // first pass's checksum
+            let mut iv = b"kerberos".to_vec();
+            for _ in 0..2 {
+                let mut key = BitVec::<u8, Msb0>::from_slice(&iv);
+                KrbDes::add_parity_bits(&mut key);
+                iv = KrbDes::cbc_checksum(&key.into_vec(), &iv, &password)?;
+            }
+            iv
+        };
+        let mut key = BitVec::<u8, Msb0>::from_vec(key);
+        KrbDes::add_parity_bits(&mut key);
+        Ok(key.into_vec())
+    }
+
+    // The last block of the zero padded CBC encryption, or the IV itself
+    // for an empty message as in MIT's mit_des_cbc_cksum
+    fn cbc_checksum(key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        if data.is_empty() {
+            KrbDes::check_key(key)?;
+            return Ok(iv.to_vec());
+        }
+        let ciphertext = DesCbc::encrypt(key, iv, data)?;
+        Ok(ciphertext[ciphertext.len() - DES_BLOCK_SIZE..].to_vec())
+    }
+
+    fn check_key(key: &[u8]) -> Result<()> {
+        if key.len() != DES_BLOCK_SIZE {
+            return Err(Error::BadKeyLength { expected: DES_BLOCK_SIZE, actual: key.len() });
+        }
+        Ok(())
+    }
+
+    fn cbc(key: &[u8], iv: &[u8]) -> Result<DesCbcRaw> {
+        DesCbcRaw::new_from_slices(key, iv)
+            .map_err(|_| Error::BadKeyLength { expected: DES_BLOCK_SIZE, actual: key.len() })
+    }
+
+    // The CRC-32 of RFC 3961 section 6.1.3: ISO 3309 without the initial
+    // or final inversion, sent least significant byte first.
+    pub fn crc32(data: &[u8]) -> Vec<u8> {
+        let mut crc: u32 = 0;
+        for byte in data {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+        crc.to_le_bytes().to_vec()
+    }
+
+    // rsa-md4 (checksum type 2)
+    pub fn md4(data: &[u8]) -> Vec<u8> {
+        Md4::digest(data).to_vec()
+    }
+
+    // rsa-md5 (checksum type 7)
+    pub fn md5(data: &[u8]) -> Vec<u8> {
+        Md5::digest(data).to_vec()
+    }
+
+    fn encrypt_message(key: &[u8], hash: DesHash, iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut confounder = [0u8; DES_BLOCK_SIZE];
+        rand::thread_rng().fill_bytes(&mut confounder);
+        KrbDes::encrypt_with_confounder(key, hash, iv, &confounder, plaintext)
+    }
+
+    // confounder | checksum | msg | pad, with the checksum taken over the
+    // whole block while its own field is still zero
+    fn encrypt_with_confounder(key: &[u8], hash: DesHash, iv: &[u8],
+            confounder: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::check_key(key)?;
+        let mut data = confounder.to_vec();
+        data.extend(vec![0u8; hash.size()]);
+        data.extend(plaintext);
+        data.resize((data.len() + DES_BLOCK_SIZE - 1) / DES_BLOCK_SIZE * DES_BLOCK_SIZE, 0);
+        let checksum = hash.digest(&data);
+        data[DES_BLOCK_SIZE..DES_BLOCK_SIZE + hash.size()].copy_from_slice(&checksum);
+        Ok(KrbDes::cbc(key, iv)?.encrypt_vec(&data))
+    }
+
+    // Like des3, the zero padding comes back with the plaintext.
+    fn decrypt_message(key: &[u8], hash: DesHash, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::check_key(key)?;
+        if ciphertext.len() < DES_BLOCK_SIZE + hash.size()
+            || ciphertext.len() % DES_BLOCK_SIZE != 0 {
+            return Err(Error::BadCiphertext(format!(
+                "{} bytes is not whole blocks holding a confounder and checksum", ciphertext.len())));
+        }
+        let mut data = KrbDes::cbc(key, iv)?
+            .decrypt_vec(ciphertext)
+            .map_err(|_| Error::BadCiphertext("not whole blocks".to_string()))?;
+        let field = DES_BLOCK_SIZE..DES_BLOCK_SIZE + hash.size();
+        let checksum = data[field.clone()].to_vec();
+        data[field].fill(0);
+        if !constant_time_eq(&hash.digest(&data), &checksum) {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        Ok(data[DES_BLOCK_SIZE + hash.size()..].to_vec())
+    }
+
+    // rsa-md5-des and rsa-md4-des from RFC 3961 section 6.2.5: a confounded
+    // digest encrypted under the key XORed with F0F0F0F0F0F0F0F0.
+    fn keyed_checksum(key: &[u8], hash: DesHash, data: &[u8]) -> Result<Vec<u8>> {
+        let mut confounder = [0u8; DES_BLOCK_SIZE];
+        rand::thread_rng().fill_bytes(&mut confounder);
+        KrbDes::keyed_checksum_with_confounder(key, hash, &confounder, data)
+    }
+
+    fn keyed_checksum_with_confounder(key: &[u8], hash: DesHash,
+            confounder: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::check_key(key)?;
+        let mut message = confounder.to_vec();
+        message.extend(data);
+        let mut block = confounder.to_vec();
+        block.extend(hash.digest(&message));
+        Ok(KrbDes::cbc(&KrbDes::checksum_key(key), &[0; DES_BLOCK_SIZE])?.encrypt_vec(&block))
+    }
+
+    fn verify_keyed_checksum(key: &[u8], hash: DesHash, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbDes::check_key(key)?;
+        if checksum.len() != DES_BLOCK_SIZE + hash.size() {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        let block = KrbDes::cbc(&KrbDes::checksum_key(key), &[0; DES_BLOCK_SIZE])?
+            .decrypt_vec(checksum)
+            .map_err(|_| Error::IntegrityCheckFailed)?;
+        let (confounder, digest) = block.split_at(DES_BLOCK_SIZE);
+        let mut message = confounder.to_vec();
+        message.extend(data);
+        if !constant_time_eq(&hash.digest(&message), digest) {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        Ok(())
+    }
+
+    // des-mac (checksum type 4) from RFC 1510 section 6.4.5: the CBC
+    // checksum of the confounded message, encrypted with the confounder
+    // under the same key variant as rsa-md5-des.
+    pub fn des_mac(key: &[u8], _usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        let mut confounder = [0u8; DES_BLOCK_SIZE];
+        rand::thread_rng().fill_bytes(&mut confounder);
+        KrbDes::des_mac_with_confounder(key, &confounder, data)
+    }
+
+    fn des_mac_with_confounder(key: &[u8], confounder: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::check_key(key)?;
+        let mut message = confounder.to_vec();
+        message.extend(data);
+        let mut block = confounder.to_vec();
+        block.extend(KrbDes::cbc_checksum(key, &[0; DES_BLOCK_SIZE], &message)?);
+        Ok(KrbDes::cbc(&KrbDes::checksum_key(key), &[0; DES_BLOCK_SIZE])?.encrypt_vec(&block))
+    }
+
+    pub fn verify_des_mac(key: &[u8], _usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbDes::check_key(key)?;
+        if checksum.len() != 2 * DES_BLOCK_SIZE {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        let block = KrbDes::cbc(&KrbDes::checksum_key(key), &[0; DES_BLOCK_SIZE])?
+            .decrypt_vec(checksum)
+            .map_err(|_| Error::IntegrityCheckFailed)?;
+        // recompute with the confounder it carries
+        let expected = KrbDes::des_mac_with_confounder(key, &block[..DES_BLOCK_SIZE], data)?;
+        if !constant_time_eq(&expected, checksum) {
+            return Err(Error::IntegrityCheckFailed);
+        }
+        Ok(())
+    }
+
+    // des-mac-k (checksum type 5): the CBC checksum of the message, with
+    // the key doubling as the IV
+    pub fn des_mac_k(key: &[u8], _usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::check_key(key)?;
+        KrbDes::cbc_checksum(key, key, data)
+    }
+
+    fn checksum_key(key: &[u8]) -> Vec<u8> {
+        key.iter().map(|b| b ^ 0xF0).collect()
+    }
+}
+
+
+const DES_BLOCK_SIZE: usize = 8;
+
+// The unkeyed checksums the DES profiles carry inside the ciphertext
+#[derive(Clone, Copy)]
+enum DesHash {
+    Crc32,
+    Md4,
+    Md5,
+}
+
+impl DesHash {
+    fn size(&self) -> usize {
+        match self {
+            DesHash::Crc32 => 4,
+            DesHash::Md4 | DesHash::Md5 => 16,
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            DesHash::Crc32 => KrbDes::crc32(data),
+            DesHash::Md4 => KrbDes::md4(data),
+            DesHash::Md5 => KrbDes::md5(data),
+        }
+    }
+}
+
+
+// des-cbc-crc (etype 1): the key doubles as the IV.
+pub struct KrbDesCbcCrc;
+
+impl KrbCipher for KrbDesCbcCrc {
+    fn encrypt(key: &[u8], _usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::encrypt_message(key, DesHash::Crc32, key, plaintext)
+    }
+
+    fn decrypt(key: &[u8], _usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::decrypt_message(key, DesHash::Crc32, key, ciphertext)
+    }
+}
+
+impl KrbDesCbcCrc {
+    // rsa-md5-des (checksum type 8)
+    pub fn checksum(key: &[u8], _usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::keyed_checksum(key, DesHash::Md5, data)
+    }
+
+    pub fn verify_checksum(key: &[u8], _usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbDes::verify_keyed_checksum(key, DesHash::Md5, data, checksum)
+    }
+}
+
+
+// des-cbc-md4 (etype 2)
+pub struct KrbDesCbcMd4;
+
+impl KrbCipher for KrbDesCbcMd4 {
+    fn encrypt(key: &[u8], _usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::encrypt_message(key, DesHash::Md4, &[0; DES_BLOCK_SIZE], plaintext)
+    }
+
+    fn decrypt(key: &[u8], _usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::decrypt_message(key, DesHash::Md4, &[0; DES_BLOCK_SIZE], ciphertext)
+    }
+}
+
+impl KrbDesCbcMd4 {
+    // rsa-md4-des (checksum type 3)
+    pub fn checksum(key: &[u8], _usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::keyed_checksum(key, DesHash::Md4, data)
+    }
+
+    pub fn verify_checksum(key: &[u8], _usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbDes::verify_keyed_checksum(key, DesHash::Md4, data, checksum)
+    }
+}
+
+
+// des-cbc-md5 (etype 3)
+pub struct KrbDesCbcMd5;
+
+impl KrbCipher for KrbDesCbcMd5 {
+    fn encrypt(key: &[u8], _usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::encrypt_message(key, DesHash::Md5, &[0; DES_BLOCK_SIZE], plaintext)
+    }
+
+    fn decrypt(key: &[u8], _usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::decrypt_message(key, DesHash::Md5, &[0; DES_BLOCK_SIZE], ciphertext)
+    }
+}
+
+impl KrbDesCbcMd5 {
+    // rsa-md5-des (checksum type 8)
+    pub fn checksum(key: &[u8], _usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbDes::keyed_checksum(key, DesHash::Md5, data)
+    }
+
+    pub fn verify_checksum(key: &[u8], _usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbDes::verify_keyed_checksum(key, DesHash::Md5, data, checksum)
+    }
+}
+
+
+// des3-cbc-sha1-kd from RFC 3961 section 6.3: the simplified profile over
+// three-key triple DES with an untruncated HMAC-SHA1.
+pub struct KrbDes3;
+
+const DES3_KEY_SIZE: usize = 24;
+const DES3_BLOCK_SIZE: usize = 8;
+const DES3_HMAC_SIZE: usize = 20;
+const DES3_SEED_SIZE: usize = 21;
+
+impl KrbDes3 {
+    // raw triple DES CBC over whole blocks
+    fn cbc_encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let cipher = Des3Cbc::new_from_slices(key, iv)
+            .map_err(|_| Error::BadKeyLength { expected: DES3_KEY_SIZE, actual: key.len() })?;
+        Ok(cipher.encrypt_vec(plaintext))
+    }
+
+    pub fn string_to_key(secret: &str, salt: &str) -> Result<Vec<u8>> {
+        KrbDes3::bytes_to_key(secret.as_bytes(), salt.as_bytes())
+    }
+
+    pub fn bytes_to_key(secret: &[u8], salt: &[u8]) -> Result<Vec<u8>> {
+        let mut bytes = secret.to_vec();
+        bytes.extend(salt);
+        let tkey = KrbDes3::random_to_key(&nfold(&bytes, 168));
+        info!("[DES3] Folded: {}", hex::encode_upper(&tkey));
+        KrbDes3::dk(&tkey, b"kerberos")
+    }
+}
+
+impl KrbCipher for KrbDes3 {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut confounder = [0u8; DES3_BLOCK_SIZE];
+        rand::thread_rng().fill_bytes(&mut confounder);
+        KrbDes3::encrypt_with_confounder(key, usage, &confounder, plaintext)
+    }
+
+    // The zero padding added on encryption is part of the plaintext as far
+    // as the cipher is concerned, so it comes back here.
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes3::check_key(key)?;
+        if ciphertext.len() < DES3_BLOCK_SIZE + DES3_HMAC_SIZE
+            || (ciphertext.len() - DES3_HMAC_SIZE) % DES3_BLOCK_SIZE != 0 {
+            return Err(Error::BadCiphertext(format!(
+                "{} bytes is not whole blocks plus a checksum", ciphertext.len())));
+        }
+        let ke = KrbDes3::usage_key(key, usage, 0xAA)?;
+        let ki = KrbDes3::usage_key(key, usage, 0x55)?;
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - DES3_HMAC_SIZE);
+        let data = Des3Cbc::new_from_slices(&ke, &[0; DES3_BLOCK_SIZE])
+            .map_err(|_| Error::BadKeyLength { expected: DES3_KEY_SIZE, actual: ke.len() })?
+            .decrypt_vec(ciphertext)
+            .map_err(|_| Error::BadCiphertext("not whole blocks".to_string()))?;
+        KrbDes3::hmac(&ki, &data)?
+            .verify_slice(tag)
+            .map_err(|_| Error::IntegrityCheckFailed)?;
+        Ok(data[DES3_BLOCK_SIZE..].to_vec())
+    }
+}
+
+impl KrbDes3 {
+    fn check_key(key: &[u8]) -> Result<()> {
+        if key.len() != DES3_KEY_SIZE {
+            return Err(Error::BadKeyLength { expected: DES3_KEY_SIZE, actual: key.len() });
+        }
+        Ok(())
+    }
+
+    // Spreads each 56 bits over eight bytes: the low bits of the first
+    // seven bytes move into the eighth and every byte gets odd parity.
+    pub(crate) fn random_to_key(bits: &[u8]) -> Vec<u8> {
+        let mut key = Vec::with_capacity(DES3_KEY_SIZE);
+        for chunk in bits.chunks(7) {
+            let mut last = 0u8;
+            for (i, byte) in chunk.iter().enumerate() {
+                last |= (byte & 1) << (i + 1);
+            }
+            let mut des_key = BitVec::<u8, Msb0>::from_slice(chunk);
+            des_key.extend(BitVec::<u8, Msb0>::from_element(last));
+            KrbDes::key_correction(&mut des_key);
+            key.extend(des_key.into_vec());
+        }
+        key
+    }
+
+    fn cipher(key: &[u8]) -> Result<TdesEde3> {
+        TdesEde3::new_from_slice(key)
+            .map_err(|_| Error::BadKeyLength { expected: DES3_KEY_SIZE, actual: key.len() })
+    }
+
+    // DK from RFC 3961 with the 168 bit seed triple DES keys are made from
+    fn dk(key: &[u8], constant: &[u8]) -> Result<Vec<u8>> {
+        Ok(derive::dk(&KrbDes3::cipher(key)?, constant, DES3_SEED_SIZE, KrbDes3::random_to_key))
+    }
+
+    fn usage_key(key: &[u8], usage: u32, kind: u8) -> Result<Vec<u8>> {
+        let seed = derive::derive_random(&KrbDes3::cipher(key)?, usage, kind, DES3_SEED_SIZE);
+        Ok(KrbDes3::random_to_key(&seed))
+    }
+
+    pub fn usage_keys(key: &[u8], usage: u32) -> Result<UsageKeys> {
+        KrbDes3::check_key(key)?;
+        Ok(UsageKeys {
+            kc: KrbDes3::usage_key(key, usage, 0x99)?,
+            ke: KrbDes3::usage_key(key, usage, 0xAA)?,
+            ki: KrbDes3::usage_key(key, usage, 0x55)?,
+        })
+    }
+
+    fn hmac(key: &[u8], data: &[u8]) -> Result<Hmac<Sha1>> {
+        let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(key)
+            .map_err(|_| Error::BadKeyLength { expected: DES3_KEY_SIZE, actual: key.len() })?;
+        mac.update(data);
+        Ok(mac)
+    }
+
+    fn encrypt_with_confounder(key: &[u8], usage: u32,
+            confounder: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbDes3::check_key(key)?;
+        let ke = KrbDes3::usage_key(key, usage, 0xAA)?;
+        let ki = KrbDes3::usage_key(key, usage, 0x55)?;
+        let mut data = confounder.to_vec();
+        data.extend(plaintext);
+        data.resize((data.len() + DES3_BLOCK_SIZE - 1) / DES3_BLOCK_SIZE * DES3_BLOCK_SIZE, 0);
+        let mut ciphertext = KrbDes3::cbc_encrypt(&ke, &[0; DES3_BLOCK_SIZE], &data)?;
+        ciphertext.extend(KrbDes3::hmac(&ki, &data)?.finalize().into_bytes());
+        Ok(ciphertext)
+    }
+
+    // hmac-sha1-des3-kd (checksum type 12)
+    pub fn checksum(key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        KrbDes3::check_key(key)?;
+        let kc = KrbDes3::usage_key(key, usage, 0x99)?;
+        Ok(KrbDes3::hmac(&kc, data)?.finalize().into_bytes().to_vec())
+    }
+
+    pub fn verify_checksum(key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbDes3::check_key(key)?;
+        let kc = KrbDes3::usage_key(key, usage, 0x99)?;
+        KrbDes3::hmac(&kc, data)?
+            .verify_slice(checksum)
+            .map_err(|_| Error::IntegrityCheckFailed)
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(data: &str) -> Vec<u8> {
+        hex::decode(data).unwrap()
+    }
+
+    const PLAINTEXT: &[u8] = b"metaros known-answer test";
+    const CONFOUNDER: [u8; DES3_BLOCK_SIZE] = [0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7];
+
+    // RFC 3961 appendix A.2; the second key folds to the weak key
+    // E0E0E0E0F1F1F1F1 on the way
+    const DES_KEY: &str = "cbc22fae235298e3";
+
+    #[test]
+    fn des_string_to_key() {
+        for (secret, salt, key) in [
+            ("password", "ATHENA.MIT.EDUraeburn", DES_KEY),
+            ("11119999", "AAAAAAAA", "984054d0f1a73e31"),
+            ("potatoe", "WHITEHOUSE.GOVdanny", "df3d32a74fd92a01"),
+            ("\u{1d11e}", "EXAMPLE.COMpianist", "4ffb26bab0cd9413"),
+            ("\u{00df}", "ATHENA.MIT.EDUJuri\u{0161}i\u{0107}", "62c81a5232b5e69d"),
+        ] {
+            assert_eq!(KrbDes::string_to_key(secret, salt).unwrap(), h(key));
+        }
+    }
+
+    // RFC 3961 appendix A.5
+    #[test]
+    fn crc32() {
+        for (data, crc) in [
+            (&b"foo"[..], "33bc3273"),
+            (b"test0123456789", "d6883eb8"),
+            (b"MASSACHVSETTS INSTITVTE OF TECHNOLOGY", "f78041e3"),
+            (&[0x80, 0x00], "4b98833b"),
+            (&[0x00, 0x08], "3288db0e"),
+            (&[0x00, 0x80], "2083b8ed"),
+            (&[0x80], "2083b8ed"),
+            (&[0x80, 0x00, 0x00, 0x00], "3bb659ed"),
+            (&[0x00, 0x00, 0x00, 0x01], "96300777"),
+        ] {
+            assert_eq!(KrbDes::crc32(data), h(crc));
+        }
+    }
+
+    // fixtures/gen/des.py
+    #[test]
+    fn des_encrypt_known_answer() {
+        let key = h(DES_KEY);
+        let crc = h("d820e25c4077d06dd827b22a2f165b835fa89f84c70a0622b1c0048812e2d6602b7aae44e1c2e91a");
+        let md5 = h("ce42b61f9929dbcc384266b3108cf85949811070ea2438870e8760e46d60e22261bcb31d9cd6bcafc00b2f8a79541d114cebb7dd7102fd98");
+        assert_eq!(KrbDes::encrypt_with_confounder(&key, DesHash::Crc32, &key, &CONFOUNDER, PLAINTEXT).unwrap(), crc);
+        assert_eq!(KrbDes::encrypt_with_confounder(&key, DesHash::Md5, &[0; DES_BLOCK_SIZE], &CONFOUNDER, PLAINTEXT).unwrap(), md5);
+        assert!(KrbDesCbcCrc::decrypt(&key, 2, &crc).unwrap().starts_with(PLAINTEXT));
+        assert!(KrbDesCbcMd5::decrypt(&key, 2, &md5).unwrap().starts_with(PLAINTEXT));
+        assert!(matches!(KrbDesCbcMd4::decrypt(&key, 2, &md5), Err(Error::IntegrityCheckFailed)));
+    }
+
+    // fixtures/gen/des.py; the first two go through crypt(3), the second
+    // with a cell short enough to leave zero bytes
+    #[test]
+    fn afs3_string_to_key() {
+        for (secret, cell, key) in [
+            ("password", "ATHENA.MIT.EDU", "ad8a68e5f4a764c4"),
+            ("abc", "cs", "dc62689891a19894"),
+            ("a longer afs password", "ATHENA.MIT.EDU", "e6d68943641cfb97"),
+            ("potatoe1potatoe2", "WHITEHOUSE.GOV", "f8ecd979519dbf2f"),
+        ] {
+            assert_eq!(KrbDes::afs3_string_to_key(secret, cell).unwrap(), h(key));
+        }
+    }
+
+    // fixtures/gen/checksums.py, with the same key and confounder
+    #[test]
+    fn des_keyed_checksums() {
+        let key = h(DES_KEY);
+        for (hash, expected) in [
+            (DesHash::Md4, "fa2771a5416c9c6ff6a34d4f19b92d7d49a10a79059e42c7"),
+            (DesHash::Md5, "fa2771a5416c9c6f8999afc819fff1ed7cd767540e662df3"),
+        ] {
+            let checksum = KrbDes::keyed_checksum_with_confounder(&key, hash, &CONFOUNDER, PLAINTEXT).unwrap();
+            assert_eq!(checksum, h(expected));
+            KrbDes::verify_keyed_checksum(&key, hash, PLAINTEXT, &checksum).unwrap();
+        }
+        let mac = KrbDes::des_mac_with_confounder(&key, &CONFOUNDER, PLAINTEXT).unwrap();
+        assert_eq!(mac, h("fa2771a5416c9c6f65b734f1c48f52e4"));
+        KrbDes::verify_des_mac(&key, 0, PLAINTEXT, &mac).unwrap();
+        assert!(matches!(KrbDes::verify_des_mac(&key, 0, b"tampered", &mac), Err(Error::IntegrityCheckFailed)));
+        assert_eq!(KrbDes::des_mac_k(&key, 0, PLAINTEXT).unwrap(), h("d1b1d72e836ff4ad"));
+        // an empty message leaves the IV, which des-mac-k takes from the key
+        assert_eq!(KrbDes::des_mac_k(&key, 0, b"").unwrap(), key);
+    }
+
+    // RFC 3961 appendix A.4
+    const DES3_KEY: &str = "850bb51358548cd05e86768c313e3bfef7511937dcf72c3e";
+
+    #[test]
+    fn des3_string_to_key() {
+        for (secret, salt, key) in [
+            ("password", "ATHENA.MIT.EDUraeburn", DES3_KEY),
+            ("potatoe", "WHITEHOUSE.GOVdanny", "dfcd233dd0a43204ea6dc437fb15e061b02979c1f74f377a"),
+            ("penny", "EXAMPLE.COMbuckaroo", "6d2fcdf2d6fbbc3ddcadb5da5710a23489b0d3b69d5d9d4a"),
+            ("\u{00df}", "ATHENA.MIT.EDUJuri\u{0161}i\u{0107}", "16d5a40e1ce3bacb61b9dce00470324c831973a7b952feb0"),
+            ("\u{1d11e}", "EXAMPLE.COMpianist", "85763726585dbc1cce6ec43e1f751f07f1c4cbb098f40b19"),
+        ] {
+            assert_eq!(KrbDes3::string_to_key(secret, salt).unwrap(), h(key));
+        }
+    }
+
+    // Kc/Ke/Ki, ciphertext and checksum below are from fixtures/gen/des3.py
+    #[test]
+    fn des3_usage_keys() {
+        let keys = KrbDes3::usage_keys(&h(DES3_KEY), 2).unwrap();
+        assert_eq!(keys.kc, h("f78c496d16e6c2dae0e0b6c24057a84c0426aeef26fd6dce"));
+        assert_eq!(keys.ke, h("5b5723d0b634cb684c3eba5264e9a70d52e683231ad3c4ce"));
+        assert_eq!(keys.ki, h("a77c94980e9b7345a81525c423a737ce67f4cd91b6b3da45"));
+    }
+
+    #[test]
+    fn des3_encrypt_known_answer() {
+        let key = h(DES3_KEY);
+        let expected = h("f3935f9d7179cb54204bf6d65f8d68acfda6996e16031ecebe245039ed77d24c1ff7dbd276326555c656c7a5c129e2f99645b0305681df4e8707c5c8");
+        assert_eq!(KrbDes3::encrypt_with_confounder(&key, 3, &CONFOUNDER, PLAINTEXT).unwrap(), expected);
+        // the padding to whole blocks comes back with the plaintext
+        let plaintext = <KrbDes3 as KrbCipher>::decrypt(&key, 3, &expected).unwrap();
+        assert_eq!(&plaintext[..PLAINTEXT.len()], PLAINTEXT);
+        assert_eq!(plaintext[PLAINTEXT.len()..], [0; 7]);
+        assert!(matches!(<KrbDes3 as KrbCipher>::decrypt(&key, 4, &expected), Err(Error::IntegrityCheckFailed)));
+    }
+
+    #[test]
+    fn des3_checksum() {
+        let key = h(DES3_KEY);
+        let checksum = h("d8543ea6c19bc347e3c4329c9ce1bbbfc5da0cf4");
+        assert_eq!(KrbDes3::checksum(&key, 17, PLAINTEXT).unwrap(), checksum);
+        KrbDes3::verify_checksum(&key, 17, PLAINTEXT, &checksum).unwrap();
+        assert!(KrbDes3::verify_checksum(&key, 17, b"tampered", &checksum).is_err());
+    }
+}
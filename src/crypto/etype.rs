@@ -0,0 +1,257 @@
+use crate::crypto::crypto::{KrbCipher, UsageKeys, WinHash};
+use crate::crypto::aes::{KrbAes128, KrbAes256, KrbAes128Sha256, KrbAes256Sha384};
+use crate::crypto::camellia::{KrbCamellia128, KrbCamellia256};
+use crate::crypto::des::{KrbDes, KrbDes3, KrbDesCbcCrc, KrbDesCbcMd4, KrbDesCbcMd5};
+use crate::crypto::lanman::NTLanMan;
+use crate::crypto::rc4::{KrbRc4, KrbRc4Export};
+use crate::kerberos::reference;
+use crate::error::{Error, Result};
+
+
+// What callers need to know about an etype without naming its type.
+// The string-to-key parameters are the opaque s2kparams of RFC 3961, as a
+// KDC would send them in ETYPE-INFO2.
+pub struct EtypeInfo {
+    pub id: i32,
+    pub key_size: usize,
+    pub block_size: usize,
+    pub checksum_size: usize,
+    pub s2k_params: &'static [u8],
+}
+
+
+// An object-safe view of an etype, so that code can pick one at runtime by
+// its number. `params` of None means the etype's default s2kparams.
+pub trait KrbEtype: Sync {
+    fn info(&self) -> &'static EtypeInfo;
+    fn string_to_key(&self, secret: &str, salt: &str, params: Option<&[u8]>) -> Result<Vec<u8>>;
+    fn encrypt(&self, key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>>;
+    fn decrypt(&self, key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>>;
+    fn usage_keys(&self, key: &[u8], usage: u32) -> Result<UsageKeys>;
+    // false for the etypes whose string-to-key ignores the salt
+    fn salted(&self) -> bool;
+
+    fn id(&self) -> i32 {
+        self.info().id
+    }
+
+    fn name(&self) -> &'static str {
+        reference::ENCRYPTION_TYPES.name(self.id())
+    }
+
+    fn key_size(&self) -> usize {
+        self.info().key_size
+    }
+
+    fn block_size(&self) -> usize {
+        self.info().block_size
+    }
+
+    fn checksum_size(&self) -> usize {
+        self.info().checksum_size
+    }
+
+    fn default_s2k_params(&self) -> &'static [u8] {
+        self.info().s2k_params
+    }
+}
+
+
+// The registry and the KrbEtype impls behind it. Each etype's EtypeInfo
+// becomes a static of the given name.
+macro_rules! etypes {
+    ($($etype:ident => $info:ident { $($field:ident: $value:expr),* $(,)? },
+            $s2k:ident, $keys:ident;)*) => {
+        $(
+            static $info: EtypeInfo = EtypeInfo { $($field: $value),* };
+            etype_impl!($etype, $info, $s2k, $keys);
+        )*
+
+        static ETYPES: &[&dyn KrbEtype] = &[$(&$etype),*];
+    };
+}
+
+// The string-to-key kinds are:
+//   des         the DES algorithm, or AFS's when the s2kparams are 1
+//   simplified  the simplified profile's, which takes no parameters
+//   pbkdf2      PBKDF2 with the s2kparams as its iteration count
+//   nt_hash     the NT hash of the password, which takes no salt
+// and usage keys are either `derived` or, for the DES profiles, the
+// `base_key` for every usage.
+macro_rules! etype_impl {
+    ($etype:ident, $info:ident, $s2k:ident, $keys:ident) => {
+        impl KrbEtype for $etype {
+            fn info(&self) -> &'static EtypeInfo {
+                &$info
+            }
+
+            fn string_to_key(&self, secret: &str, salt: &str, params: Option<&[u8]>) -> Result<Vec<u8>> {
+                etype_impl!(@s2k $s2k, $etype, self, secret, salt, params)
+            }
+
+            fn encrypt(&self, key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+                <$etype as KrbCipher>::encrypt(key, usage, plaintext)
+            }
+
+            fn decrypt(&self, key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+                <$etype as KrbCipher>::decrypt(key, usage, ciphertext)
+            }
+
+            fn usage_keys(&self, key: &[u8], usage: u32) -> Result<UsageKeys> {
+                etype_impl!(@keys $keys, $etype, self, key, usage)
+            }
+
+            fn salted(&self) -> bool {
+                etype_impl!(@salted $s2k)
+            }
+        }
+    };
+    (@s2k des, $etype:ty, $self:ident, $secret:ident, $salt:ident, $params:ident) => {
+        des_string_to_key($secret, $salt, $params)
+    };
+    (@s2k simplified, $etype:ty, $self:ident, $secret:ident, $salt:ident, $params:ident) => {{
+        default_params($self, $params)?;
+        <$etype>::string_to_key($secret, $salt)
+    }};
+    (@s2k pbkdf2, $etype:ty, $self:ident, $secret:ident, $salt:ident, $params:ident) => {
+        <$etype>::string_to_key_with_iterations($secret, $salt, iterations($self, $params)?)
+    };
+    (@s2k nt_hash, $etype:ty, $self:ident, $secret:ident, $salt:ident, $params:ident) => {{
+        let _ = $salt;
+        default_params($self, $params)?;
+        NTLanMan::from_string($secret)
+    }};
+    (@keys derived, $etype:ty, $self:ident, $key:ident, $usage:ident) => {
+        <$etype>::usage_keys($key, $usage)
+    };
+    (@keys base_key, $etype:ty, $self:ident, $key:ident, $usage:ident) => {{
+        let _ = ($key, $usage);
+        Err(Error::NoUsageKeys($self.name()))
+    }};
+    (@salted nt_hash) => { false };
+    (@salted $s2k:ident) => { true };
+}
+
+
+// Every etype metaros implements, one per line: the type, its EtypeInfo,
+// how it turns a password into a key (see etype_impl!) and whether it
+// derives usage keys. Adding an etype is a matter of listing it here.
+etypes! {
+    KrbDesCbcCrc => DES_CBC_CRC {
+        id: 1, key_size: 8, block_size: 8, checksum_size: 4, s2k_params: &[0] }, des, base_key;
+    KrbDesCbcMd4 => DES_CBC_MD4 {
+        id: 2, key_size: 8, block_size: 8, checksum_size: 16, s2k_params: &[0] }, des, base_key;
+    KrbDesCbcMd5 => DES_CBC_MD5 {
+        id: 3, key_size: 8, block_size: 8, checksum_size: 16, s2k_params: &[0] }, des, base_key;
+    KrbDes3 => DES3_CBC_SHA1_KD {
+        id: 16, key_size: 24, block_size: 8, checksum_size: 20, s2k_params: &[] }, simplified, derived;
+    KrbAes128 => AES128_CTS_HMAC_SHA1_96 {
+        id: 17, key_size: 16, block_size: 16, checksum_size: 12, s2k_params: &[0, 0, 0x10, 0] }, pbkdf2, derived;
+    KrbAes256 => AES256_CTS_HMAC_SHA1_96 {
+        id: 18, key_size: 32, block_size: 16, checksum_size: 12, s2k_params: &[0, 0, 0x10, 0] }, pbkdf2, derived;
+    KrbAes128Sha256 => AES128_CTS_HMAC_SHA256_128 {
+        id: 19, key_size: 16, block_size: 16, checksum_size: 16, s2k_params: &[0, 0, 0x80, 0] }, pbkdf2, derived;
+    KrbAes256Sha384 => AES256_CTS_HMAC_SHA384_192 {
+        id: 20, key_size: 32, block_size: 16, checksum_size: 24, s2k_params: &[0, 0, 0x80, 0] }, pbkdf2, derived;
+    // RC4 is a stream cipher, so its "blocks" are single bytes
+    KrbRc4 => RC4_HMAC {
+        id: 23, key_size: 16, block_size: 1, checksum_size: 16, s2k_params: &[] }, nt_hash, derived;
+    KrbRc4Export => RC4_HMAC_EXP {
+        id: 24, key_size: 16, block_size: 1, checksum_size: 16, s2k_params: &[] }, nt_hash, derived;
+    KrbCamellia128 => CAMELLIA128_CTS_CMAC {
+        id: 25, key_size: 16, block_size: 16, checksum_size: 16, s2k_params: &[0, 0, 0x80, 0] }, pbkdf2, derived;
+    KrbCamellia256 => CAMELLIA256_CTS_CMAC {
+        id: 26, key_size: 32, block_size: 16, checksum_size: 16, s2k_params: &[0, 0, 0x80, 0] }, pbkdf2, derived;
+}
+
+pub fn all() -> impl Iterator<Item = &'static dyn KrbEtype> {
+    ETYPES.iter().copied()
+}
+
+pub fn by_id(id: i32) -> Option<&'static dyn KrbEtype> {
+    all().find(|e| e.id() == id)
+}
+
+// Accepts anything reference::ENCRYPTION_TYPES knows: a name, a shortname
+// or a number.
+pub fn lookup(query: &str) -> Result<&'static dyn KrbEtype> {
+    let id = reference::ENCRYPTION_TYPES.lookup(query)
+        .ok_or_else(|| Error::UnknownEtype(query.to_string()))?
+        .id;
+    by_id(id).ok_or_else(|| Error::UnsupportedEtype(query.to_string()))
+}
+
+
+// For the etypes whose string-to-key takes no parameters beyond their
+// defaults.
+fn default_params(etype: &dyn KrbEtype, params: Option<&[u8]>) -> Result<()> {
+    match params {
+        Some(params) if params != etype.default_s2k_params() =>
+            Err(Error::BadS2kParams { etype: etype.name(), params: hex::encode_upper(params) }),
+        _ => Ok(()),
+    }
+}
+
+// The PBKDF2 etypes take the iteration count as a 32 bit big-endian number
+fn iterations(etype: &dyn KrbEtype, params: Option<&[u8]>) -> Result<u32> {
+    match params.unwrap_or(etype.default_s2k_params()) {
+        &[a, b, c, d] if [a, b, c, d] != [0; 4] => Ok(u32::from_be_bytes([a, b, c, d])),
+        params => Err(Error::BadS2kParams { etype: etype.name(), params: hex::encode_upper(params) }),
+    }
+}
+
+// The DES s2kparams are a single byte: 0 for the usual algorithm and 1 for
+// the AFS one, with the salt holding the cell name.
+fn des_string_to_key(secret: &str, salt: &str, params: Option<&[u8]>) -> Result<Vec<u8>> {
+    match params {
+        None | Some([]) | Some([0]) => KrbDes::string_to_key(secret, salt),
+        Some([1]) => KrbDes::afs3_string_to_key(secret, salt),
+        Some(params) => Err(Error::BadS2kParams { etype: "des", params: hex::encode_upper(params) }),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_matches_reference() {
+        for etype in all() {
+            assert_eq!(by_id(etype.id()).unwrap().name(), etype.name());
+            assert_eq!(lookup(etype.name()).unwrap().id(), etype.id());
+            assert_eq!(lookup(&etype.id().to_string()).unwrap().id(), etype.id());
+            assert_ne!(etype.name(), "unknown");
+        }
+        assert_eq!(lookup("aes256").unwrap().id(), 18);
+        assert!(matches!(lookup("des3-cbc-sha1"), Err(Error::UnsupportedEtype(_))));
+        assert!(matches!(lookup("bogus"), Err(Error::UnknownEtype(_))));
+    }
+
+    #[test]
+    fn string_to_key_kinds() {
+        let key = lookup("des-cbc-md5").unwrap().string_to_key("password", "ATHENA.MIT.EDUraeburn", None).unwrap();
+        assert_eq!(hex::encode(key), "cbc22fae235298e3");
+        let rc4 = lookup("rc4-hmac").unwrap();
+        assert!(!rc4.salted());
+        assert_eq!(hex::encode(rc4.string_to_key("password", "ignored", None).unwrap()), "8846f7eaee8fb117ad06bdd830b7586c");
+        assert!(matches!(rc4.string_to_key("password", "", Some(&[1])), Err(Error::BadS2kParams { .. })));
+        assert!(lookup("des3").unwrap().usage_keys(&[0; 24], 2).is_ok());
+        assert!(matches!(lookup("des-crc").unwrap().usage_keys(&[0; 8], 2), Err(Error::NoUsageKeys(_))));
+    }
+
+    #[test]
+    fn iteration_params() {
+        let aes = lookup("aes128").unwrap();
+        assert_eq!(iterations(aes, None).unwrap(), 4096);
+        assert_eq!(iterations(lookup("aes256-sha2").unwrap(), None).unwrap(), 32768);
+        assert_eq!(iterations(lookup("camellia128").unwrap(), None).unwrap(), 32768);
+        assert_eq!(iterations(aes, Some(&[0, 0, 0x04, 0xb0])).unwrap(), 1200);
+        for params in [&[0, 0, 0, 0][..], &[1], &[0, 0, 0, 0, 1]] {
+            assert!(matches!(iterations(aes, Some(params)), Err(Error::BadS2kParams { .. })));
+        }
+        // RFC 3962 appendix B, two iterations
+        let key = aes.string_to_key("password", "ATHENA.MIT.EDUraeburn", Some(&2u32.to_be_bytes())).unwrap();
+        assert_eq!(hex::encode(key), "c651bf29e2300ac27fa469d693bdda13");
+    }
+}
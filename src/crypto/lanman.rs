@@ -1,6 +1,7 @@
 use crate::crypto::des::DesEcb;
 use md4::{Md4, Digest};
-use crate::crypto::crypto::{WinHash, EncType};
+use crate::crypto::crypto::WinHash;
+use crate::error::Result;
 use bitvec::prelude::*;
 
 
@@ -9,7 +10,7 @@ pub struct LanMan;
 
 impl WinHash for LanMan {
 
-    fn from_bytes(plaintext: &[u8]) -> Vec<u8> {
+    fn from_bytes(plaintext: &[u8]) -> Result<Vec<u8>> {
         let mut plaintext: Vec<u8> = plaintext.iter().map(|x| x.to_ascii_uppercase()).collect();
         plaintext.truncate(14);
         plaintext.extend(vec![0; 14 - &plaintext.len()]);
@@ -21,15 +22,15 @@ impl WinHash for LanMan {
         let mut key = Vec::new();
         info!("[LM] clear 1/2: {}", hex::encode_upper(&i_keys[..8]));
         info!("[LM] clear 2/2: {}", hex::encode_upper(&i_keys[8..]));
-        key.extend(DesEcb::encrypt(&i_keys[..8], &vec![0; 8], &String::from("KGS!@#$%").into_bytes()));
+        key.extend(DesEcb::encrypt(&i_keys[..8], &[0; 8], &String::from("KGS!@#$%").into_bytes())?);
         info!("[LM] hash 1/2: {}", hex::encode_upper(&key));
-        key.extend(DesEcb::encrypt(&i_keys[8..], &vec![0; 8], &String::from("KGS!@#$%").into_bytes()));
+        key.extend(DesEcb::encrypt(&i_keys[8..], &[0; 8], &String::from("KGS!@#$%").into_bytes())?);
         info!("[LM] hash 2/2: {}", hex::encode_upper(&key[8..]));
-        return key;
+        Ok(key)
     }
 
-    fn from_string(plaintext: &str) -> Vec<u8> {
-        return LanMan::from_bytes(plaintext.as_bytes());
+    fn from_string(plaintext: &str) -> Result<Vec<u8>> {
+        LanMan::from_bytes(plaintext.as_bytes())
     }
 
 }
@@ -37,18 +38,18 @@ impl WinHash for LanMan {
 pub struct NTLanMan;
 
 impl WinHash for NTLanMan {
-    fn from_bytes(plaintext: &[u8]) -> Vec<u8> {
-        return Md4::new()
-            .chain_update(&plaintext)
-            .finalize().to_vec();
+    fn from_bytes(plaintext: &[u8]) -> Result<Vec<u8>> {
+        Ok(Md4::new()
+            .chain_update(plaintext)
+            .finalize().to_vec())
     }
-    fn from_string(plaintext: &str) -> Vec<u8> {
-        let plaintext = plaintext.encode_utf16().into_iter();
+    fn from_string(plaintext: &str) -> Result<Vec<u8>> {
+        let plaintext = plaintext.encode_utf16();
         let mut plaintext_fmt = Vec::new();
         for c in plaintext {
-            plaintext_fmt.extend(c.clone().to_le_bytes());
+            plaintext_fmt.extend(c.to_le_bytes());
         }
-        return NTLanMan::from_bytes(&plaintext_fmt);
+        NTLanMan::from_bytes(&plaintext_fmt)
     }
 }
 
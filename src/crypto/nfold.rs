@@ -0,0 +1,92 @@
+use bitvec::prelude::*;
+use crate::utils::utils::lcm;
+
+
+// n-fold from RFC 3961 section 5.1: the input is repeated up to the lcm of
+// the two lengths, each copy rotated 13 bits further, and the result is
+// summed in out_bits chunks with ones' complement addition. An out_bits that
+// is not a whole number of bytes leaves the last byte zero padded.
+pub fn nfold(input: &[u8], out_bits: usize) -> Vec<u8> {
+    // there is nothing to repeat, and the lcm would divide by zero
+    if input.is_empty() || out_bits == 0 {
+        return vec![0; (out_bits + 7) / 8];
+    }
+    let mut constant = BitVec::<_, Msb0>::from_vec(input.to_vec());
+    // a rotation by the input's length is no rotation at all
+    let offset = 13 % constant.len();
+
+    let mut long_constant = constant.clone();
+    for _ in 1..(lcm(&out_bits, &constant.len()) / constant.len()) {
+        constant.rotate_right(offset);
+        long_constant.extend(constant.clone());
+    };
+
+    let mut keybits = long_constant[0..out_bits].to_bitvec();
+    for i in 1 ..(long_constant.len()/out_bits) {
+        keybits = add_chunks_ones_comp(
+            &keybits,
+            &long_constant[i * out_bits..((i + 1) * out_bits)],
+            &out_bits);
+    }
+    keybits.to_bitvec().into_vec()
+}
+
+
+fn add_chunks_ones_comp(first: &BitSlice<u8, Msb0>, second: &BitSlice<u8, Msb0>, out_bits: &usize) -> BitVec::<u8, Msb0> {
+    let mut result = first.to_bitvec();
+    let mut carry = false;
+    let mut set;
+    for b in (0 ..*out_bits).rev(){
+        if result[b] & second[b] {
+            set = carry;
+            carry = true;
+        } else if result[b] ^ second[b] {
+            set = !carry;
+        } else {
+            set = carry;
+            carry = false;
+        }
+        result.set(b, set);
+
+    }
+    if carry {
+        let mut carrybits = bitvec![u8, Msb0; 0; *out_bits];
+        carrybits.set(*out_bits -1, carry);
+        result = add_chunks_ones_comp(&result, &carrybits, out_bits);
+    }
+    result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 3961 appendix A.1: (output bits, input, n-fold)
+    const NFOLD: [(usize, &str, &str); 11] = [
+        (64, "012345", "be072631276b1955"),
+        (56, "password", "78a07b6caf85fa"),
+        (64, "Rough Consensus, and Running Code", "bb6ed30870b7f0e0"),
+        (168, "password", "59e4a8ca7c0385c3c37b3f6d2000247cb6e6bd5b3e"),
+        (192, "MASSACHVSETTS INSTITVTE OF TECHNOLOGY", "db3b0d8f0b061e603282b308a50841229ad798fab9540c1b"),
+        (168, "Q", "518a54a215a8452a518a54a215a8452a518a54a215"),
+        (168, "ba", "fb25d531ae8974499f52fd92ea9857c4ba24cf297e"),
+        (64, "kerberos", "6b65726265726f73"),
+        (128, "kerberos", "6b65726265726f737b9b5b2b93132b93"),
+        (168, "kerberos", "8372c236344e5f1550cd0747e15d62ca7a5a3bcea4"),
+        (256, "kerberos", "6b65726265726f737b9b5b2b93132b935c9bdcdad95c9899c4cae4dee6d6cae4"),
+    ];
+
+    #[test]
+    fn rfc3961_nfold() {
+        for (bits, input, expected) in NFOLD {
+            assert_eq!(hex::encode(nfold(input.as_bytes(), bits)), expected, "{}-fold({:?})", bits, input);
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(nfold(b"", 64), [0; 8]);
+        assert!(nfold(b"kerberos", 0).is_empty());
+    }
+}
@@ -1,20 +1,282 @@
-use crate::crypto::crypto::{KrbEncType, WinHash};
-use crate::crypto::lanman::NTLanMan;
+use hmac::{Hmac, Mac};
+use md5::{Md5, Digest};
+use rand::RngCore;
+use crate::crypto::crypto::{KrbCipher, UsageKeys};
+use crate::error::{Error, Result};
 
+type HmacMd5 = Hmac<Md5>;
+
+const CHECKSUM_SIZE: usize = 16;
+const CONFOUNDER_SIZE: usize = 8;
+
+
+// RC4-HMAC from RFC 4757. The key is the NT hash of the password and every
+// message key comes from HMAC-MD5 over the (Windows) key usage number.
 pub struct KrbRc4;
 
-impl KrbEncType for KrbRc4 {
-    fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
-        println!("not yet implemented{:?}{:?}", iv, plaintext);
-        return key.clone().to_vec();
+impl KrbCipher for KrbRc4 {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbRc4::encrypt_message(key, usage, false, plaintext)
+    }
+
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbRc4::decrypt_message(key, usage, false, ciphertext)
+    }
+}
+
+impl KrbRc4 {
+    // Windows numbers a few usages differently from RFC 4120. The RFC also
+    // maps the TGS-REP part under a subkey (9) to 8, which its errata and
+    // every implementation leave as 9.
+    pub fn translate_usage(usage: u32) -> u32 {
+        match usage {
+            // the AS-REP encrypted part
+            3 => 8,
+            // GSS wrap token signing
+            23 => 13,
+            _ => usage,
+        }
+    }
+
+    fn hmac(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let mut mac = HmacMd5::new_from_slice(key)
+            .map_err(|_| Error::BadKeyLength { expected: 16, actual: key.len() })?;
+        mac.update(data);
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+
+    // Returns K1, which keys the RC4 stream, and K2, which keys the
+    // checksum. They only differ in the 40 bit export variant, where most
+    // of K1 is overwritten.
+    fn message_keys(key: &[u8], usage: u32, export: bool) -> Result<(Vec<u8>, Vec<u8>)> {
+        if key.len() != 16 {
+            return Err(Error::BadKeyLength { expected: 16, actual: key.len() });
+        }
+        let usage = KrbRc4::translate_usage(usage).to_le_bytes();
+        let k2 = if export {
+            let mut salt = b"fortybits\0".to_vec();
+            salt.extend(usage);
+            KrbRc4::hmac(key, &salt)?
+        } else {
+            KrbRc4::hmac(key, &usage)?
+        };
+        let mut k1 = k2.clone();
+        if export {
+            k1[7..].fill(0xAB);
+        }
+        Ok((k1, k2))
+    }
+
+    // RC4-HMAC is not a simplified profile, so these are its nearest
+    // equivalents: the usage independent signing key, K1 (which K3 is made
+    // from for each message) and K2 (which keys the HMAC).
+    pub fn usage_keys(key: &[u8], usage: u32) -> Result<UsageKeys> {
+        KrbRc4::profile_keys(key, usage, false)
+    }
+
+    fn profile_keys(key: &[u8], usage: u32, export: bool) -> Result<UsageKeys> {
+        let (k1, k2) = KrbRc4::message_keys(key, usage, export)?;
+        Ok(UsageKeys {
+            kc: KrbRc4::hmac(key, b"signaturekey\0")?,
+            ke: k1,
+            ki: k2,
+        })
     }
 
-    fn string_to_key(secret:&str, salt: &str) -> Vec<u8> {
-        return NTLanMan::from_string(&secret);
+    fn encrypt_message(key: &[u8], usage: u32, export: bool, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut confounder = [0u8; CONFOUNDER_SIZE];
+        rand::thread_rng().fill_bytes(&mut confounder);
+        KrbRc4::encrypt_with_confounder(key, usage, export, &confounder, plaintext)
     }
 
-    fn bytes_to_key(secret: &[u8], salt: &[u8]) -> Vec<u8> {
-        return NTLanMan::from_bytes(&secret);
+    fn encrypt_with_confounder(key: &[u8], usage: u32, export: bool,
+            confounder: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let (k1, k2) = KrbRc4::message_keys(key, usage, export)?;
+        let mut data = confounder.to_vec();
+        data.extend(plaintext);
+        let mut ciphertext = KrbRc4::hmac(&k2, &data)?;
+        let k3 = KrbRc4::hmac(&k1, &ciphertext)?;
+        ciphertext.extend(Rc4::new(&k3).apply(&data));
+        Ok(ciphertext)
+    }
+
+    fn decrypt_message(key: &[u8], usage: u32, export: bool, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < CHECKSUM_SIZE + CONFOUNDER_SIZE {
+            return Err(Error::BadCiphertext(format!(
+                "{} bytes cannot hold a checksum and confounder", ciphertext.len())));
+        }
+        let (k1, k2) = KrbRc4::message_keys(key, usage, export)?;
+        let (checksum, ciphertext) = ciphertext.split_at(CHECKSUM_SIZE);
+        let k3 = KrbRc4::hmac(&k1, checksum)?;
+        let data = Rc4::new(&k3).apply(ciphertext);
+        let mut mac = HmacMd5::new_from_slice(&k2)
+            .map_err(|_| Error::BadKeyLength { expected: 16, actual: k2.len() })?;
+        mac.update(&data);
+        mac.verify_slice(checksum)
+            .map_err(|_| Error::IntegrityCheckFailed)?;
+        Ok(data[CONFOUNDER_SIZE..].to_vec())
+    }
+
+    // The keyed HMAC-MD5 checksum (type -138) from section 4 of RFC 4757.
+    pub fn checksum(key: &[u8], usage: u32, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(KrbRc4::checksum_mac(key, usage, data)?.finalize().into_bytes().to_vec())
+    }
+
+    pub fn verify_checksum(key: &[u8], usage: u32, data: &[u8], checksum: &[u8]) -> Result<()> {
+        KrbRc4::checksum_mac(key, usage, data)?
+            .verify_slice(checksum)
+            .map_err(|_| Error::IntegrityCheckFailed)
+    }
+
+    fn checksum_mac(key: &[u8], usage: u32, data: &[u8]) -> Result<HmacMd5> {
+        let ksign = KrbRc4::hmac(key, b"signaturekey\0")?;
+        let digest = Md5::new()
+            .chain_update(KrbRc4::translate_usage(usage).to_le_bytes())
+            .chain_update(data)
+            .finalize();
+        let mut mac = HmacMd5::new_from_slice(&ksign)
+            .map_err(|_| Error::BadKeyLength { expected: 16, actual: ksign.len() })?;
+        mac.update(&digest);
+        Ok(mac)
     }
 }
 
+
+// rc4-hmac-exp (etype 24): the same scheme with the message key cut down
+// to 40 bits for pre-2000 export rules.
+pub struct KrbRc4Export;
+
+impl KrbRc4Export {
+    pub fn usage_keys(key: &[u8], usage: u32) -> Result<UsageKeys> {
+        KrbRc4::profile_keys(key, usage, true)
+    }
+}
+
+impl KrbCipher for KrbRc4Export {
+    fn encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>> {
+        KrbRc4::encrypt_message(key, usage, true, plaintext)
+    }
+
+    fn decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        KrbRc4::decrypt_message(key, usage, true, ciphertext)
+    }
+}
+
+
+struct Rc4 {
+    state: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4 {
+    fn new(key: &[u8]) -> Rc4 {
+        let mut state = [0u8; 256];
+        for (i, s) in state.iter_mut().enumerate() {
+            *s = i as u8;
+        }
+        let mut j: u8 = 0;
+        for i in 0..256 {
+            j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+            state.swap(i, j as usize);
+        }
+        Rc4 { state, i: 0, j: 0 }
+    }
+
+    fn apply(&mut self, data: &[u8]) -> Vec<u8> {
+        data.iter().map(|b| {
+            self.i = self.i.wrapping_add(1);
+            self.j = self.j.wrapping_add(self.state[self.i as usize]);
+            self.state.swap(self.i as usize, self.j as usize);
+            let k = self.state[(self.state[self.i as usize].wrapping_add(self.state[self.j as usize])) as usize];
+            b ^ k
+        }).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::crypto::WinHash;
+    use crate::crypto::lanman::NTLanMan;
+
+    fn h(data: &str) -> Vec<u8> {
+        hex::decode(data).unwrap()
+    }
+
+    // NTOWFv1("Password") from [MS-NLMP] section 4.2.2.1.1
+    const KEY: &str = "a4f49c406510bdcab6824ee7c30fd852";
+    const PLAINTEXT: &[u8] = b"metaros known-answer test";
+    const CONFOUNDER: [u8; CONFOUNDER_SIZE] = [0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7];
+
+    #[test]
+    fn string_to_key() {
+        assert_eq!(NTLanMan::from_string("Password").unwrap(), h(KEY));
+    }
+
+    // RFC 6229 section 2, the 40 bit key at offsets 0 and 16
+    #[test]
+    fn rc4_stream() {
+        assert_eq!(Rc4::new(&[1, 2, 3, 4, 5]).apply(&[0; 32]),
+            h("b2396305f03dc027ccc3524a0a1118a86982944f18fc82d589c403a47a0d0919"));
+    }
+
+    #[test]
+    fn usage_translation() {
+        for (usage, windows) in [(3, 8), (9, 9), (23, 13), (7, 7), (11, 11)] {
+            assert_eq!(KrbRc4::translate_usage(usage), windows);
+        }
+    }
+
+    // The rest are computed from KEY by fixtures/gen/rc4_hmac.py.
+    #[test]
+    fn message_keys() {
+        let (k1, k2) = KrbRc4::message_keys(&h(KEY), 3, false).unwrap();
+        assert_eq!(k1, h("2f54d10a4b09debfa1006476066c7839"));
+        assert_eq!(k1, k2);
+        assert_eq!(KrbRc4::message_keys(&h(KEY), 8, false).unwrap().0, k1);
+        assert_eq!(KrbRc4::message_keys(&h(KEY), 9, false).unwrap().0, h("ea02eb5c762441f749bc2656c3f2d998"));
+    }
+
+    #[test]
+    fn export_message_keys() {
+        for (usage, k1, k2) in [
+            (3, "72dc99f3c7ff34ababababababababab", "72dc99f3c7ff34e8017665115aa6bdfd"),
+            (23, "0607509c7ee115ababababababababab", "0607509c7ee1158e12040abb1485b0d4"),
+            (7, "f823a795dc49f9ababababababababab", "f823a795dc49f93862316e1d46d056be"),
+        ] {
+            assert_eq!(KrbRc4::message_keys(&h(KEY), usage, true).unwrap(), (h(k1), h(k2)));
+        }
+    }
+
+    #[test]
+    fn hmac_md5_checksum() {
+        let checksum = h("ebba73c7a68f329b6eda3d88ba317867");
+        assert_eq!(KrbRc4::checksum(&h(KEY), 17, PLAINTEXT).unwrap(), checksum);
+        KrbRc4::verify_checksum(&h(KEY), 17, PLAINTEXT, &checksum).unwrap();
+        assert!(matches!(KrbRc4::verify_checksum(&h(KEY), 16, PLAINTEXT, &checksum),
+            Err(Error::IntegrityCheckFailed)));
+    }
+
+    #[test]
+    fn encrypt_known_answer() {
+        let expected = h("fb0c9188cb1a72be0455bfd30bc2796a5c281c16de1a1ce8a77ba2cdfdb973cf0a933b3677276bd57f4d5ee8ee575dd9b1");
+        assert_eq!(KrbRc4::encrypt_with_confounder(&h(KEY), 9, false, &CONFOUNDER, PLAINTEXT).unwrap(), expected);
+        assert_eq!(<KrbRc4 as KrbCipher>::decrypt(&h(KEY), 9, &expected).unwrap(), PLAINTEXT);
+        assert!(matches!(<KrbRc4 as KrbCipher>::decrypt(&h(KEY), 8, &expected),
+            Err(Error::IntegrityCheckFailed)));
+        let expected = h("6eac9bf809e4aa575c975585974a825b275dccaefb323278e108ffccf5d16007fe9b3ef9617d2174edb9b0ce5b77caa03e");
+        assert_eq!(KrbRc4::encrypt_with_confounder(&h(KEY), 3, true, &CONFOUNDER, PLAINTEXT).unwrap(), expected);
+        assert_eq!(KrbRc4Export::decrypt(&h(KEY), 3, &expected).unwrap(), PLAINTEXT);
+    }
+
+    #[test]
+    fn decrypt_rejects_tampering() {
+        let mut ciphertext = <KrbRc4 as KrbCipher>::encrypt(&h(KEY), 7, PLAINTEXT).unwrap();
+        let last = ciphertext.len() - 1;
+        ciphertext[last] ^= 1;
+        assert!(matches!(<KrbRc4 as KrbCipher>::decrypt(&h(KEY), 7, &ciphertext),
+            Err(Error::IntegrityCheckFailed)));
+    }
+}
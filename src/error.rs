@@ -0,0 +1,121 @@
+use std::{fmt, io};
+
+
+#[derive(Debug)]
+pub enum Error {
+    MalformedKeytab { offset: usize, field: &'static str, reason: String },
+    MalformedCcache { offset: usize, field: &'static str, reason: String },
+    MalformedDer { offset: usize, field: &'static str, reason: String },
+    UnknownEtype(String),
+    UnsupportedEtype(String),
+    UnknownChecksumType(String),
+    UnsupportedChecksumType(String),
+    MissingKey(&'static str),
+    MissingArgument(&'static str),
+    UnknownNameType(String),
+    UnknownKeyUsage(String),
+    BadHex(String),
+    BadBase64(String),
+    BadKeyLength { expected: usize, actual: usize },
+    FieldTooLong(usize),
+    BadCiphertext(String),
+    BadS2kParams { etype: &'static str, params: String },
+    IntegrityCheckFailed,
+    NoUsageKeys(&'static str),
+    EncryptedKrbCred(i32),
+    IncompleteCredential(&'static str),
+    TimeOutOfRange(i64),
+    NoMatchingKey { principal: String, kvno: Option<u32>, etype: &'static str },
+    Io(io::Error),
+    Csv(csv::Error),
+    Crypt(pwhash::error::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MalformedKeytab { offset, field, reason } =>
+                write!(f, "malformed keytab at offset {} ({}): {}", offset, field, reason),
+            Error::MalformedCcache { offset, field, reason } =>
+                write!(f, "malformed ccache at offset {} ({}): {}", offset, field, reason),
+            Error::MalformedDer { offset, field, reason } =>
+                write!(f, "malformed DER at offset {} ({}): {}", offset, field, reason),
+            Error::UnknownEtype(etype) =>
+                write!(f, "unknown encryption type: {}", etype),
+            Error::UnsupportedEtype(etype) =>
+                write!(f, "encryption type not supported: {}", etype),
+            Error::UnknownChecksumType(cksumtype) =>
+                write!(f, "unknown checksum type: {}", cksumtype),
+            Error::UnsupportedChecksumType(cksumtype) =>
+                write!(f, "checksum type not supported: {}", cksumtype),
+            Error::MissingKey(cksumtype) =>
+                write!(f, "{} is a keyed checksum: a key and key usage are needed", cksumtype),
+            Error::MissingArgument(name) =>
+                write!(f, "missing required argument: --{}", name),
+            Error::UnknownNameType(name_type) =>
+                write!(f, "unknown principal name type: {}", name_type),
+            Error::UnknownKeyUsage(usage) =>
+                write!(f, "unknown key usage: {}", usage),
+            Error::BadHex(data) =>
+                write!(f, "invalid hex string: {}", data),
+            Error::BadBase64(reason) =>
+                write!(f, "invalid base64: {}", reason),
+            Error::BadKeyLength { expected, actual } =>
+                write!(f, "bad key length: expected {} bytes, got {}", expected, actual),
+            Error::FieldTooLong(length) =>
+                write!(f, "value of {} bytes does not fit in a length-prefixed field", length),
+            Error::BadCiphertext(reason) =>
+                write!(f, "malformed ciphertext: {}", reason),
+            Error::BadS2kParams { etype, params } =>
+                write!(f, "unsupported string-to-key parameters for {}: {}", etype, params),
+            Error::IntegrityCheckFailed =>
+                write!(f, "integrity check failed: wrong key or usage, or corrupted ciphertext"),
+            Error::NoUsageKeys(etype) =>
+                write!(f, "{} does not derive keys per key usage", etype),
+            Error::EncryptedKrbCred(etype) =>
+                write!(f, "KRB-CRED is encrypted with etype {}: only unencrypted (etype 0) credentials can be read", etype),
+            Error::IncompleteCredential(field) =>
+                write!(f, "credential has no {}", field),
+            Error::TimeOutOfRange(time) =>
+                write!(f, "time {} does not fit in a credential cache", time),
+            Error::NoMatchingKey { principal, kvno: Some(kvno), etype } =>
+                write!(f, "no keytab entry for {} with kvno {} and etype {}", principal, kvno, etype),
+            Error::NoMatchingKey { principal, kvno: None, etype } =>
+                write!(f, "no keytab entry for {} with etype {}", principal, etype),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Csv(e) => write!(f, "{}", e),
+            Error::Crypt(e) => write!(f, "crypt(3) failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Csv(e) => Some(e),
+            Error::Crypt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Error {
+        Error::Csv(e)
+    }
+}
+
+impl From<pwhash::error::Error> for Error {
+    fn from(e: pwhash::error::Error) -> Error {
+        Error::Crypt(e)
+    }
+}
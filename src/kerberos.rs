@@ -1,2 +1,8 @@
+pub mod asn1;
+pub mod ccache;
+mod cursor;
 pub mod keytab;
+pub mod kirbi;
 pub mod reference;
+pub mod salt;
+pub mod ticket;
@@ -0,0 +1,1150 @@
+use std::fmt;
+use crate::kerberos::reference;
+use crate::error::Result;
+use self::der::Reader;
+use chrono::{TimeZone, Utc};
+
+pub mod der;
+
+
+pub const PVNO: i32 = 5;
+
+// RFC 4120 section 5 messages, encoded and decoded as DER. Field names
+// follow the ASN.1 module with hyphens turned into underscores.
+pub trait Asn1: Sized {
+    fn read(der: &mut Reader) -> Result<Self>;
+    fn to_der(&self) -> Vec<u8>;
+
+    // Decodes exactly one value, rejecting anything after it.
+    fn from_der(bytes: &[u8]) -> Result<Self> {
+        let mut der = Reader::new(bytes, "message");
+        let value = Self::read(&mut der)?;
+        der.finish()?;
+        Ok(value)
+    }
+}
+
+// KerberosString
+impl Asn1 for String {
+    fn read(der: &mut Reader) -> Result<String> {
+        der.string()
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::string(self)
+    }
+}
+
+// Int32, as used for the etype list of a KDC-REQ-BODY
+impl Asn1 for i32 {
+    fn read(der: &mut Reader) -> Result<i32> {
+        der.i32()
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::integer((*self).into())
+    }
+}
+
+
+// KerberosFlags: a BIT STRING of at least 32 bits, with bit 0 the most
+// significant bit of `value`. The bit string's own length, unused bits and
+// any bits past 31 are kept, so that flags write back as they were read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KerberosFlags {
+    pub value: u32,
+    length: usize,
+    unused: u8,
+    rest: Vec<u8>,
+}
+
+impl KerberosFlags {
+    pub fn new(value: u32) -> KerberosFlags {
+        KerberosFlags { value, length: 4, unused: 0, rest: Vec::new() }
+    }
+}
+
+impl Default for KerberosFlags {
+    fn default() -> KerberosFlags {
+        KerberosFlags::new(0)
+    }
+}
+
+impl Asn1 for KerberosFlags {
+    fn read(der: &mut Reader) -> Result<KerberosFlags> {
+        let (unused, bytes) = der.bit_string()?;
+        let mut value = [0u8; 4];
+        for (v, b) in value.iter_mut().zip(bytes) {
+            *v = *b;
+        }
+        Ok(KerberosFlags {
+            value: u32::from_be_bytes(value),
+            length: bytes.len().min(4),
+            unused,
+            rest: bytes.get(4..).unwrap_or_default().to_vec(),
+        })
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let value = self.value.to_be_bytes();
+        // a short bit string grows back to 32 bits if a flag past its end
+        // has since been set
+        if self.length < 4 && value[self.length..].iter().any(|b| *b != 0) {
+            return der::bit_string(0, &value);
+        }
+        let mut bytes = value[..self.length].to_vec();
+        bytes.extend(&self.rest);
+        der::bit_string(self.unused, &bytes)
+    }
+}
+
+// Reads the pvno and msg-type that open every message, checking the type
+// against the application tag it arrived in.
+fn read_header(seq: &mut Reader, first: u8, expected: &[i32]) -> Result<(i32, i32)> {
+    let pvno = seq.field(first, "pvno", Reader::i32)?;
+    let msg_type = seq.field(first + 1, "msg-type", Reader::i32)?;
+    if !expected.contains(&msg_type) {
+        return Err(seq.error(format!("msg-type {} ({}) does not match the message",
+            reference::MESSAGE_TYPES.name(msg_type), msg_type)));
+    }
+    Ok((pvno, msg_type))
+}
+
+fn write_header(first: u8, pvno: i32, msg_type: i32) -> Vec<Vec<u8>> {
+    vec![
+        der::field(first, der::integer(pvno.into())),
+        der::field(first + 1, der::integer(msg_type.into())),
+    ]
+}
+
+fn application(n: i32, fields: Vec<Vec<u8>>) -> Vec<u8> {
+    der::tlv(der::application(n as u8), &der::sequence(fields))
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrincipalName {
+    pub name_type: i32,
+    pub name_string: Vec<String>,
+}
+
+impl PrincipalName {
+    // Splits a principal without its realm into components.
+    pub fn new(name_type: i32, name: &str) -> PrincipalName {
+        PrincipalName {
+            name_type,
+            name_string: name.split('/').map(str::to_string).collect(),
+        }
+    }
+}
+
+impl fmt::Display for PrincipalName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name_string.join("/"))
+    }
+}
+
+impl Asn1 for PrincipalName {
+    fn read(der: &mut Reader) -> Result<PrincipalName> {
+        let mut seq = der.sequence()?;
+        let name = PrincipalName {
+            name_type: seq.field(0, "name-type", Reader::i32)?,
+            name_string: seq.field(1, "name-string", Reader::sequence_of)?,
+        };
+        seq.finish()?;
+        Ok(name)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::sequence(vec![
+            der::field(0, der::integer(self.name_type.into())),
+            der::field(1, der::sequence_of(&self.name_string)),
+        ])
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostAddress {
+    pub addr_type: i32,
+    pub address: Vec<u8>,
+}
+
+impl Asn1 for HostAddress {
+    fn read(der: &mut Reader) -> Result<HostAddress> {
+        let mut seq = der.sequence()?;
+        let address = HostAddress {
+            addr_type: seq.field(0, "addr-type", Reader::i32)?,
+            address: seq.field(1, "address", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(address)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::sequence(vec![
+            der::field(0, der::integer(self.addr_type.into())),
+            der::field(1, der::octet_string(&self.address)),
+        ])
+    }
+}
+
+
+// One element of AuthorizationData, which is a SEQUENCE OF these
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthorizationDataEntry {
+    pub ad_type: i32,
+    pub ad_data: Vec<u8>,
+}
+
+impl Asn1 for AuthorizationDataEntry {
+    fn read(der: &mut Reader) -> Result<AuthorizationDataEntry> {
+        let mut seq = der.sequence()?;
+        let entry = AuthorizationDataEntry {
+            ad_type: seq.field(0, "ad-type", Reader::i32)?,
+            ad_data: seq.field(1, "ad-data", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(entry)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::sequence(vec![
+            der::field(0, der::integer(self.ad_type.into())),
+            der::field(1, der::octet_string(&self.ad_data)),
+        ])
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedData {
+    pub etype: i32,
+    pub kvno: Option<u32>,
+    pub cipher: Vec<u8>,
+}
+
+impl Asn1 for EncryptedData {
+    fn read(der: &mut Reader) -> Result<EncryptedData> {
+        let mut seq = der.sequence()?;
+        let data = EncryptedData {
+            etype: seq.field(0, "etype", Reader::i32)?,
+            kvno: seq.optional_field(1, "kvno", Reader::u32)?,
+            cipher: seq.field(2, "cipher", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(data)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = vec![der::field(0, der::integer(self.etype.into()))];
+        fields.extend(self.kvno.map(|k| der::field(1, der::integer(k.into()))));
+        fields.push(der::field(2, der::octet_string(&self.cipher)));
+        der::sequence(fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptionKey {
+    pub keytype: i32,
+    pub keyvalue: Vec<u8>,
+}
+
+impl Asn1 for EncryptionKey {
+    fn read(der: &mut Reader) -> Result<EncryptionKey> {
+        let mut seq = der.sequence()?;
+        let key = EncryptionKey {
+            keytype: seq.field(0, "keytype", Reader::i32)?,
+            keyvalue: seq.field(1, "keyvalue", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(key)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::sequence(vec![
+            der::field(0, der::integer(self.keytype.into())),
+            der::field(1, der::octet_string(&self.keyvalue)),
+        ])
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checksum {
+    pub cksumtype: i32,
+    pub checksum: Vec<u8>,
+}
+
+impl Asn1 for Checksum {
+    fn read(der: &mut Reader) -> Result<Checksum> {
+        let mut seq = der.sequence()?;
+        let checksum = Checksum {
+            cksumtype: seq.field(0, "cksumtype", Reader::i32)?,
+            checksum: seq.field(1, "checksum", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(checksum)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::sequence(vec![
+            der::field(0, der::integer(self.cksumtype.into())),
+            der::field(1, der::octet_string(&self.checksum)),
+        ])
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ticket {
+    pub tkt_vno: i32,
+    pub realm: String,
+    pub sname: PrincipalName,
+    pub enc_part: EncryptedData,
+}
+
+impl Asn1 for Ticket {
+    fn read(der: &mut Reader) -> Result<Ticket> {
+        let mut seq = der.application(1)?;
+        let ticket = Ticket {
+            tkt_vno: seq.field(0, "tkt-vno", Reader::i32)?,
+            realm: seq.field(1, "realm", Reader::string)?,
+            sname: seq.field(2, "sname", PrincipalName::read)?,
+            enc_part: seq.field(3, "enc-part", EncryptedData::read)?,
+        };
+        seq.finish()?;
+        Ok(ticket)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        application(1, vec![
+            der::field(0, der::integer(self.tkt_vno.into())),
+            der::field(1, der::string(&self.realm)),
+            der::field(2, self.sname.to_der()),
+            der::field(3, self.enc_part.to_der()),
+        ])
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransitedEncoding {
+    pub tr_type: i32,
+    pub contents: Vec<u8>,
+}
+
+impl Asn1 for TransitedEncoding {
+    fn read(der: &mut Reader) -> Result<TransitedEncoding> {
+        let mut seq = der.sequence()?;
+        let transited = TransitedEncoding {
+            tr_type: seq.field(0, "tr-type", Reader::i32)?,
+            contents: seq.field(1, "contents", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(transited)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::sequence(vec![
+            der::field(0, der::integer(self.tr_type.into())),
+            der::field(1, der::octet_string(&self.contents)),
+        ])
+    }
+}
+
+
+// The decrypted enc-part of a Ticket
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncTicketPart {
+    pub flags: KerberosFlags,
+    pub key: EncryptionKey,
+    pub crealm: String,
+    pub cname: PrincipalName,
+    pub transited: TransitedEncoding,
+    pub authtime: i64,
+    pub starttime: Option<i64>,
+    pub endtime: i64,
+    pub renew_till: Option<i64>,
+    pub caddr: Option<Vec<HostAddress>>,
+    pub authorization_data: Option<Vec<AuthorizationDataEntry>>,
+}
+
+impl fmt::Display for EncTicketPart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f,"Client: {}@{}", self.cname, self.crealm)?;
+        writeln!(f,"Flags: {:#010x} ({})", self.flags.value,
+            reference::TICKET_FLAGS.flag_names(self.flags.value).join(", "))?;
+        writeln!(f,"Session Key: {} {}",
+            reference::ENCRYPTION_TYPES.name(self.key.keytype), hex::encode(&self.key.keyvalue))?;
+        writeln!(f,"Auth Time: {}", format_time(Some(self.authtime)))?;
+        writeln!(f,"Start Time: {}", format_time(self.starttime))?;
+        writeln!(f,"End Time: {}", format_time(Some(self.endtime)))?;
+        writeln!(f,"Renew Till: {}", format_time(self.renew_till))?;
+        // an empty DOMAIN-X500-COMPRESS encoding means no realms were crossed
+        match self.transited.contents.as_slice() {
+            [] => writeln!(f,"Transited: none (type {})", self.transited.tr_type)?,
+            c => writeln!(f,"Transited: {} (type {})", String::from_utf8_lossy(c), self.transited.tr_type)?,
+        }
+        for a in self.caddr.iter().flatten() {
+            writeln!(f,"Address: {} {}", a.addr_type, hex::encode(&a.address))?;
+        }
+        write_authorization_data(f, self.authorization_data.as_deref().unwrap_or_default(), 0)
+    }
+}
+
+// AD-IF-RELEVANT holds more AuthorizationData, which is where a PAC sits.
+fn write_authorization_data(f: &mut fmt::Formatter, entries: &[AuthorizationDataEntry], depth: usize) -> fmt::Result {
+    for entry in entries {
+        writeln!(f,"{}Authorization Data: {} ({}, {} bytes)", "  ".repeat(depth), entry.ad_type,
+            reference::AUTHORIZATION_DATA_TYPES.name(entry.ad_type), entry.ad_data.len())?;
+        if entry.ad_type == 1 {
+            if let Ok(inner) = Reader::new(&entry.ad_data, "ad-data").sequence_of() {
+                write_authorization_data(f, &inner, depth + 1)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn format_time(time: Option<i64>) -> String {
+    match time {
+        Some(t) => Utc.timestamp_opt(t, 0).single()
+            .map_or_else(|| format!("{} seconds since the epoch", t), |t| t.to_rfc2822()),
+        None => "not set".to_string(),
+    }
+}
+
+impl Asn1 for EncTicketPart {
+    fn read(der: &mut Reader) -> Result<EncTicketPart> {
+        let mut seq = der.application(3)?;
+        let part = EncTicketPart {
+            flags: seq.field(0, "flags", KerberosFlags::read)?,
+            key: seq.field(1, "key", EncryptionKey::read)?,
+            crealm: seq.field(2, "crealm", Reader::string)?,
+            cname: seq.field(3, "cname", PrincipalName::read)?,
+            transited: seq.field(4, "transited", TransitedEncoding::read)?,
+            authtime: seq.field(5, "authtime", Reader::time)?,
+            starttime: seq.optional_field(6, "starttime", Reader::time)?,
+            endtime: seq.field(7, "endtime", Reader::time)?,
+            renew_till: seq.optional_field(8, "renew-till", Reader::time)?,
+            caddr: seq.optional_field(9, "caddr", Reader::sequence_of)?,
+            authorization_data: seq.optional_field(10, "authorization-data", Reader::sequence_of)?,
+        };
+        seq.finish()?;
+        Ok(part)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = vec![
+            der::field(0, self.flags.to_der()),
+            der::field(1, self.key.to_der()),
+            der::field(2, der::string(&self.crealm)),
+            der::field(3, self.cname.to_der()),
+            der::field(4, self.transited.to_der()),
+            der::field(5, der::time(self.authtime)),
+        ];
+        fields.extend(self.starttime.map(|t| der::field(6, der::time(t))));
+        fields.push(der::field(7, der::time(self.endtime)));
+        fields.extend(self.renew_till.map(|t| der::field(8, der::time(t))));
+        fields.extend(self.caddr.as_ref().map(|a| der::field(9, der::sequence_of(a))));
+        fields.extend(self.authorization_data.as_ref().map(|a| der::field(10, der::sequence_of(a))));
+        application(3, fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaData {
+    pub padata_type: i32,
+    pub padata_value: Vec<u8>,
+}
+
+impl PaData {
+    pub fn new(value: &PaValue) -> PaData {
+        let (padata_type, padata_value) = match value {
+            PaValue::TgsReq(req) => (1, req.to_der()),
+            PaValue::EncTimestamp(data) => (2, data.to_der()),
+            PaValue::PwSalt(salt) => (3, salt.clone()),
+            PaValue::EtypeInfo(info) => (11, der::sequence_of(info)),
+            PaValue::EtypeInfo2(info) => (19, der::sequence_of(info)),
+            PaValue::PacRequest(req) => (128, req.to_der()),
+            PaValue::Other(padata_type, value) => (*padata_type, value.clone()),
+        };
+        PaData { padata_type, padata_value }
+    }
+
+    pub fn name(&self) -> &'static str {
+        reference::PADATA_TYPES.name(self.padata_type)
+    }
+
+    // Decodes the value of the padata types metaros knows about.
+    pub fn value(&self) -> Result<PaValue> {
+        let value = &self.padata_value;
+        Ok(match self.padata_type {
+            1 => PaValue::TgsReq(ApReq::from_der(value)?),
+            2 => PaValue::EncTimestamp(EncryptedData::from_der(value)?),
+            3 => PaValue::PwSalt(value.clone()),
+            11 => PaValue::EtypeInfo(sequence_of_der(value)?),
+            19 => PaValue::EtypeInfo2(sequence_of_der(value)?),
+            128 => PaValue::PacRequest(PacRequest::from_der(value)?),
+            t => PaValue::Other(t, value.clone()),
+        })
+    }
+}
+
+impl Asn1 for PaData {
+    fn read(der: &mut Reader) -> Result<PaData> {
+        let mut seq = der.sequence()?;
+        let padata = PaData {
+            padata_type: seq.field(1, "padata-type", Reader::i32)?,
+            padata_value: seq.field(2, "padata-value", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(padata)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::sequence(vec![
+            der::field(1, der::integer(self.padata_type.into())),
+            der::field(2, der::octet_string(&self.padata_value)),
+        ])
+    }
+}
+
+fn sequence_of_der<T: Asn1>(bytes: &[u8]) -> Result<Vec<T>> {
+    let mut der = Reader::new(bytes, "padata-value");
+    let items = der.sequence_of()?;
+    der.finish()?;
+    Ok(items)
+}
+
+
+// The padata-value of the padata types with a defined structure. PA-PW-SALT
+// is a bare salt, and anything else is kept as it came.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PaValue {
+    TgsReq(ApReq),
+    EncTimestamp(EncryptedData),
+    PwSalt(Vec<u8>),
+    EtypeInfo(Vec<EtypeInfoEntry>),
+    EtypeInfo2(Vec<EtypeInfo2Entry>),
+    PacRequest(PacRequest),
+    Other(i32, Vec<u8>),
+}
+
+
+// The plaintext of PA-ENC-TIMESTAMP, encrypted with key usage 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaEncTsEnc {
+    pub patimestamp: i64,
+    pub pausec: Option<u32>,
+}
+
+impl Asn1 for PaEncTsEnc {
+    fn read(der: &mut Reader) -> Result<PaEncTsEnc> {
+        let mut seq = der.sequence()?;
+        let ts = PaEncTsEnc {
+            patimestamp: seq.field(0, "patimestamp", Reader::time)?,
+            pausec: seq.optional_field(1, "pausec", Reader::u32)?,
+        };
+        seq.finish()?;
+        Ok(ts)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = vec![der::field(0, der::time(self.patimestamp))];
+        fields.extend(self.pausec.map(|u| der::field(1, der::integer(u.into()))));
+        der::sequence(fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EtypeInfoEntry {
+    pub etype: i32,
+    pub salt: Option<Vec<u8>>,
+}
+
+impl Asn1 for EtypeInfoEntry {
+    fn read(der: &mut Reader) -> Result<EtypeInfoEntry> {
+        let mut seq = der.sequence()?;
+        let entry = EtypeInfoEntry {
+            etype: seq.field(0, "etype", Reader::i32)?,
+            salt: seq.optional_field(1, "salt", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(entry)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = vec![der::field(0, der::integer(self.etype.into()))];
+        fields.extend(self.salt.as_ref().map(|s| der::field(1, der::octet_string(s))));
+        der::sequence(fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EtypeInfo2Entry {
+    pub etype: i32,
+    pub salt: Option<String>,
+    pub s2kparams: Option<Vec<u8>>,
+}
+
+impl Asn1 for EtypeInfo2Entry {
+    fn read(der: &mut Reader) -> Result<EtypeInfo2Entry> {
+        let mut seq = der.sequence()?;
+        let entry = EtypeInfo2Entry {
+            etype: seq.field(0, "etype", Reader::i32)?,
+            salt: seq.optional_field(1, "salt", Reader::string)?,
+            s2kparams: seq.optional_field(2, "s2kparams", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(entry)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = vec![der::field(0, der::integer(self.etype.into()))];
+        fields.extend(self.salt.as_ref().map(|s| der::field(1, der::string(s))));
+        fields.extend(self.s2kparams.as_ref().map(|p| der::field(2, der::octet_string(p))));
+        der::sequence(fields)
+    }
+}
+
+
+// KERB-PA-PAC-REQUEST from MS-KILE
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PacRequest {
+    pub include_pac: bool,
+}
+
+impl Asn1 for PacRequest {
+    fn read(der: &mut Reader) -> Result<PacRequest> {
+        let mut seq = der.sequence()?;
+        let req = PacRequest {
+            include_pac: seq.field(0, "include-pac", Reader::boolean)?,
+        };
+        seq.finish()?;
+        Ok(req)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        der::sequence(vec![der::field(0, der::boolean(self.include_pac))])
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KdcReqBody {
+    pub kdc_options: KerberosFlags,
+    pub cname: Option<PrincipalName>,
+    pub realm: String,
+    pub sname: Option<PrincipalName>,
+    pub from: Option<i64>,
+    pub till: i64,
+    pub rtime: Option<i64>,
+    pub nonce: u32,
+    pub etype: Vec<i32>,
+    pub addresses: Option<Vec<HostAddress>>,
+    pub enc_authorization_data: Option<EncryptedData>,
+    pub additional_tickets: Option<Vec<Ticket>>,
+}
+
+impl Asn1 for KdcReqBody {
+    fn read(der: &mut Reader) -> Result<KdcReqBody> {
+        let mut seq = der.sequence()?;
+        let body = KdcReqBody {
+            kdc_options: seq.field(0, "kdc-options", KerberosFlags::read)?,
+            cname: seq.optional_field(1, "cname", PrincipalName::read)?,
+            realm: seq.field(2, "realm", Reader::string)?,
+            sname: seq.optional_field(3, "sname", PrincipalName::read)?,
+            from: seq.optional_field(4, "from", Reader::time)?,
+            till: seq.field(5, "till", Reader::time)?,
+            rtime: seq.optional_field(6, "rtime", Reader::time)?,
+            nonce: seq.field(7, "nonce", Reader::u32)?,
+            etype: seq.field(8, "etype", Reader::sequence_of)?,
+            addresses: seq.optional_field(9, "addresses", Reader::sequence_of)?,
+            enc_authorization_data: seq.optional_field(10, "enc-authorization-data", EncryptedData::read)?,
+            additional_tickets: seq.optional_field(11, "additional-tickets", Reader::sequence_of)?,
+        };
+        seq.finish()?;
+        Ok(body)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = vec![der::field(0, self.kdc_options.to_der())];
+        fields.extend(self.cname.as_ref().map(|n| der::field(1, n.to_der())));
+        fields.push(der::field(2, der::string(&self.realm)));
+        fields.extend(self.sname.as_ref().map(|n| der::field(3, n.to_der())));
+        fields.extend(self.from.map(|t| der::field(4, der::time(t))));
+        fields.push(der::field(5, der::time(self.till)));
+        fields.extend(self.rtime.map(|t| der::field(6, der::time(t))));
+        fields.push(der::field(7, der::integer(self.nonce.into())));
+        fields.push(der::field(8, der::sequence_of(&self.etype)));
+        fields.extend(self.addresses.as_ref().map(|a| der::field(9, der::sequence_of(a))));
+        fields.extend(self.enc_authorization_data.as_ref().map(|d| der::field(10, d.to_der())));
+        fields.extend(self.additional_tickets.as_ref().map(|t| der::field(11, der::sequence_of(t))));
+        der::sequence(fields)
+    }
+}
+
+
+// AS-REQ and TGS-REQ, told apart by msg_type
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KdcReq {
+    pub pvno: i32,
+    pub msg_type: i32,
+    pub padata: Option<Vec<PaData>>,
+    pub req_body: KdcReqBody,
+}
+
+impl Asn1 for KdcReq {
+    fn read(der: &mut Reader) -> Result<KdcReq> {
+        let tag = der.peek_tag().unwrap_or(0);
+        let msg_type = i32::from(tag & 0x1f);
+        if tag != der::application(10) && tag != der::application(12) {
+            return Err(der.error(format!("expected an AS-REQ or TGS-REQ, found tag {:#04x}", tag)));
+        }
+        let mut seq = der.application(msg_type as u8)?;
+        let (pvno, msg_type) = read_header(&mut seq, 1, &[msg_type])?;
+        let req = KdcReq {
+            pvno,
+            msg_type,
+            padata: seq.optional_field(3, "padata", Reader::sequence_of)?,
+            req_body: seq.field(4, "req-body", KdcReqBody::read)?,
+        };
+        seq.finish()?;
+        Ok(req)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = write_header(1, self.pvno, self.msg_type);
+        fields.extend(self.padata.as_ref().map(|p| der::field(3, der::sequence_of(p))));
+        fields.push(der::field(4, self.req_body.to_der()));
+        application(self.msg_type, fields)
+    }
+}
+
+
+// AS-REP and TGS-REP, told apart by msg_type
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KdcRep {
+    pub pvno: i32,
+    pub msg_type: i32,
+    pub padata: Option<Vec<PaData>>,
+    pub crealm: String,
+    pub cname: PrincipalName,
+    pub ticket: Ticket,
+    pub enc_part: EncryptedData,
+}
+
+impl Asn1 for KdcRep {
+    fn read(der: &mut Reader) -> Result<KdcRep> {
+        let tag = der.peek_tag().unwrap_or(0);
+        let msg_type = i32::from(tag & 0x1f);
+        if tag != der::application(11) && tag != der::application(13) {
+            return Err(der.error(format!("expected an AS-REP or TGS-REP, found tag {:#04x}", tag)));
+        }
+        let mut seq = der.application(msg_type as u8)?;
+        let (pvno, msg_type) = read_header(&mut seq, 0, &[msg_type])?;
+        let rep = KdcRep {
+            pvno,
+            msg_type,
+            padata: seq.optional_field(2, "padata", Reader::sequence_of)?,
+            crealm: seq.field(3, "crealm", Reader::string)?,
+            cname: seq.field(4, "cname", PrincipalName::read)?,
+            ticket: seq.field(5, "ticket", Ticket::read)?,
+            enc_part: seq.field(6, "enc-part", EncryptedData::read)?,
+        };
+        seq.finish()?;
+        Ok(rep)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = write_header(0, self.pvno, self.msg_type);
+        fields.extend(self.padata.as_ref().map(|p| der::field(2, der::sequence_of(p))));
+        fields.push(der::field(3, der::string(&self.crealm)));
+        fields.push(der::field(4, self.cname.to_der()));
+        fields.push(der::field(5, self.ticket.to_der()));
+        fields.push(der::field(6, self.enc_part.to_der()));
+        application(self.msg_type, fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApReq {
+    pub pvno: i32,
+    pub msg_type: i32,
+    pub ap_options: KerberosFlags,
+    pub ticket: Ticket,
+    pub authenticator: EncryptedData,
+}
+
+impl Asn1 for ApReq {
+    fn read(der: &mut Reader) -> Result<ApReq> {
+        let mut seq = der.application(14)?;
+        let (pvno, msg_type) = read_header(&mut seq, 0, &[14])?;
+        let req = ApReq {
+            pvno,
+            msg_type,
+            ap_options: seq.field(2, "ap-options", KerberosFlags::read)?,
+            ticket: seq.field(3, "ticket", Ticket::read)?,
+            authenticator: seq.field(4, "authenticator", EncryptedData::read)?,
+        };
+        seq.finish()?;
+        Ok(req)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = write_header(0, self.pvno, self.msg_type);
+        fields.push(der::field(2, self.ap_options.to_der()));
+        fields.push(der::field(3, self.ticket.to_der()));
+        fields.push(der::field(4, self.authenticator.to_der()));
+        application(14, fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApRep {
+    pub pvno: i32,
+    pub msg_type: i32,
+    pub enc_part: EncryptedData,
+}
+
+impl Asn1 for ApRep {
+    fn read(der: &mut Reader) -> Result<ApRep> {
+        let mut seq = der.application(15)?;
+        let (pvno, msg_type) = read_header(&mut seq, 0, &[15])?;
+        let rep = ApRep {
+            pvno,
+            msg_type,
+            enc_part: seq.field(2, "enc-part", EncryptedData::read)?,
+        };
+        seq.finish()?;
+        Ok(rep)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = write_header(0, self.pvno, self.msg_type);
+        fields.push(der::field(2, self.enc_part.to_der()));
+        application(15, fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KrbError {
+    pub pvno: i32,
+    pub msg_type: i32,
+    pub ctime: Option<i64>,
+    pub cusec: Option<u32>,
+    pub stime: i64,
+    pub susec: u32,
+    pub error_code: i32,
+    pub crealm: Option<String>,
+    pub cname: Option<PrincipalName>,
+    pub realm: String,
+    pub sname: PrincipalName,
+    pub e_text: Option<String>,
+    pub e_data: Option<Vec<u8>>,
+}
+
+impl KrbError {
+    pub fn name(&self) -> &'static str {
+        reference::ERROR_CODES.name(self.error_code)
+    }
+}
+
+impl fmt::Display for KrbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}) from {}@{}", self.name(), self.error_code, self.sname, self.realm)?;
+        match &self.e_text {
+            Some(text) => write!(f, ": {}", text),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Asn1 for KrbError {
+    fn read(der: &mut Reader) -> Result<KrbError> {
+        let mut seq = der.application(30)?;
+        let (pvno, msg_type) = read_header(&mut seq, 0, &[30])?;
+        let error = KrbError {
+            pvno,
+            msg_type,
+            ctime: seq.optional_field(2, "ctime", Reader::time)?,
+            cusec: seq.optional_field(3, "cusec", Reader::u32)?,
+            stime: seq.field(4, "stime", Reader::time)?,
+            susec: seq.field(5, "susec", Reader::u32)?,
+            error_code: seq.field(6, "error-code", Reader::i32)?,
+            crealm: seq.optional_field(7, "crealm", Reader::string)?,
+            cname: seq.optional_field(8, "cname", PrincipalName::read)?,
+            realm: seq.field(9, "realm", Reader::string)?,
+            sname: seq.field(10, "sname", PrincipalName::read)?,
+            e_text: seq.optional_field(11, "e-text", Reader::string)?,
+            e_data: seq.optional_field(12, "e-data", Reader::octet_string)?,
+        };
+        seq.finish()?;
+        Ok(error)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = write_header(0, self.pvno, self.msg_type);
+        fields.extend(self.ctime.map(|t| der::field(2, der::time(t))));
+        fields.extend(self.cusec.map(|u| der::field(3, der::integer(u.into()))));
+        fields.push(der::field(4, der::time(self.stime)));
+        fields.push(der::field(5, der::integer(self.susec.into())));
+        fields.push(der::field(6, der::integer(self.error_code.into())));
+        fields.extend(self.crealm.as_ref().map(|r| der::field(7, der::string(r))));
+        fields.extend(self.cname.as_ref().map(|n| der::field(8, n.to_der())));
+        fields.push(der::field(9, der::string(&self.realm)));
+        fields.push(der::field(10, self.sname.to_der()));
+        fields.extend(self.e_text.as_ref().map(|t| der::field(11, der::string(t))));
+        fields.extend(self.e_data.as_ref().map(|d| der::field(12, der::octet_string(d))));
+        application(30, fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KrbCred {
+    pub pvno: i32,
+    pub msg_type: i32,
+    pub tickets: Vec<Ticket>,
+    pub enc_part: EncryptedData,
+}
+
+impl Asn1 for KrbCred {
+    fn read(der: &mut Reader) -> Result<KrbCred> {
+        let mut seq = der.application(22)?;
+        let (pvno, msg_type) = read_header(&mut seq, 0, &[22])?;
+        let cred = KrbCred {
+            pvno,
+            msg_type,
+            tickets: seq.field(2, "tickets", Reader::sequence_of)?,
+            enc_part: seq.field(3, "enc-part", EncryptedData::read)?,
+        };
+        seq.finish()?;
+        Ok(cred)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = write_header(0, self.pvno, self.msg_type);
+        fields.push(der::field(2, der::sequence_of(&self.tickets)));
+        fields.push(der::field(3, self.enc_part.to_der()));
+        application(22, fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncKrbCredPart {
+    pub ticket_info: Vec<KrbCredInfo>,
+    pub nonce: Option<u32>,
+    pub timestamp: Option<i64>,
+    pub usec: Option<u32>,
+    pub s_address: Option<HostAddress>,
+    pub r_address: Option<HostAddress>,
+}
+
+impl Asn1 for EncKrbCredPart {
+    fn read(der: &mut Reader) -> Result<EncKrbCredPart> {
+        let mut seq = der.application(29)?;
+        let part = EncKrbCredPart {
+            ticket_info: seq.field(0, "ticket-info", Reader::sequence_of)?,
+            nonce: seq.optional_field(1, "nonce", Reader::u32)?,
+            timestamp: seq.optional_field(2, "timestamp", Reader::time)?,
+            usec: seq.optional_field(3, "usec", Reader::u32)?,
+            s_address: seq.optional_field(4, "s-address", HostAddress::read)?,
+            r_address: seq.optional_field(5, "r-address", HostAddress::read)?,
+        };
+        seq.finish()?;
+        Ok(part)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = vec![der::field(0, der::sequence_of(&self.ticket_info))];
+        fields.extend(self.nonce.map(|n| der::field(1, der::integer(n.into()))));
+        fields.extend(self.timestamp.map(|t| der::field(2, der::time(t))));
+        fields.extend(self.usec.map(|u| der::field(3, der::integer(u.into()))));
+        fields.extend(self.s_address.as_ref().map(|a| der::field(4, a.to_der())));
+        fields.extend(self.r_address.as_ref().map(|a| der::field(5, a.to_der())));
+        application(29, fields)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KrbCredInfo {
+    pub key: EncryptionKey,
+    pub prealm: Option<String>,
+    pub pname: Option<PrincipalName>,
+    pub flags: Option<KerberosFlags>,
+    pub authtime: Option<i64>,
+    pub starttime: Option<i64>,
+    pub endtime: Option<i64>,
+    pub renew_till: Option<i64>,
+    pub srealm: Option<String>,
+    pub sname: Option<PrincipalName>,
+    pub caddr: Option<Vec<HostAddress>>,
+}
+
+impl Asn1 for KrbCredInfo {
+    fn read(der: &mut Reader) -> Result<KrbCredInfo> {
+        let mut seq = der.sequence()?;
+        let info = KrbCredInfo {
+            key: seq.field(0, "key", EncryptionKey::read)?,
+            prealm: seq.optional_field(1, "prealm", Reader::string)?,
+            pname: seq.optional_field(2, "pname", PrincipalName::read)?,
+            flags: seq.optional_field(3, "flags", KerberosFlags::read)?,
+            authtime: seq.optional_field(4, "authtime", Reader::time)?,
+            starttime: seq.optional_field(5, "starttime", Reader::time)?,
+            endtime: seq.optional_field(6, "endtime", Reader::time)?,
+            renew_till: seq.optional_field(7, "renew-till", Reader::time)?,
+            srealm: seq.optional_field(8, "srealm", Reader::string)?,
+            sname: seq.optional_field(9, "sname", PrincipalName::read)?,
+            caddr: seq.optional_field(10, "caddr", Reader::sequence_of)?,
+        };
+        seq.finish()?;
+        Ok(info)
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let mut fields = vec![der::field(0, self.key.to_der())];
+        fields.extend(self.prealm.as_ref().map(|r| der::field(1, der::string(r))));
+        fields.extend(self.pname.as_ref().map(|n| der::field(2, n.to_der())));
+        fields.extend(self.flags.as_ref().map(|f| der::field(3, f.to_der())));
+        fields.extend(self.authtime.map(|t| der::field(4, der::time(t))));
+        fields.extend(self.starttime.map(|t| der::field(5, der::time(t))));
+        fields.extend(self.endtime.map(|t| der::field(6, der::time(t))));
+        fields.extend(self.renew_till.map(|t| der::field(7, der::time(t))));
+        fields.extend(self.srealm.as_ref().map(|r| der::field(8, der::string(r))));
+        fields.extend(self.sname.as_ref().map(|n| der::field(9, n.to_der())));
+        fields.extend(self.caddr.as_ref().map(|a| der::field(10, der::sequence_of(a))));
+        der::sequence(fields)
+    }
+}
+
+
+// Any of the top level messages, picked by application tag
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    AsReq(KdcReq),
+    AsRep(KdcRep),
+    TgsReq(KdcReq),
+    TgsRep(KdcRep),
+    ApReq(ApReq),
+    ApRep(ApRep),
+    KrbCred(KrbCred),
+    KrbError(KrbError),
+}
+
+impl Message {
+    pub fn msg_type(&self) -> i32 {
+        match self {
+            Message::AsReq(m) | Message::TgsReq(m) => m.msg_type,
+            Message::AsRep(m) | Message::TgsRep(m) => m.msg_type,
+            Message::ApReq(m) => m.msg_type,
+            Message::ApRep(m) => m.msg_type,
+            Message::KrbCred(m) => m.msg_type,
+            Message::KrbError(m) => m.msg_type,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        reference::MESSAGE_TYPES.name(self.msg_type())
+    }
+}
+
+impl Asn1 for Message {
+    fn read(der: &mut Reader) -> Result<Message> {
+        let tag = der.peek_tag()
+            .ok_or_else(|| der.error("no message".to_string()))?;
+        Ok(match tag {
+            t if t == der::application(10) => Message::AsReq(KdcReq::read(der)?),
+            t if t == der::application(11) => Message::AsRep(KdcRep::read(der)?),
+            t if t == der::application(12) => Message::TgsReq(KdcReq::read(der)?),
+            t if t == der::application(13) => Message::TgsRep(KdcRep::read(der)?),
+            t if t == der::application(14) => Message::ApReq(ApReq::read(der)?),
+            t if t == der::application(15) => Message::ApRep(ApRep::read(der)?),
+            t if t == der::application(22) => Message::KrbCred(KrbCred::read(der)?),
+            t if t == der::application(30) => Message::KrbError(KrbError::read(der)?),
+            t => return Err(der.error(format!("tag {:#04x} is not a Kerberos message", t))),
+        })
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        match self {
+            Message::AsReq(m) | Message::TgsReq(m) => m.to_der(),
+            Message::AsRep(m) | Message::TgsRep(m) => m.to_der(),
+            Message::ApReq(m) => m.to_der(),
+            Message::ApRep(m) => m.to_der(),
+            Message::KrbCred(m) => m.to_der(),
+            Message::KrbError(m) => m.to_der(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing;
+
+    fn h(data: &str) -> Vec<u8> {
+        hex::decode(data.replace(' ', "")).unwrap()
+    }
+
+    // DER in fixtures/der, from fixtures/gen/der.py
+    fn round_trip<T: Asn1>(name: &str) -> T {
+        testing::round_trip(&format!("der/{}", name), T::from_der, T::to_der)
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        for (name, msg_type) in [
+            ("as-req.der", 10), ("as-rep.der", 11), ("tgs-req.der", 12), ("tgs-rep.der", 13),
+            ("ap-req.der", 14), ("ap-rep.der", 15), ("krb-cred.der", 22), ("krb-error.der", 30),
+        ] {
+            assert_eq!(round_trip::<Message>(name).msg_type(), msg_type, "{}", name);
+        }
+        let req: KdcReq = round_trip("as-req.der");
+        assert_eq!(req.req_body.kdc_options.value, 0x40810010);
+        let ap_req: ApReq = round_trip("ap-req.der");
+        assert_eq!(ap_req.ap_options.value, 0x20000000);
+        round_trip::<KdcRep>("tgs-rep.der");
+        round_trip::<KrbError>("krb-error.der");
+        round_trip::<KrbCred>("krb-cred.der");
+        // the second ticket info ends past the 32-bit range
+        let part: EncKrbCredPart = round_trip("enc-krb-cred-part.der");
+        assert_eq!(part.ticket_info[1].endtime, Some(u32::MAX as i64 + 1));
+        round_trip::<EncTicketPart>("enc-ticket-part.der");
+    }
+
+    #[test]
+    fn flags_keep_their_bit_string() {
+        for (der, value) in [
+            ("03 05 00 40810010", 0x40810010),
+            // 16 bits, one bit with seven unused, and 48 bits
+            ("03 03 00 4081", 0x40810000),
+            ("03 02 07 80", 0x80000000),
+            ("03 07 00 40810010 8000", 0x40810010),
+            ("03 05 04 408100f0", 0x408100f0),
+        ] {
+            let flags = KerberosFlags::from_der(&h(der)).unwrap();
+            assert_eq!(flags.value, value, "{}", der);
+            assert_eq!(flags.to_der(), h(der));
+        }
+        assert_eq!(KerberosFlags::new(0x40810010).to_der(), h("03 05 00 40810010"));
+        // a flag set past the end of a short bit string widens it
+        let mut flags = KerberosFlags::from_der(&h("03 03 00 4081")).unwrap();
+        flags.value |= 0x10;
+        assert_eq!(flags.to_der(), h("03 05 00 40810010"));
+        assert!(KerberosFlags::from_der(&h("03 01 01")).is_err());
+        assert!(KerberosFlags::from_der(&h("03 02 08 00")).is_err());
+    }
+}
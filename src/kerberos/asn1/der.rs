@@ -0,0 +1,428 @@
+use crate::kerberos::asn1::Asn1;
+use crate::error::{Error, Result};
+use chrono::NaiveDateTime;
+
+
+pub const BOOLEAN: u8 = 0x01;
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const GENERALIZED_TIME: u8 = 0x18;
+pub const GENERAL_STRING: u8 = 0x1b;
+pub const SEQUENCE: u8 = 0x30;
+
+// KerberosTime is always UTC with no fractional seconds
+const TIME_FORMAT: &str = "%Y%m%d%H%M%SZ";
+
+pub fn context(n: u8) -> u8 {
+    0xa0 | n
+}
+
+pub fn application(n: u8) -> u8 {
+    0x60 | n
+}
+
+
+// Bounds-checked reader over DER, in the style of the keytab cursor.
+// Offsets are absolute and errors name the innermost field being read.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    end: usize,
+    field: &'static str,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8], field: &'static str) -> Reader<'a> {
+        Reader {
+            bytes,
+            offset: 0,
+            end: bytes.len(),
+            field,
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset == self.end
+    }
+
+    pub fn error(&self, reason: String) -> Error {
+        Error::MalformedDer {
+            offset: self.offset,
+            field: self.field,
+            reason,
+        }
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.bytes[self.offset])
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        let byte = self.peek_tag()
+            .ok_or_else(|| self.error("unexpected end of data".to_string()))?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn read_length(&mut self) -> Result<usize> {
+        let first = self.read_byte()?;
+        if first < 0x80 {
+            return Ok(first as usize);
+        }
+        let count = first & 0x7f;
+        if count == 0 {
+            return Err(self.error("indefinite lengths are not allowed in DER".to_string()));
+        }
+        if count > 4 {
+            return Err(self.error(format!("length of {} bytes is too long", count)));
+        }
+        let start = self.offset;
+        let mut length = 0usize;
+        for _ in 0..count {
+            length = (length << 8) | self.read_byte()? as usize;
+        }
+        // DER takes the fewest bytes: the short form below 0x80 and no
+        // leading zeros in the long form
+        if length < 0x80 || self.bytes[start] == 0 {
+            self.offset = start - 1;
+            return Err(self.error(format!("length {} is not minimally encoded", length)));
+        }
+        Ok(length)
+    }
+
+    // Reads any element, returning its tag and a reader over its contents.
+    pub fn any(&mut self) -> Result<(u8, Reader<'a>)> {
+        let tag = self.read_byte()?;
+        // Kerberos never needs tag numbers above 30
+        if tag & 0x1f == 0x1f {
+            return Err(self.error(format!("unsupported high tag number form {:#04x}", tag)));
+        }
+        let length = self.read_length()?;
+        if length > self.end - self.offset {
+            return Err(self.error(format!(
+                "element of {} bytes overruns the {} remaining", length, self.end - self.offset)));
+        }
+        let contents = Reader {
+            bytes: self.bytes,
+            offset: self.offset,
+            end: self.offset + length,
+            field: self.field,
+        };
+        self.offset += length;
+        Ok((tag, contents))
+    }
+
+    pub fn element(&mut self, tag: u8) -> Result<Reader<'a>> {
+        let start = self.offset;
+        let (actual, contents) = self.any()?;
+        if actual != tag {
+            self.offset = start;
+            return Err(self.error(format!("expected tag {:#04x}, found {:#04x}", tag, actual)));
+        }
+        Ok(contents)
+    }
+
+    pub fn sequence(&mut self) -> Result<Reader<'a>> {
+        self.element(SEQUENCE)
+    }
+
+    // Most messages are an [APPLICATION n] wrapper around a single SEQUENCE.
+    pub fn application(&mut self, n: u8) -> Result<Reader<'a>> {
+        let mut wrapper = self.element(application(n))?;
+        let contents = wrapper.sequence()?;
+        wrapper.finish()?;
+        Ok(contents)
+    }
+
+    // Reads an explicitly tagged [n] field holding exactly one value.
+    pub fn field<T, F>(&mut self, n: u8, name: &'static str, read: F) -> Result<T>
+    where
+        F: FnOnce(&mut Reader<'a>) -> Result<T>,
+    {
+        let outer = std::mem::replace(&mut self.field, name);
+        let value = self.element(context(n)).and_then(|mut contents| {
+            let value = read(&mut contents)?;
+            contents.finish()?;
+            Ok(value)
+        });
+        self.field = outer;
+        value
+    }
+
+    pub fn optional_field<T, F>(&mut self, n: u8, name: &'static str, read: F) -> Result<Option<T>>
+    where
+        F: FnOnce(&mut Reader<'a>) -> Result<T>,
+    {
+        if self.peek_tag() == Some(context(n)) {
+            self.field(n, name, read).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Fails if anything is left over, such as a field this codec does not know.
+    pub fn finish(&self) -> Result<()> {
+        if !self.is_empty() {
+            return Err(self.error(format!("{} unexpected trailing bytes", self.end - self.offset)));
+        }
+        Ok(())
+    }
+
+    pub fn bytes(&mut self) -> &'a [u8] {
+        let bytes = &self.bytes[self.offset..self.end];
+        self.offset = self.end;
+        bytes
+    }
+
+    pub fn integer(&mut self) -> Result<i64> {
+        let mut contents = self.element(INTEGER)?;
+        let bytes = contents.bytes();
+        if bytes.is_empty() || bytes.len() > 8 {
+            return Err(self.error(format!("integer of {} bytes", bytes.len())));
+        }
+        // as with lengths, no leading byte that only repeats the sign
+        if let [first, second, ..] = bytes {
+            if (*first == 0x00 && second & 0x80 == 0) || (*first == 0xff && second & 0x80 != 0) {
+                return Err(self.error("integer is not minimally encoded".to_string()));
+            }
+        }
+        // sign extend from the first byte
+        let mut value: i64 = if bytes[0] & 0x80 != 0 { -1 } else { 0 };
+        for b in bytes {
+            value = (value << 8) | *b as i64;
+        }
+        Ok(value)
+    }
+
+    pub fn i32(&mut self) -> Result<i32> {
+        let value = self.integer()?;
+        i32::try_from(value).map_err(|_| self.error(format!("{} does not fit in Int32", value)))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        let value = self.integer()?;
+        u32::try_from(value).map_err(|_| self.error(format!("{} does not fit in UInt32", value)))
+    }
+
+    // DER allows only 0xFF for TRUE
+    pub fn boolean(&mut self) -> Result<bool> {
+        let mut contents = self.element(BOOLEAN)?;
+        match contents.bytes() {
+            [0x00] => Ok(false),
+            [0xff] => Ok(true),
+            [b] => Err(self.error(format!("boolean {:#04x} is neither 0x00 nor 0xff", b))),
+            b => Err(self.error(format!("boolean of {} bytes", b.len()))),
+        }
+    }
+
+    pub fn octet_string(&mut self) -> Result<Vec<u8>> {
+        Ok(self.element(OCTET_STRING)?.bytes().to_vec())
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        let bytes = self.element(GENERAL_STRING)?.bytes();
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| self.error("string is not valid UTF-8".to_string()))
+    }
+
+    // Seconds since the epoch, which KerberosTime's four digit years put
+    // on either side of the 32-bit range
+    pub fn time(&mut self) -> Result<i64> {
+        let bytes = self.element(GENERALIZED_TIME)?.bytes();
+        let text = String::from_utf8_lossy(bytes);
+        let time = NaiveDateTime::parse_from_str(&text, TIME_FORMAT)
+            .map_err(|e| self.error(format!("bad time {:?}: {}", text, e)))?;
+        Ok(time.timestamp())
+    }
+
+    // The unused bits count and the bytes of a BIT STRING
+    pub fn bit_string(&mut self) -> Result<(u8, &'a [u8])> {
+        let bytes = self.element(BIT_STRING)?.bytes();
+        match bytes {
+            [] => Err(self.error("bit string has no unused bits count".to_string())),
+            [unused, ..] if *unused > 7 => Err(self.error(format!("{} unused bits", unused))),
+            [unused] if *unused != 0 => Err(self.error("empty bit string with unused bits".to_string())),
+            [unused, rest @ ..] => Ok((*unused, rest)),
+        }
+    }
+
+    pub fn sequence_of<T: Asn1>(&mut self) -> Result<Vec<T>> {
+        let mut contents = self.sequence()?;
+        let mut items = Vec::new();
+        while !contents.is_empty() {
+            items.push(T::read(&mut contents)?);
+        }
+        Ok(items)
+    }
+}
+
+
+pub fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![tag];
+    let length = contents.len();
+    if length < 0x80 {
+        bytes.push(length as u8);
+    } else {
+        let be = (length as u32).to_be_bytes();
+        let skip = be.iter().take_while(|b| **b == 0).count();
+        bytes.push(0x80 | (4 - skip) as u8);
+        bytes.extend(&be[skip..]);
+    }
+    bytes.extend(contents);
+    bytes
+}
+
+pub fn sequence(fields: Vec<Vec<u8>>) -> Vec<u8> {
+    tlv(SEQUENCE, &fields.concat())
+}
+
+pub fn field(n: u8, value: Vec<u8>) -> Vec<u8> {
+    tlv(context(n), &value)
+}
+
+pub fn integer(value: i64) -> Vec<u8> {
+    let be = value.to_be_bytes();
+    // drop leading bytes that only repeat the sign
+    let mut start = 0;
+    while start < 7 {
+        let redundant = (be[start] == 0x00 && be[start + 1] & 0x80 == 0)
+            || (be[start] == 0xff && be[start + 1] & 0x80 != 0);
+        if !redundant {
+            break;
+        }
+        start += 1;
+    }
+    tlv(INTEGER, &be[start..])
+}
+
+pub fn boolean(value: bool) -> Vec<u8> {
+    tlv(BOOLEAN, &[if value { 0xff } else { 0x00 }])
+}
+
+pub fn octet_string(value: &[u8]) -> Vec<u8> {
+    tlv(OCTET_STRING, value)
+}
+
+pub fn string(value: &str) -> Vec<u8> {
+    tlv(GENERAL_STRING, value.as_bytes())
+}
+
+// The first and last seconds a four digit year can hold; times outside
+// them are written as the nearer one.
+const EARLIEST_TIME: i64 = -62_167_219_200;
+const LATEST_TIME: i64 = 253_402_300_799;
+
+pub fn time(value: i64) -> Vec<u8> {
+    let text = NaiveDateTime::from_timestamp_opt(value.clamp(EARLIEST_TIME, LATEST_TIME), 0)
+        .map(|time| time.format(TIME_FORMAT).to_string())
+        .unwrap_or_default();
+    tlv(GENERALIZED_TIME, text.as_bytes())
+}
+
+pub fn bit_string(unused: u8, value: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![unused];
+    bytes.extend(value);
+    tlv(BIT_STRING, &bytes)
+}
+
+pub fn sequence_of<T: Asn1>(items: &[T]) -> Vec<u8> {
+    sequence(items.iter().map(T::to_der).collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read<'a, T>(bytes: &'a [u8], read: impl FnOnce(&mut Reader<'a>) -> Result<T>) -> Result<T> {
+        let mut der = Reader::new(bytes, "test");
+        let value = read(&mut der)?;
+        der.finish()?;
+        Ok(value)
+    }
+
+    fn octet_string(bytes: &[u8]) -> Result<Vec<u8>> {
+        read(bytes, Reader::octet_string)
+    }
+
+    fn assert_rejected<T: std::fmt::Debug>(bytes: &[u8], result: Result<T>, expected: &str) {
+        match result {
+            Err(Error::MalformedDer { reason, .. }) => assert!(reason.contains(expected), "{}", reason),
+            result => panic!("{:02x?}: {:?}", bytes, result),
+        }
+    }
+
+    #[test]
+    fn lengths_are_minimal() {
+        let mut long = vec![OCTET_STRING, 0x81, 0x80];
+        long.extend([0xab; 0x80]);
+        assert_eq!(octet_string(&long).unwrap(), [0xab; 0x80]);
+        assert_eq!(tlv(OCTET_STRING, &[0xab; 0x80]), long);
+        assert_eq!(octet_string(&[OCTET_STRING, 0x01, 0xab]).unwrap(), [0xab]);
+        // the long form for a short length, and a leading zero
+        for bytes in [&[OCTET_STRING, 0x81, 0x01, 0xab][..], &[OCTET_STRING, 0x82, 0x00, 0x01, 0xab]] {
+            match octet_string(bytes) {
+                Err(Error::MalformedDer { offset: 1, reason, .. }) => assert!(reason.contains("minimally"), "{}", reason),
+                result => panic!("{:02x?}: {:?}", bytes, result),
+            }
+        }
+        let mut padded = vec![OCTET_STRING, 0x82, 0x00, 0x80];
+        padded.extend([0xab; 0x80]);
+        assert!(octet_string(&padded).is_err());
+        assert!(octet_string(&[OCTET_STRING, 0x80, 0xab, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn booleans_are_0x00_or_0xff() {
+        assert_eq!(boolean(false), [BOOLEAN, 0x01, 0x00]);
+        assert_eq!(boolean(true), [BOOLEAN, 0x01, 0xff]);
+        assert!(!read(&[BOOLEAN, 0x01, 0x00], Reader::boolean).unwrap());
+        assert!(read(&[BOOLEAN, 0x01, 0xff], Reader::boolean).unwrap());
+        for bytes in [[BOOLEAN, 0x01, 0x01], [BOOLEAN, 0x01, 0x80]] {
+            assert_rejected(&bytes, read(&bytes, Reader::boolean), "neither");
+        }
+        assert!(read(&[BOOLEAN, 0x02, 0x00, 0xff], Reader::boolean).is_err());
+    }
+
+    #[test]
+    fn integers_are_minimal() {
+        for value in [0, 1, 127, 128, 255, 256, -1, -128, -129, i32::MAX as i64, i32::MIN as i64, i64::MAX, i64::MIN] {
+            let bytes = integer(value);
+            assert_eq!(read(&bytes, Reader::integer).unwrap(), value, "{:02x?}", bytes);
+        }
+        assert_eq!(integer(128), [INTEGER, 0x02, 0x00, 0x80]);
+        assert_eq!(integer(-129), [INTEGER, 0x02, 0xff, 0x7f]);
+        // a leading byte that only repeats the sign of the next
+        for bytes in [&[INTEGER, 0x02, 0x00, 0x01][..], &[INTEGER, 0x02, 0x00, 0x7f], &[INTEGER, 0x02, 0xff, 0xff], &[INTEGER, 0x03, 0xff, 0x80, 0x00]] {
+            assert_rejected(bytes, read(bytes, Reader::integer), "minimally");
+        }
+        assert!(read(&[INTEGER, 0x00], Reader::integer).is_err());
+    }
+
+    #[test]
+    fn times_beyond_32_bits() {
+        for (value, text) in [
+            (0, "19700101000000Z"),
+            (u32::MAX as i64, "21060207062815Z"),
+            (u32::MAX as i64 + 1, "21060207062816Z"),
+            (-1, "19691231235959Z"),
+            (EARLIEST_TIME, "00000101000000Z"),
+            (LATEST_TIME, "99991231235959Z"),
+        ] {
+            let bytes = tlv(GENERALIZED_TIME, text.as_bytes());
+            assert_eq!(time(value), bytes, "{}", text);
+            assert_eq!(read(&bytes, Reader::time).unwrap(), value, "{}", text);
+        }
+        assert_eq!(time(i64::MAX), time(LATEST_TIME));
+        assert_eq!(time(i64::MIN), time(EARLIEST_TIME));
+    }
+}
@@ -0,0 +1,442 @@
+use std::{fmt, fs};
+use std::path::Path;
+use crate::kerberos::reference;
+use crate::kerberos::keytab::{CosKind, CountedOctetString, Keyblock, Principal};
+use crate::kerberos::cursor::{Cursor, FileKind};
+use crate::error::{Error, Result};
+use chrono::{TimeZone, Utc};
+
+pub mod format;
+
+
+// Versions 1 and 2 are written in the host's byte order and lay principals
+// out differently; MIT has not written them in decades. 0x0503 and 0x0504
+// are big endian throughout, and 0x0504 adds header tags.
+#[derive (Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CcacheVersion {
+    V3,
+    V4,
+}
+
+impl CcacheVersion {
+    pub fn from_u16(version: u16) -> Option<CcacheVersion> {
+        match version {
+            0x0503 => Some(CcacheVersion::V3),
+            0x0504 => Some(CcacheVersion::V4),
+            _ => None,
+        }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            CcacheVersion::V3 => 0x0503,
+            CcacheVersion::V4 => 0x0504,
+        }
+    }
+}
+
+
+// The only header tag MIT defines: how far the KDC's clock is ahead of ours
+const TAG_KDC_OFFSET: u16 = 1;
+
+pub enum HeaderTag {
+    KdcOffset { seconds: i32, microseconds: i32 },
+    Other { tag: u16, data: Vec<u8> },
+}
+
+impl HeaderTag {
+    fn read(cursor: &mut Cursor) -> Result<HeaderTag> {
+        let tag = cursor.read_u16("header tag")?;
+        let length = cursor.read_u16("header tag length")?;
+        let mut data = cursor.sub(length as usize, "header tag")?;
+        Ok(match (tag, length) {
+            (TAG_KDC_OFFSET, 8) => HeaderTag::KdcOffset {
+                seconds: data.read_i32("kdc offset")?,
+                microseconds: data.read_i32("kdc offset")?,
+            },
+            _ => HeaderTag::Other {
+                tag,
+                data: data.take(length as usize, "header tag")?.to_vec(),
+            },
+        })
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        match self {
+            HeaderTag::KdcOffset { seconds, microseconds } => {
+                bytes.extend(TAG_KDC_OFFSET.to_be_bytes());
+                bytes.extend(8u16.to_be_bytes());
+                bytes.extend(seconds.to_be_bytes());
+                bytes.extend(microseconds.to_be_bytes());
+            },
+            HeaderTag::Other { tag, data } => {
+                bytes.extend(tag.to_be_bytes());
+                bytes.extend((data.len() as u16).to_be_bytes());
+                bytes.extend(data);
+            },
+        }
+    }
+}
+
+
+pub struct Ccache {
+    pub version: CcacheVersion,
+    // only written out by 0x0504 caches
+    pub header: Vec<HeaderTag>,
+    pub default_principal: Principal,
+    pub credentials: Vec<Credential>,
+}
+
+impl Ccache {
+    pub fn new(default_principal: Principal) -> Ccache {
+        Ccache {
+            version: CcacheVersion::V4,
+            header: Vec::new(),
+            default_principal,
+            credentials: Vec::new(),
+        }
+    }
+
+    pub fn kdc_offset(&self) -> Option<(i32, i32)> {
+        self.header.iter().find_map(|t| match t {
+            HeaderTag::KdcOffset { seconds, microseconds } => Some((*seconds, *microseconds)),
+            HeaderTag::Other { .. } => None,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.version.to_u16().to_be_bytes().to_vec();
+        if self.version == CcacheVersion::V4 {
+            let mut header = Vec::new();
+            for tag in &self.header {
+                tag.write(&mut header);
+            }
+            bytes.extend((header.len() as u16).to_be_bytes());
+            bytes.extend(header);
+        }
+        write_principal(&mut bytes, &self.default_principal);
+        for c in &self.credentials {
+            c.write(&mut bytes, self.version);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Ccache> {
+        let mut cursor = Cursor::with_kind(bytes, FileKind::Ccache);
+        let version = cursor.read_u16("file format version")?;
+        let version = CcacheVersion::from_u16(version)
+            .ok_or_else(|| Error::MalformedCcache {
+                offset: 0,
+                field: "file format version",
+                reason: format!("unsupported version {:#06x}", version),
+            })?;
+        let mut header = Vec::new();
+        if version == CcacheVersion::V4 {
+            let length = cursor.read_u16("header length")?;
+            let mut tags = cursor.sub(length as usize, "header")?;
+            while !tags.is_empty() {
+                header.push(HeaderTag::read(&mut tags)?);
+            }
+        }
+        let default_principal = read_principal(&mut cursor, "default principal")?;
+        let mut credentials = Vec::new();
+        while !cursor.is_empty() {
+            credentials.push(Credential::read(&mut cursor, version)?);
+        }
+        Ok(Ccache {
+            version,
+            header,
+            default_principal,
+            credentials,
+        })
+    }
+
+    pub fn from_file(path: &dyn AsRef<Path>) -> Result<Ccache> {
+        let bytes = fs::read(path)?;
+        Ccache::from_bytes(&bytes)
+    }
+
+    pub fn to_file(&self, path: &dyn AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Ccache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f,"Ccache Format: {}", self.version.to_u16())?;
+        if let Some((seconds, microseconds)) = self.kdc_offset() {
+            writeln!(f,"KDC Offset: {}s {}us", seconds, microseconds)?;
+        }
+        writeln!(f,"Default Principal: {}", self.default_principal)?;
+        writeln!(f,"Credentials: {}", self.credentials.len())?;
+        for (i, c) in self.credentials.iter().enumerate() {
+            writeln!(f,"Credential[{}]", i + 1)?;
+            writeln!(f,"{}", c)?;
+        }
+        write!(f,"")
+    }
+}
+
+
+#[derive (Clone)]
+pub struct Address {
+    pub addr_type: u16,
+    pub data: Vec<u8>,
+}
+
+#[derive (Clone)]
+pub struct Authdata {
+    pub ad_type: u16,
+    pub data: Vec<u8>,
+}
+
+
+#[derive (Clone)]
+pub struct Credential {
+    pub client: Principal,
+    pub server: Principal,
+    pub key: Keyblock,
+    pub authtime: u32,
+    pub starttime: u32,
+    pub endtime: u32,
+    pub renew_till: u32,
+    // set when the ticket is encrypted in a session key (user-to-user)
+    pub is_skey: bool,
+    pub ticket_flags: u32,
+    pub addresses: Vec<Address>,
+    pub authdata: Vec<Authdata>,
+    // the DER encoded Ticket, and the one used for user-to-user if any
+    pub ticket: Vec<u8>,
+    pub second_ticket: Vec<u8>,
+}
+
+impl Credential {
+    // MIT keeps cache settings as fake credentials for this realm.
+    pub fn is_config(&self) -> bool {
+        self.server.realm.data == b"X-CACHECONF:"
+    }
+
+    fn read(cursor: &mut Cursor, version: CcacheVersion) -> Result<Credential> {
+        let client = read_principal(cursor, "client")?;
+        let server = read_principal(cursor, "server")?;
+        let key_type = cursor.read_u16("key type")?;
+        // version 3 repeats the enctype; MIT reads the copy and ignores it,
+        // so a cache where the two differ could not be written back as it was
+        if version == CcacheVersion::V3 {
+            let offset = cursor.offset();
+            let repeated = cursor.read_u16("key type")?;
+            if repeated != key_type {
+                return Err(Error::MalformedCcache {
+                    offset,
+                    field: "key type",
+                    reason: format!("repeated as {} after {}", repeated, key_type),
+                });
+            }
+        }
+        let key = Keyblock {
+            key_type,
+            key: read_counted(cursor, CosKind::Hex, "key")?,
+        };
+        let authtime = cursor.read_u32("authtime")?;
+        let starttime = cursor.read_u32("starttime")?;
+        let endtime = cursor.read_u32("endtime")?;
+        let renew_till = cursor.read_u32("renew till")?;
+        let is_skey = cursor.read_u8("is skey")? != 0;
+        let ticket_flags = cursor.read_u32("ticket flags")?;
+        let mut addresses = Vec::new();
+        for _ in 0..cursor.read_u32("address count")? {
+            addresses.push(Address {
+                addr_type: cursor.read_u16("address type")?,
+                data: read_data(cursor, "address")?,
+            });
+        }
+        let mut authdata = Vec::new();
+        for _ in 0..cursor.read_u32("authdata count")? {
+            authdata.push(Authdata {
+                ad_type: cursor.read_u16("authdata type")?,
+                data: read_data(cursor, "authdata")?,
+            });
+        }
+        Ok(Credential {
+            client,
+            server,
+            key,
+            authtime,
+            starttime,
+            endtime,
+            renew_till,
+            is_skey,
+            ticket_flags,
+            addresses,
+            authdata,
+            ticket: read_data(cursor, "ticket")?,
+            second_ticket: read_data(cursor, "second ticket")?,
+        })
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, version: CcacheVersion) {
+        write_principal(bytes, &self.client);
+        write_principal(bytes, &self.server);
+        bytes.extend(self.key.key_type.to_be_bytes());
+        if version == CcacheVersion::V3 {
+            bytes.extend(self.key.key_type.to_be_bytes());
+        }
+        write_data(bytes, &self.key.key.data);
+        bytes.extend(self.authtime.to_be_bytes());
+        bytes.extend(self.starttime.to_be_bytes());
+        bytes.extend(self.endtime.to_be_bytes());
+        bytes.extend(self.renew_till.to_be_bytes());
+        bytes.push(self.is_skey as u8);
+        bytes.extend(self.ticket_flags.to_be_bytes());
+        bytes.extend((self.addresses.len() as u32).to_be_bytes());
+        for a in &self.addresses {
+            bytes.extend(a.addr_type.to_be_bytes());
+            write_data(bytes, &a.data);
+        }
+        bytes.extend((self.authdata.len() as u32).to_be_bytes());
+        for a in &self.authdata {
+            bytes.extend(a.ad_type.to_be_bytes());
+            write_data(bytes, &a.data);
+        }
+        write_data(bytes, &self.ticket);
+        write_data(bytes, &self.second_ticket);
+    }
+}
+
+impl fmt::Display for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f,"Client: {}", self.client)?;
+        writeln!(f,"Server: {}", self.server)?;
+        if self.is_config() {
+            writeln!(f,"Config: {}", String::from_utf8_lossy(&self.ticket))?;
+            return write!(f,"");
+        }
+        write!(f,"{}", self.key)?;
+        writeln!(f,"Auth Time: {}", format_time(self.authtime))?;
+        writeln!(f,"Start Time: {}", format_time(self.starttime))?;
+        writeln!(f,"End Time: {}", format_time(self.endtime))?;
+        writeln!(f,"Renew Till: {}", format_time(self.renew_till))?;
+        writeln!(f,"Flags: {:#010x} ({})", self.ticket_flags,
+            reference::TICKET_FLAGS.flag_names(self.ticket_flags).join(", "))?;
+        if self.is_skey {
+            writeln!(f,"Session Key Ticket: yes")?;
+        }
+        for a in &self.addresses {
+            writeln!(f,"Address: {} {}", a.addr_type, hex::encode(&a.data))?;
+        }
+        for a in &self.authdata {
+            writeln!(f,"Authdata: {} ({} bytes)", a.ad_type, a.data.len())?;
+        }
+        writeln!(f,"Ticket: {} bytes", self.ticket.len())?;
+        if !self.second_ticket.is_empty() {
+            writeln!(f,"Second Ticket: {} bytes", self.second_ticket.len())?;
+        }
+        write!(f,"")
+    }
+}
+
+fn format_time(time: u32) -> String {
+    match time {
+        0 => "not set".to_string(),
+        t => Utc.timestamp(t as i64, 0).to_rfc2822(),
+    }
+}
+
+
+// ccache strings are counted with 32 bits rather than the keytab's 16
+fn read_data(cursor: &mut Cursor, field: &'static str) -> Result<Vec<u8>> {
+    let length = cursor.read_u32(field)?;
+    Ok(cursor.take(length as usize, field)?.to_vec())
+}
+
+fn read_counted(cursor: &mut Cursor, kind: CosKind, field: &'static str) -> Result<CountedOctetString> {
+    let start = cursor.offset();
+    let data = read_data(cursor, field)?;
+    CountedOctetString::from_uncounted_bytes(data, kind)
+        .map_err(|e| Error::MalformedCcache { offset: start, field, reason: e.to_string() })
+}
+
+fn write_data(bytes: &mut Vec<u8>, data: &[u8]) {
+    bytes.extend((data.len() as u32).to_be_bytes());
+    bytes.extend(data);
+}
+
+fn read_principal(cursor: &mut Cursor, field: &'static str) -> Result<Principal> {
+    let name_type = cursor.read_u32(field)?;
+    let count = cursor.read_u32(field)?;
+    let realm = read_counted(cursor, CosKind::Text, field)?;
+    let mut components = Vec::new();
+    for _ in 0..count {
+        components.push(read_counted(cursor, CosKind::Text, field)?);
+    }
+    Ok(Principal {
+        name_type,
+        realm,
+        components,
+    })
+}
+
+fn write_principal(bytes: &mut Vec<u8>, principal: &Principal) {
+    bytes.extend(principal.name_type.to_be_bytes());
+    bytes.extend((principal.components.len() as u32).to_be_bytes());
+    write_data(bytes, &principal.realm.data);
+    for c in &principal.components {
+        write_data(bytes, &c.data);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::testing::{self, fixture};
+
+    // Caches in fixtures/ccache, from fixtures/gen/ccache.py: a fast_avail
+    // config entry, a TGT and a service ticket with an address, authdata
+    // and a second ticket.
+    fn round_trip(name: &str) -> Ccache {
+        testing::round_trip(&format!("ccache/{}", name), Ccache::from_bytes, Ccache::to_bytes)
+    }
+
+    #[test]
+    fn fixtures_round_trip() {
+        for (name, version) in [("v3.ccache", CcacheVersion::V3), ("v4.ccache", CcacheVersion::V4)] {
+            let ccache = round_trip(name);
+            assert!(ccache.version == version, "{}", name);
+            assert_eq!(ccache.default_principal.to_string(), "user@EXAMPLE.COM");
+            assert_eq!(ccache.credentials.len(), 3);
+            assert!(ccache.credentials[0].is_config());
+            let service = &ccache.credentials[2];
+            assert_eq!(service.server.to_string(), "cifs/fs01.example.com@EXAMPLE.COM");
+            assert_eq!((service.key.key_type, service.ticket_flags, service.is_skey), (23, 0x40250000, true));
+            assert_eq!(service.addresses[0].data, [10, 0, 0, 5]);
+            assert_eq!(service.authdata[0].data, [0x30, 0x00]);
+            assert_eq!(service.second_ticket, [0x61, 0x00]);
+        }
+        assert_eq!(round_trip("v4.ccache").kdc_offset(), Some((-42, 123456)));
+        assert_eq!(round_trip("v3.ccache").kdc_offset(), None);
+    }
+
+    #[test]
+    fn v3_enctypes_must_match() {
+        let mut bytes = fixture("ccache/v3.ccache");
+        let ccache = Ccache::from_bytes(&bytes).unwrap();
+        let config = &ccache.credentials[0];
+        // the version, then the principals before the first key type
+        let mut offset = 2;
+        for principal in [&ccache.default_principal, &config.client, &config.server] {
+            let mut written = Vec::new();
+            write_principal(&mut written, principal);
+            offset += written.len();
+        }
+        assert_eq!(bytes[offset..offset + 4], [0, 0, 0, 0]);
+        bytes[offset + 3] = 18;
+        match Ccache::from_bytes(&bytes) {
+            Err(Error::MalformedCcache { offset: at, field: "key type", .. }) => assert_eq!(at, offset + 2),
+            Err(e) => panic!("{}", e),
+            Ok(_) => panic!("mismatched enctypes were accepted"),
+        }
+    }
+}
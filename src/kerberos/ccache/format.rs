@@ -0,0 +1,185 @@
+use std::io;
+use crate::kerberos::reference;
+use crate::kerberos::asn1::{Asn1, Ticket};
+use crate::kerberos::ccache::{Ccache, Credential};
+use crate::kerberos::keytab::format::{format_timestamp, push_row};
+use crate::error::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+
+// The same renderings `keytab read` offers, for credential caches. Config
+// entries are only shown by the text and JSON dumps.
+
+#[derive (Serialize)]
+struct CsvCredential {
+    client: String,
+    server: String,
+    etype: String,
+    key: String,
+    authtime: u32,
+    starttime: u32,
+    endtime: u32,
+    renew_till: u32,
+    flags: String,
+    ticket: String,
+}
+
+pub fn csv<W: io::Write>(ccache: &Ccache, writer: W) -> Result<()> {
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+    for c in tickets(ccache) {
+        wtr.serialize(CsvCredential {
+            client: c.client.to_string(),
+            server: c.server.to_string(),
+            etype: reference::ENCRYPTION_TYPES.name(c.key.key_type).to_string(),
+            key: c.key.key.decode(),
+            authtime: c.authtime,
+            starttime: c.starttime,
+            endtime: c.endtime,
+            renew_till: c.renew_till,
+            flags: flag_letters(c.ticket_flags),
+            ticket: hex::encode(&c.ticket),
+        })?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+pub fn json(ccache: &Ccache) -> String {
+    let credentials: Vec<Value> = ccache.credentials.iter().map(json_credential).collect();
+    let value = json!({
+        "file_format_version": ccache.version.to_u16(),
+        "kdc_offset": ccache.kdc_offset().map(|(s, us)| json!({
+            "seconds": s,
+            "microseconds": us,
+        })),
+        "default_principal": ccache.default_principal.to_string(),
+        "credentials": credentials,
+    });
+    // serialising a Value cannot fail
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
+fn json_credential(c: &Credential) -> Value {
+    json!({
+        "client": c.client.to_string(),
+        "server": c.server.to_string(),
+        "config": c.is_config(),
+        "etype": c.key.key_type,
+        "etype_name": reference::ENCRYPTION_TYPES.name(c.key.key_type),
+        "key": c.key.key.decode(),
+        "authtime": c.authtime,
+        "starttime": c.starttime,
+        "endtime": c.endtime,
+        "renew_till": c.renew_till,
+        "is_skey": c.is_skey,
+        "flags": c.ticket_flags,
+        "flag_names": reference::TICKET_FLAGS.flag_names(c.ticket_flags),
+        "addresses": c.addresses.iter().map(|a| json!({
+            "type": a.addr_type,
+            "address": hex::encode(&a.data),
+        })).collect::<Vec<Value>>(),
+        "authdata": c.authdata.iter().map(|a| json!({
+            "type": a.ad_type,
+            "data": hex::encode(&a.data),
+        })).collect::<Vec<Value>>(),
+        "ticket": hex::encode(&c.ticket),
+        "second_ticket": hex::encode(&c.second_ticket),
+    })
+}
+
+pub fn table(ccache: &Ccache) -> String {
+    let header = ["Client", "Server", "Etype", "Start", "End", "Renew Till", "Flags"];
+    let rows: Vec<[String; 7]> = tickets(ccache).map(|c| [
+        c.client.to_string(),
+        c.server.to_string(),
+        reference::ENCRYPTION_TYPES.name(c.key.key_type).to_string(),
+        format_timestamp(start(c)),
+        format_timestamp(c.endtime),
+        if c.renew_till == 0 { String::new() } else { format_timestamp(c.renew_till) },
+        flag_letters(c.ticket_flags),
+    ]).collect();
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut out = String::new();
+    push_row(&mut out, &widths, header.iter());
+    push_row(&mut out, &widths, widths.map(|w| "-".repeat(w)).iter());
+    for row in &rows {
+        push_row(&mut out, &widths, row.iter());
+    }
+    out
+}
+
+// Mirrors `klist -e -f`.
+pub fn klist(ccache: &Ccache, name: &str) -> String {
+    let mut out = format!("Ticket cache: FILE:{}\n", name);
+    out.push_str(&format!("Default principal: {}\n\n", ccache.default_principal));
+    out.push_str("Valid starting       Expires              Service principal\n");
+    for c in tickets(ccache) {
+        out.push_str(&format!("{}  {}  {}\n",
+            format_timestamp(start(c)),
+            format_timestamp(c.endtime),
+            c.server,
+        ));
+        let mut extra = Vec::new();
+        if c.renew_till != 0 {
+            extra.push(format!("renew until {}", format_timestamp(c.renew_till)));
+        }
+        let flags = flag_letters(c.ticket_flags);
+        if !flags.is_empty() {
+            extra.push(format!("Flags: {}", flags));
+        }
+        if !extra.is_empty() {
+            out.push_str(&format!("\t{}\n", extra.join(", ")));
+        }
+        let ticket_etype = match Ticket::from_der(&c.ticket) {
+            Ok(t) => reference::ENCRYPTION_TYPES.name(t.enc_part.etype),
+            Err(_) => "unknown",
+        };
+        out.push_str(&format!("\tEtype (skey, tkt): {}, {}\n",
+            reference::ENCRYPTION_TYPES.name(c.key.key_type),
+            ticket_etype,
+        ));
+    }
+    out
+}
+
+fn tickets(ccache: &Ccache) -> impl Iterator<Item = &Credential> {
+    ccache.credentials.iter().filter(|c| !c.is_config())
+}
+
+// As MIT does, an unset start time means the ticket is valid from authtime.
+fn start(c: &Credential) -> u32 {
+    if c.starttime == 0 { c.authtime } else { c.starttime }
+}
+
+// klist's one letter flags, by bit with bit 0 the most significant
+const FLAG_LETTERS: [(u32, char); 14] = [
+    (1, 'F'),
+    (2, 'f'),
+    (3, 'P'),
+    (4, 'p'),
+    (5, 'D'),
+    (6, 'd'),
+    (7, 'i'),
+    (8, 'R'),
+    (9, 'I'),
+    (11, 'H'),
+    (10, 'A'),
+    (12, 'T'),
+    (13, 'O'),
+    (14, 'a'),
+];
+
+pub fn flag_letters(flags: u32) -> String {
+    FLAG_LETTERS.iter()
+        .filter(|(bit, _)| flags & (0x8000_0000 >> bit) != 0)
+        .map(|(_, letter)| *letter)
+        .collect()
+}
@@ -0,0 +1,143 @@
+use byteorder::{ByteOrder, BigEndian, NativeEndian};
+use crate::error::{Error, Result};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Native,
+}
+
+impl Endian {
+    pub fn u16_bytes(self, value: u16) -> [u8; 2] {
+        match self {
+            Endian::Big => value.to_be_bytes(),
+            Endian::Native => value.to_ne_bytes(),
+        }
+    }
+
+    pub fn u32_bytes(self, value: u32) -> [u8; 4] {
+        match self {
+            Endian::Big => value.to_be_bytes(),
+            Endian::Native => value.to_ne_bytes(),
+        }
+    }
+
+    pub fn i32_bytes(self, value: i32) -> [u8; 4] {
+        match self {
+            Endian::Big => value.to_be_bytes(),
+            Endian::Native => value.to_ne_bytes(),
+        }
+    }
+}
+
+
+// Which file a cursor is reading, so errors say what is malformed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    Keytab,
+    Ccache,
+}
+
+
+// Bounds-checked reader over a keytab or ccache buffer. Offsets are always
+// absolute so errors from nested reads point at the right byte in the file.
+pub struct Cursor<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    end: usize,
+    endian: Endian,
+    kind: FileKind,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(bytes: &'a [u8]) -> Cursor<'a> {
+        Cursor::with_kind(bytes, FileKind::Keytab)
+    }
+
+    pub fn with_kind(bytes: &'a [u8], kind: FileKind) -> Cursor<'a> {
+        Cursor {
+            bytes,
+            offset: 0,
+            end: bytes.len(),
+            endian: Endian::Big,
+            kind,
+        }
+    }
+
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.end - self.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    pub fn error(&self, field: &'static str, reason: String) -> Error {
+        let offset = self.offset;
+        match self.kind {
+            FileKind::Keytab => Error::MalformedKeytab { offset, field, reason },
+            FileKind::Ccache => Error::MalformedCcache { offset, field, reason },
+        }
+    }
+
+    pub fn take(&mut self, length: usize, field: &'static str) -> Result<&'a [u8]> {
+        if length > self.remaining() {
+            return Err(self.error(field, format!(
+                "needs {} bytes, {} available", length, self.remaining())));
+        }
+        let bytes = &self.bytes[self.offset..self.offset + length];
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    // Splits off the next `length` bytes as a cursor of their own, so a
+    // record cannot read past its declared size.
+    pub fn sub(&mut self, length: usize, field: &'static str) -> Result<Cursor<'a>> {
+        let start = self.offset;
+        self.take(length, field)?;
+        Ok(Cursor {
+            bytes: self.bytes,
+            offset: start,
+            end: start + length,
+            endian: self.endian,
+            kind: self.kind,
+        })
+    }
+
+    pub fn read_u8(&mut self, field: &'static str) -> Result<u8> {
+        Ok(self.take(1, field)?[0])
+    }
+
+    pub fn read_u16(&mut self, field: &'static str) -> Result<u16> {
+        let bytes = self.take(2, field)?;
+        Ok(match self.endian {
+            Endian::Big => BigEndian::read_u16(bytes),
+            Endian::Native => NativeEndian::read_u16(bytes),
+        })
+    }
+
+    pub fn read_u32(&mut self, field: &'static str) -> Result<u32> {
+        let bytes = self.take(4, field)?;
+        Ok(match self.endian {
+            Endian::Big => BigEndian::read_u32(bytes),
+            Endian::Native => NativeEndian::read_u32(bytes),
+        })
+    }
+
+    pub fn read_i32(&mut self, field: &'static str) -> Result<i32> {
+        let bytes = self.take(4, field)?;
+        Ok(match self.endian {
+            Endian::Big => BigEndian::read_i32(bytes),
+            Endian::Native => NativeEndian::read_i32(bytes),
+        })
+    }
+}
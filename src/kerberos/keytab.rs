@@ -1,113 +1,283 @@
-use std::{fmt, fs, error::Error};
+use std::{fmt, fs, io};
 use std::path::Path;
-use byteorder::{ByteOrder, BigEndian};
 use crate::kerberos::reference;
+use crate::crypto::etype;
+use crate::kerberos::cursor::{Cursor, Endian};
+use crate::error::{Error, Result};
+use crate::utils::utils::timestamp_now;
 use chrono::{TimeZone, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+pub mod format;
+
+
+// 0x0501 keytabs are written in the host's byte order, count the realm as
+// one of the principal components and carry no name type. 0x0502 keytabs
+// are big endian throughout.
+#[derive (Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum KeytabVersion {
+    V1,
+    V2,
+}
+
+impl KeytabVersion {
+    pub fn from_u16(version: u16) -> Option<KeytabVersion> {
+        match version {
+            0x0501 => Some(KeytabVersion::V1),
+            0x0502 => Some(KeytabVersion::V2),
+            _ => None,
+        }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            KeytabVersion::V1 => 0x0501,
+            KeytabVersion::V2 => 0x0502,
+        }
+    }
+
+    pub(crate) fn endian(self) -> Endian {
+        match self {
+            KeytabVersion::V1 => Endian::Native,
+            KeytabVersion::V2 => Endian::Big,
+        }
+    }
+}
 
 
 #[derive (Deserialize)]
 pub struct Keytab {
-    pub file_format_version: u16,
-    pub entries: Vec<KeytabEntry>,
+    pub file_format_version: KeytabVersion,
+    pub records: Vec<KeytabRecord>,
 }
 
 impl Keytab {
     pub fn new(entries: Vec<KeytabEntry>) -> Keytab {
         Keytab {
-            file_format_version: 1282,
-            entries,
+            file_format_version: KeytabVersion::V2,
+            records: entries.into_iter().map(KeytabRecord::Entry).collect(),
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &KeytabEntry> {
+        self.records.iter().filter_map(|r| match r {
+            KeytabRecord::Entry(e) => Some(e),
+            KeytabRecord::Hole(_) => None,
+        })
+    }
+
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = &mut KeytabEntry> {
+        self.records.iter_mut().filter_map(|r| match r {
+            KeytabRecord::Entry(e) => Some(e),
+            KeytabRecord::Hole(_) => None,
+        })
+    }
+
+    pub fn holes(&self) -> impl Iterator<Item = &KeytabHole> {
+        self.records.iter().filter_map(|r| match r {
+            KeytabRecord::Entry(_) => None,
+            KeytabRecord::Hole(h) => Some(h),
+        })
+    }
+
+    // Drops the holes left behind by deleted entries.
+    pub fn compact(&mut self) {
+        self.records.retain(|r| matches!(r, KeytabRecord::Entry(_)));
+    }
+
+    // Appends the entries of another keytab, skipping any that share a
+    // principal, kvno and etype with an entry already present.
+    pub fn merge(&mut self, other: Keytab) -> usize {
+        let mut added = 0;
+        for record in other.records {
+            if let KeytabRecord::Entry(entry) = record {
+                if !self.entries().any(|e| e.same_key_slot(&entry)) {
+                    self.records.push(KeytabRecord::Entry(entry));
+                    added += 1;
+                }
+            }
+        }
+        added
+    }
+
+    // Removes the matching entries outright, returning how many went.
+    pub fn remove(&mut self, filter: &EntryFilter) -> usize {
+        let before = self.records.len();
+        self.records.retain(|r| match r {
+            KeytabRecord::Entry(e) => !filter.matches(e),
+            KeytabRecord::Hole(_) => true,
+        });
+        before - self.records.len()
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<usize> {
+        let mut renamed = 0;
+        for entry in self.entries_mut().filter(|e| e.principal() == from) {
+            entry.set_principal(to)?;
+            renamed += 1;
+        }
+        Ok(renamed)
+    }
+
+    pub fn set_kvno(&mut self, filter: &EntryFilter, kvno: u32) -> usize {
+        let mut changed = 0;
+        for entry in self.entries_mut().filter(|e| filter.matches(e)) {
+            entry.set_kvno(kvno);
+            changed += 1;
         }
+        changed
     }
+
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes: Vec<u8> = self.file_format_version.to_be_bytes().to_vec();
-        for e in &self.entries {
-            bytes.extend(e.to_bytes());
+        // the version is two single bytes, so it is never byte swapped
+        let mut bytes: Vec<u8> = self.file_format_version.to_u16().to_be_bytes().to_vec();
+        for r in &self.records {
+            match r {
+                KeytabRecord::Entry(e) => bytes.extend(e.to_bytes(self.file_format_version)),
+                KeytabRecord::Hole(h) => bytes.extend(h.to_bytes(self.file_format_version)),
+            }
         }
         bytes
     }
-    pub fn from_bytes(bytes: &Vec<u8>) -> Keytab {
-        let file_format_version = BigEndian::read_u16(&bytes[0..2].to_vec());
-        let mut spl: usize = 2;
-        let mut entries: Vec<KeytabEntry> = Vec::new();
-        while spl < bytes.len() {
-            let entry_size = BigEndian::read_i32(&bytes[spl..spl + 4].to_vec());
+    pub fn from_bytes(bytes: &[u8]) -> Result<Keytab> {
+        let mut cursor = Cursor::new(bytes);
+        let version = cursor.read_u16("file format version")?;
+        let file_format_version = KeytabVersion::from_u16(version)
+            .ok_or_else(|| Error::MalformedKeytab {
+                offset: 0,
+                field: "file format version",
+                reason: format!("unsupported version {:#06x}", version),
+            })?;
+        cursor.set_endian(file_format_version.endian());
+        let mut records: Vec<KeytabRecord> = Vec::new();
+        while !cursor.is_empty() {
+            let start = cursor.offset();
+            let entry_size = cursor.read_i32("entry size")?;
+            // MIT stops reading at a zero size, which would silently drop
+            // whatever follows it
+            if entry_size == 0 {
+                return Err(Error::MalformedKeytab {
+                    offset: start,
+                    field: "entry size",
+                    reason: "zero size entry".to_string(),
+                });
+            }
+            if entry_size == i32::MIN {
+                return Err(Error::MalformedKeytab {
+                    offset: start,
+                    field: "entry size",
+                    reason: "size cannot be negated".to_string(),
+                });
+            }
             if entry_size.is_positive(){
-                let entry = &bytes[spl..spl + (entry_size as usize)].to_vec();
-                entries.push(KeytabEntry::from_bytes(entry));
+                let mut entry = cursor.sub(entry_size as usize, "entry")?;
+                records.push(KeytabRecord::Entry(
+                    KeytabEntry::read(file_format_version, &mut entry)?));
+            } else {
+                // a negative size marks a deleted entry, skip over its -size bytes
+                let data = cursor.take(entry_size.unsigned_abs() as usize, "hole")?;
+                records.push(KeytabRecord::Hole(KeytabHole { data: data.to_vec() }));
             }
-            spl += entry_size as usize;
         }
-        Keytab {
+        Ok(Keytab {
             file_format_version,
-            entries }
+            records })
+    }
+    pub fn from_csv(path: &dyn AsRef<Path>) -> Result<Keytab> {
+        Keytab::from_csv_reader(fs::File::open(path)?)
     }
-    pub fn from_csv(path: &dyn AsRef<Path>) -> Result<Keytab, Box<dyn Error>> {
+
+    // Headerless rows of principal, etype, key, name type, timestamp and
+    // kvno, as format::csv writes them.
+    pub fn from_csv_reader<R: io::Read>(reader: R) -> Result<Keytab> {
         let mut rdr = csv::ReaderBuilder::new()
             .has_headers(false)
-            .from_path(path)?;
+            .from_reader(reader);
         let mut entries: Vec<KeytabEntry> = Vec::new();
         for result in rdr.deserialize() {
             let entry: CsvEntry = result?;
             let name_type = match entry.kind {
                 Some(k) => k,
-                _ => "krb_nt_principal".to_string(),
+                _ => "krb5_nt_principal".to_string(),
             };
             let timestamp: u32 = match entry.timestamp {
-                Some(t) => t.try_into().unwrap(),
-                _ => Utc::now().timestamp().try_into().unwrap(),
-            };
-            let version: u32 = match entry.version {
-                Some(v) => v.try_into().unwrap(),
-                _ => 0,
+                Some(t) => t,
+                _ => timestamp_now(),
             };
+            let version: u32 = entry.version.unwrap_or(0);
             let entry = KeytabEntry::new(
                 &entry.principal,
                 &name_type,
                 &timestamp,
-                &version.try_into().unwrap(),
+                &version,
                 &entry.etype,
                 &entry.key,
-                &version,
-            );
+            )?;
             entries.push(entry);
         };
         let keytab = Keytab::new(entries);
         Ok(keytab)
     }
 
-    pub fn from_file(path: &dyn AsRef<Path>) -> Result<Keytab, Box<dyn Error>> {
+    pub fn from_file(path: &dyn AsRef<Path>) -> Result<Keytab> {
         let bytes = fs::read(path)?;
-        Ok(Keytab::from_bytes(&bytes))
+        Keytab::from_bytes(&bytes)
     }
     
-    pub fn to_file(&self, path: &dyn AsRef<Path>) -> Result<(), Box<dyn Error>> {
-        fs::write(path, &self.to_bytes())?;
+    pub fn to_file(&self, path: &dyn AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_bytes())?;
         Ok(())
     }
 }
  
 
+#[derive (Deserialize)]
+pub enum KeytabRecord {
+    Entry(KeytabEntry),
+    Hole(KeytabHole),
+}
+
+
+// Space left behind by a deleted entry. The old contents are kept so the
+// keytab can be written back out unchanged.
+#[derive (Deserialize)]
+pub struct KeytabHole {
+    pub data: Vec<u8>,
+}
+
+impl KeytabHole {
+    pub fn to_bytes(&self, version: KeytabVersion) -> Vec<u8> {
+        let mut bytes = version.endian().i32_bytes(-(self.data.len() as i32)).to_vec();
+        bytes.extend(&self.data);
+        bytes
+    }
+}
+
+
 #[derive (Deserialize)]
 pub struct KeytabEntry {
-    size: i32,
-    num_components: u16,
-    realm: CountedOctetString,
-    components: Vec<CountedOctetString>,
-    name_type: u32,
-    timestamp: u32,
-    vno8: u8,
-    key: Keyblock,
-    vno: u32,
+    pub name: Principal,
+    pub timestamp: u32,
+    pub vno8: u8,
+    pub key: Keyblock,
+    // only present when the entry has room for it
+    pub vno: Option<u32>,
+    // MIT reuses holes bigger than the entry, leaving unused bytes at the end
+    pub padding: Vec<u8>,
 }
 
 impl fmt::Display for Keytab {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f,"Keytab Format: {}\n", self.file_format_version)?;
-        write!(f,"Entries: {}\n", self.entries.len())?;
-        for (i, entry) in self.entries.iter().enumerate() {
-            write!(f,"Entry[{}]\n", i + 1)?;
-            write!(f,"{}\n", entry)?;
+        writeln!(f,"Keytab Format: {}", self.file_format_version.to_u16())?;
+        writeln!(f,"Entries: {}", self.entries().count())?;
+        let holes = self.holes().count();
+        if holes > 0 {
+            writeln!(f,"Holes: {} ({} bytes)", holes, self.holes().map(|h| h.data.len()).sum::<usize>())?;
+        }
+        for (i, entry) in self.entries().enumerate() {
+            writeln!(f,"Entry[{}]", i + 1)?;
+            writeln!(f,"Size: {} bytes", entry.size(self.file_format_version))?;
+            writeln!(f,"{}", entry)?;
         };
         write!(f,"")
     }
@@ -115,114 +285,246 @@ impl fmt::Display for Keytab {
 
 impl fmt::Display for KeytabEntry {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut principal: String = String::new(); 
-        for i in 0..self.num_components as usize {
-            principal.push_str(&self.components[i].to_string());
-            principal.push('/'); 
-        }
-        principal.pop(); 
-        principal.push('@'); 
-        principal.push_str(&self.realm.to_string()); 
-        write!(f,"Size: {} bytes\n", self.size)?;
-        write!(f,"Principal: {}\n", principal)?;
-        write!(f,"Name Type: {}\n", (reference::PRINCIPAL_TYPES.lookup(&self.name_type)).name)?;
-        write!(f,"Timestamp: {}\n", (Utc.timestamp(self.timestamp as i64, 0)).to_rfc2822())?;
-        write!(f,"Vno8: {}\n", self.vno8)?;
+        writeln!(f,"Principal: {}", self.principal())?;
+        writeln!(f,"Name Type: {}", reference::PRINCIPAL_TYPES.name(self.name.name_type))?;
+        writeln!(f,"Timestamp: {}", (Utc.timestamp(self.timestamp as i64, 0)).to_rfc2822())?;
+        writeln!(f,"Kvno: {}", self.kvno())?;
+        writeln!(f,"Vno8: {}", self.vno8)?;
         write!(f,"{}", self.key)?;
-        write!(f,"Vno: {}\n", self.vno)
+        match self.vno {
+            Some(vno) => writeln!(f,"Vno: {}", vno),
+            None => writeln!(f,"Vno: not present"),
+        }
     }
 }
 
 impl KeytabEntry {
-    fn from_bytes(bytes: &Vec<u8>) -> KeytabEntry {
-        let size = BigEndian::read_i32(&bytes[0..4].to_vec());
-        let num_components = BigEndian::read_u16(&bytes[4..6].to_vec());
-        let r_size = BigEndian::read_u16(&bytes[6..8].to_vec());
-        let realm = CountedOctetString::from_bytes(&bytes[6.. r_size as usize + 8].to_vec(), CosKind::Text);
-        let mut spl: usize = 8 + r_size as usize;
+    pub fn from_bytes(bytes: &[u8], version: KeytabVersion) -> Result<KeytabEntry> {
+        let mut cursor = Cursor::new(bytes);
+        cursor.set_endian(version.endian());
+        let size = cursor.read_i32("entry size")?;
+        if !size.is_positive() {
+            return Err(Error::MalformedKeytab {
+                offset: 0,
+                field: "entry size",
+                reason: format!("expected a positive size, got {}", size),
+            });
+        }
+        let mut entry = cursor.sub(size as usize, "entry")?;
+        KeytabEntry::read(version, &mut entry)
+    }
+
+    pub(crate) fn read(version: KeytabVersion, cursor: &mut Cursor) -> Result<KeytabEntry> {
+        let mut num_components = cursor.read_u16("component count")?;
+        if version == KeytabVersion::V1 {
+            num_components = num_components.checked_sub(1)
+                .ok_or_else(|| cursor.error("component count", "count must include the realm".to_string()))?;
+        }
+        let realm = CountedOctetString::read(cursor, CosKind::Text, "realm")?;
         let mut components: Vec<CountedOctetString> =Vec::new();
         for _ in 0..num_components {
-            let s_size = BigEndian::read_u16(&bytes[spl..(spl + 2)].to_vec());
-            let component = CountedOctetString::from_bytes(
-                &bytes[spl..(spl + s_size as usize + 2)]
-                .to_vec(),
-                CosKind::Text);
-            components.push(component);
-            spl += s_size as usize + 2;
+            components.push(CountedOctetString::read(cursor, CosKind::Text, "component")?);
         } 
-        let name_type = BigEndian::read_u32(&bytes[spl..spl+4].to_vec());
-        spl += 4;
-        let timestamp = BigEndian::read_u32(&bytes[spl..spl+4].to_vec());
-        spl += 4;
-        let vno8: u8 = bytes[spl];
-        spl += 1;
-        let k_size = BigEndian::read_u16(&bytes[spl + 2..spl + 4].to_vec());
-        let key = Keyblock::from_bytes(&bytes[spl..(spl + k_size as usize + 4)].to_vec());
-        spl += 4 + k_size as usize;
-        let vno = BigEndian::read_u32(&bytes[spl..spl+4].to_vec());
-        KeytabEntry {
-            size,
-            num_components,
-            realm,
-            components,
-            name_type,
+        let name_type = match version {
+            KeytabVersion::V1 => 0,
+            KeytabVersion::V2 => cursor.read_u32("name type")?,
+        };
+        let timestamp = cursor.read_u32("timestamp")?;
+        let vno8 = cursor.read_u8("vno8")?;
+        let key = Keyblock::read(cursor)?;
+        // older writers stop after the key, so the 32 bit kvno is optional
+        let vno = if cursor.remaining() >= 4 {
+            Some(cursor.read_u32("vno")?)
+        } else {
+            None
+        };
+        let padding = cursor.take(cursor.remaining(), "padding")?.to_vec();
+        Ok(KeytabEntry {
+            name: Principal {
+                name_type,
+                realm,
+                components,
+            },
             timestamp,
             vno8,
             key,
             vno,
-        } 
+            padding,
+        })
     }
 
-    fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes: Vec<u8> = self.size.to_be_bytes().to_vec();
-        bytes.extend(self.num_components.to_be_bytes().to_vec());
-        bytes.extend(self.realm.to_bytes());
-        for c in &self.components {
-            bytes.extend(c.to_bytes());
+    pub fn to_bytes(&self, version: KeytabVersion) -> Vec<u8> {
+        let endian = version.endian();
+        let mut num_components = self.name.components.len() as u16;
+        if version == KeytabVersion::V1 {
+            num_components += 1;
+        }
+        let mut body: Vec<u8> = endian.u16_bytes(num_components).to_vec();
+        self.name.realm.write(&mut body, endian);
+        for c in &self.name.components {
+            c.write(&mut body, endian);
         } 
-        bytes.extend(self.name_type.to_be_bytes().to_vec());
-        bytes.extend(self.timestamp.to_be_bytes().to_vec());
-        bytes.extend(self.vno8.to_be_bytes().to_vec());
-        bytes.extend(self.key.to_bytes());
-        bytes.extend(self.vno.to_be_bytes().to_vec());
+        if version == KeytabVersion::V2 {
+            body.extend(endian.u32_bytes(self.name.name_type));
+        }
+        body.extend(endian.u32_bytes(self.timestamp));
+        body.push(self.vno8);
+        self.key.write(&mut body, endian);
+        if let Some(vno) = self.vno {
+            body.extend(endian.u32_bytes(vno));
+        }
+        body.extend(&self.padding);
+        let mut bytes = endian.i32_bytes(body.len() as i32).to_vec();
+        bytes.extend(body);
         bytes
     }
-    pub fn new(principal: &String, name_type: &String,
-           timestamp: &u32, vno8: &u8,
-           etype: &String, key: &String,
-           vno: &u32) -> KeytabEntry { 
-        let name_type: u32 = (reference::PRINCIPAL_TYPES.lookup(name_type)).id.try_into().unwrap();
-        let principal: Vec<&str> = principal.split('@').collect();
-        let components = CountedOctetString::from_components(principal[0]);
-        let num_components: u16 = components.len().try_into().unwrap();
-        let realm = CountedOctetString::from_string(principal[1]);
-        let key = Keyblock::new(&etype, &key);
-        let size:i32 = 25 + i32::from(
-            realm.length + 
-            key.key.length + 
-            &components.iter().map(
-                |x| x.length + 2).sum()
-            );
-        KeytabEntry {
-            size,
-            num_components,
-            realm,
-            components,
-            name_type,
+    pub fn new(principal: &str, name_type: &str,
+           timestamp: &u32, kvno: &u32,
+           etype: &str, key: &str) -> Result<KeytabEntry> { 
+        let key = Keyblock::new(etype, key)?;
+        KeytabEntry::with_key(principal, name_type, timestamp, kvno, key)
+    }
+
+    pub fn from_password(principal: &str, name_type: &str,
+           timestamp: &u32, kvno: &u32,
+           etype: &str, password: &str, salt: &str) -> Result<KeytabEntry> {
+        let etype = etype::lookup(etype)?;
+        let key = etype.string_to_key(password, salt, None)?;
+        let key = Keyblock {
+            key_type: etype.id() as u16,
+            key: CountedOctetString::from_uncounted_bytes(key, CosKind::Hex)?,
+        };
+        KeytabEntry::with_key(principal, name_type, timestamp, kvno, key)
+    }
+
+    fn with_key(principal: &str, name_type: &str,
+           timestamp: &u32, kvno: &u32,
+           key: Keyblock) -> Result<KeytabEntry> {
+        // types missing from the table can still be given by number
+        let name_type: u32 = reference::PRINCIPAL_TYPES.lookup(name_type)
+            .map(|item| item.id as u32)
+            .or_else(|| name_type.parse().ok())
+            .ok_or_else(|| Error::UnknownNameType(name_type.to_string()))?;
+        let mut entry = KeytabEntry {
+            name: Principal::new("", name_type)?,
             timestamp: *timestamp,
-            vno8: *vno8,
+            vno8: 0,
             key,
-            vno: *vno,
+            vno: None,
+            padding: Vec::new(),
+        };
+        entry.set_principal(principal)?;
+        entry.set_kvno(*kvno);
+        Ok(entry)
+    }
+
+    pub fn principal(&self) -> String {
+        self.name.to_string()
+    }
+
+    pub fn set_principal(&mut self, principal: &str) -> Result<()> {
+        let name = Principal::new(principal, self.name.name_type)?;
+        // leave room for the realm, which 0x0501 keytabs count as a component
+        if name.components.len() >= u16::MAX as usize {
+            return Err(Error::FieldTooLong(name.components.len()));
         }
+        self.name = name;
+        Ok(())
+    }
+
+    // kvnos above 255 only fit in the trailing field, but it is written for
+    // every kvno as MIT does: readers that find it ignore vno8, and one left
+    // over from an earlier kvno would otherwise override the new vno8.
+    pub fn set_kvno(&mut self, kvno: u32) {
+        self.vno8 = kvno as u8;
+        self.vno = Some(kvno);
+    }
+
+    // MIT rules: the 32 bit kvno wins when present and non-zero
+    pub fn kvno(&self) -> u32 {
+        match self.vno {
+            Some(vno) if vno != 0 => vno,
+            _ => u32::from(self.vno8),
+        }
+    }
+
+    fn same_key_slot(&self, other: &KeytabEntry) -> bool {
+        self.principal() == other.principal()
+            && self.kvno() == other.kvno()
+            && self.key.key_type == other.key.key_type
+    }
+
+    pub fn size(&self, version: KeytabVersion) -> i32 {
+        (self.to_bytes(version).len() - 4) as i32
     }
 }
 
 
-#[derive (Deserialize)]
-struct CountedOctetString {
-    length: u16,
-    data: Vec<u8>,
-    kind: CosKind,
+// A principal as keytabs and credential caches hold it. Each file format
+// lays the fields out differently, so reading and writing live with them.
+#[derive (Clone, Deserialize)]
+pub struct Principal {
+    pub name_type: u32,
+    pub realm: CountedOctetString,
+    pub components: Vec<CountedOctetString>,
+}
+
+impl fmt::Display for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let components: Vec<String> = self.components.iter().map(|c| c.decode()).collect();
+        write!(f, "{}@{}", components.join("/"), self.realm.decode())
+    }
+}
+
+impl Principal {
+    // Parses name/instance@REALM. A missing realm is left empty.
+    pub fn new(principal: &str, name_type: u32) -> Result<Principal> {
+        let (components, realm) = principal.rsplit_once('@').unwrap_or((principal, ""));
+        Ok(Principal {
+            name_type,
+            realm: CountedOctetString::from_string(realm)?,
+            components: CountedOctetString::from_components(components)?,
+        })
+    }
+}
+
+
+// Selects entries by principal, kvno and etype. Unset fields match anything.
+#[derive (Default)]
+pub struct EntryFilter {
+    pub principal: Option<String>,
+    pub kvno: Option<u32>,
+    pub etype: Option<u16>,
+}
+
+impl EntryFilter {
+    pub fn new(principal: Option<&str>, kvno: Option<u32>, etype: Option<&str>) -> Result<EntryFilter> {
+        let etype = match etype {
+            Some(etype) => Some(reference::ENCRYPTION_TYPES.lookup(etype)
+                .ok_or_else(|| Error::UnknownEtype(etype.to_string()))?
+                .id as u16),
+            None => None,
+        };
+        Ok(EntryFilter {
+            principal: principal.map(str::to_string),
+            kvno,
+            etype,
+        })
+    }
+
+    pub fn matches(&self, entry: &KeytabEntry) -> bool {
+        self.principal.as_ref().map_or(true, |p| *p == entry.principal())
+            && self.kvno.map_or(true, |k| k == entry.kvno())
+            && self.etype.map_or(true, |t| t == entry.key.key_type)
+    }
+}
+
+
+#[derive (Clone, Deserialize)]
+pub struct CountedOctetString {
+    pub length: u16,
+    pub data: Vec<u8>,
+    pub kind: CosKind,
 }
 
 impl fmt::Display for CountedOctetString {
@@ -231,117 +533,136 @@ impl fmt::Display for CountedOctetString {
             CosKind::Text => "Decoded:",
             CosKind::Hex => "Key:",
         };
-        write!(f,"{} {} ({} bytes)\n", msg, self.to_string(), self.length)
+        writeln!(f,"{} {} ({} bytes)", msg, self.decode(), self.length)
     }
 }
 
 impl CountedOctetString {
-    fn from_hex(data: &str)  -> CountedOctetString {
-        let hexbytes = match hex::decode(data) {
-            Ok(decoded) => decoded,
-            Err(_) => panic!("dead"),
-        };
+    pub fn from_hex(data: &str)  -> Result<CountedOctetString> {
+        let hexbytes = hex::decode(data)
+            .map_err(|e| Error::BadHex(format!("{} ({})", data, e)))?;
         CountedOctetString::from_uncounted_bytes(hexbytes, CosKind::Hex)
     }
 
-    fn from_string(data: &str) -> CountedOctetString {
+    pub fn from_string(data: &str) -> Result<CountedOctetString> {
         let strbytes = data.as_bytes().to_vec();
         CountedOctetString::from_uncounted_bytes(strbytes, CosKind::Text)
     }
 
-    fn from_components(data: &str) -> Vec<CountedOctetString> {
+    pub fn from_components(data: &str) -> Result<Vec<CountedOctetString>> {
         let components: Vec<&str> = data.split('/').collect();
         let mut cosv = Vec::new(); 
         for c in components {
-            let c = CountedOctetString::from_uncounted_bytes(c.as_bytes().to_vec(), CosKind::Text);
+            let c = CountedOctetString::from_uncounted_bytes(c.as_bytes().to_vec(), CosKind::Text)?;
             cosv.push(c);
         }
-        cosv
+        Ok(cosv)
     }
 
-    fn from_uncounted_bytes(data: Vec<u8>, kind: CosKind) -> CountedOctetString {
-        let length: u16 = data.len().try_into().unwrap();
-        CountedOctetString {
+    pub fn from_uncounted_bytes(data: Vec<u8>, kind: CosKind) -> Result<CountedOctetString> {
+        let length = u16::try_from(data.len())
+            .map_err(|_| Error::FieldTooLong(data.len()))?;
+        Ok(CountedOctetString {
             length,
             data,
             kind, 
-        }
+        })
     }
     
-    fn to_string(&self) -> String {
-        let data = self.data.clone();
-        let decoded = match self.kind {
-            CosKind::Text => String::from_utf8(data).unwrap(),
-            CosKind::Hex => hex::encode(data), 
-        };
-        decoded.to_string()
+    pub fn decode(&self) -> String {
+        match self.kind {
+            CosKind::Text => String::from_utf8_lossy(&self.data).to_string(),
+            CosKind::Hex => hex::encode(&self.data), 
+        }
     }
 
-    fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes  = self.length.to_be_bytes().to_vec();
-        bytes.extend(self.data.clone());
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes, Endian::Big);
         bytes 
     }
 
-    fn from_bytes(bytes: &Vec<u8>, kind: CosKind) -> CountedOctetString {
-        let length = BigEndian::read_u16(&bytes[0..2].to_vec()); 
-        let data = bytes[2..].to_vec();
-        CountedOctetString {
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>, endian: Endian) {
+        bytes.extend(endian.u16_bytes(self.length));
+        bytes.extend(&self.data);
+    }
+
+    pub fn from_bytes(bytes: &[u8], kind: CosKind) -> Result<CountedOctetString> {
+        CountedOctetString::read(&mut Cursor::new(bytes), kind, "counted octet string")
+    }
+
+    pub(crate) fn read(cursor: &mut Cursor, kind: CosKind, field: &'static str) -> Result<CountedOctetString> {
+        let length = cursor.read_u16(field)?; 
+        let data = cursor.take(length as usize, field)?.to_vec();
+        Ok(CountedOctetString {
             length,
             data,
             kind,
-        }
+        })
     }
 }
 
 
-#[derive (Deserialize)]
-enum CosKind {
+#[derive (Clone, Copy, Deserialize)]
+pub enum CosKind {
     Text,
     Hex,
 }
 
 
-#[derive (Deserialize)]
-struct Keyblock {
-    key_type: u16,
-    key: CountedOctetString,
+#[derive (Clone, Deserialize)]
+pub struct Keyblock {
+    pub key_type: u16,
+    pub key: CountedOctetString,
 }
 
 impl fmt::Display for Keyblock {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f,"Type: {} ({})\n", (reference::ENCRYPTION_TYPES.lookup(self.key_type)).name, self.key_type)?;
+        writeln!(f,"Type: {} ({})", reference::ENCRYPTION_TYPES.name(self.key_type), self.key_type)?;
         write!(f,"{}", self.key)
     }
 }
 
 impl Keyblock {
-    fn to_bytes(&self) -> Vec<u8> { 
-        let mut bytes = self.key_type.to_be_bytes().to_vec();
-        bytes.extend(self.key.to_bytes());
+    pub fn to_bytes(&self) -> Vec<u8> { 
+        let mut bytes = Vec::new();
+        self.write(&mut bytes, Endian::Big);
         bytes
     }
 
-    fn new(etype: &String, key: &String) -> Keyblock {
-        let key = CountedOctetString::from_hex(key);
-        let etype: u16 = (reference::ENCRYPTION_TYPES.lookup(etype)).id.try_into().unwrap();
-       Keyblock {
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>, endian: Endian) {
+        bytes.extend(endian.u16_bytes(self.key_type));
+        self.key.write(bytes, endian);
+    }
+
+    pub fn new(etype: &str, key: &str) -> Result<Keyblock> {
+        let key = CountedOctetString::from_hex(key)?;
+        let etype: u16 = reference::ENCRYPTION_TYPES.lookup(etype)
+            .map(|item| item.id as u16)
+            .or_else(|| etype.parse().ok())
+            .ok_or_else(|| Error::UnknownEtype(etype.to_string()))?;
+       Ok(Keyblock {
            key_type: etype,
            key,
-       }
+       })
     }
     
-    fn from_bytes(bytes: &Vec<u8>) -> Keyblock {
-        Keyblock {
-            key_type: BigEndian::read_u16(&bytes[0..2].to_vec()),
-            key: CountedOctetString::from_bytes(&bytes[2..].to_vec(), CosKind::Hex),
-        }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Keyblock> {
+        Keyblock::read(&mut Cursor::new(bytes))
+    }
+
+    pub(crate) fn read(cursor: &mut Cursor) -> Result<Keyblock> {
+        let key_type = cursor.read_u16("key type")?;
+        Ok(Keyblock {
+            key_type,
+            key: CountedOctetString::read(cursor, CosKind::Hex, "key")?,
+        })
     }
 
 }
 
 
-#[derive (Deserialize)]
+#[derive (Deserialize, Serialize)]
 pub struct CsvEntry {
     pub principal: String,
     pub etype: String,
@@ -350,5 +671,269 @@ pub struct CsvEntry {
     pub timestamp: Option<u32>,
     pub version: Option<u32>,
 }
-       
 
+impl From<&KeytabEntry> for CsvEntry {
+    // Types missing from the reference tables are written as their number,
+    // which the lookups in from_csv also accept.
+    fn from(entry: &KeytabEntry) -> CsvEntry {
+        let etype = match reference::ENCRYPTION_TYPES.lookup(entry.key.key_type) {
+            Some(item) => item.name.to_string(),
+            None => entry.key.key_type.to_string(),
+        };
+        let kind = match reference::PRINCIPAL_TYPES.lookup(entry.name.name_type) {
+            Some(item) => item.name.to_string(),
+            None => entry.name.name_type.to_string(),
+        };
+        CsvEntry {
+            principal: entry.principal(),
+            etype,
+            key: entry.key.key.decode(),
+            kind: Some(kind),
+            timestamp: Some(entry.timestamp),
+            version: Some(entry.kvno()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(data: &str) -> Vec<u8> {
+        hex::decode(data).unwrap()
+    }
+
+    // Every file in fuzz/corpus/keytab, with the offset and field its error
+    // must report, or None for those that parse.
+    const CORPUS: [(&str, Option<(usize, &str)>); 19] = [
+        ("component-count-past-entry", Some((51, "component"))),
+        ("empty", Some((0, "file format version"))),
+        ("entry-size-int-min", Some((2, "entry size"))),
+        ("entry-size-past-eof", Some((6, "entry"))),
+        ("entry-size-smaller-than-fields", Some((8, "realm"))),
+        ("entry-truncated-in-key", Some((57, "key"))),
+        ("entry-without-vno", None),
+        ("hole-past-eof", Some((6, "hole"))),
+        ("key-length-past-entry", Some((57, "key"))),
+        ("realm-length-past-entry", Some((10, "realm"))),
+        ("truncated-entry-size", Some((2, "entry size"))),
+        ("truncated-header", Some((0, "file format version"))),
+        ("unknown-version", Some((0, "file format version"))),
+        ("v1-zero-component-count", Some((8, "component count"))),
+        ("valid-single-entry", None),
+        ("valid-two-entries", None),
+        ("valid-v1-entry", None),
+        ("valid-with-holes", None),
+        ("zero-entry-size", Some((2, "entry size"))),
+    ];
+
+    fn corpus_dir() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/keytab")
+    }
+
+    #[test]
+    fn corpus_is_listed() {
+        let mut names: Vec<String> = fs::read_dir(corpus_dir()).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        let listed: Vec<&str> = CORPUS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, listed);
+    }
+
+    #[test]
+    fn corpus_regressions() {
+        for (name, expected) in CORPUS {
+            let bytes = fs::read(corpus_dir().join(name)).unwrap();
+            match (Keytab::from_bytes(&bytes), expected) {
+                (Ok(keytab), None) => {
+                    // whatever parses must survive being written back out
+                    Keytab::from_bytes(&keytab.to_bytes()).unwrap();
+                },
+                (Err(Error::MalformedKeytab { offset, field, .. }), Some(expected)) =>
+                    assert_eq!((offset, field), expected, "{}", name),
+                (result, _) => panic!("{}: unexpected result {:?}", name, result.map(|_| ())),
+            }
+            let _ = KeytabEntry::from_bytes(&bytes, KeytabVersion::V1);
+            let _ = KeytabEntry::from_bytes(&bytes, KeytabVersion::V2);
+        }
+    }
+
+    #[test]
+    fn valid_files_round_trip() {
+        for name in ["entry-without-vno", "valid-single-entry", "valid-two-entries",
+                     "valid-v1-entry", "valid-with-holes"] {
+            let bytes = fs::read(corpus_dir().join(name)).unwrap();
+            assert_eq!(Keytab::from_bytes(&bytes).unwrap().to_bytes(), bytes, "{}", name);
+        }
+    }
+
+    fn entry(principal: &str, kvno: u32, etype: &str) -> KeytabEntry {
+        KeytabEntry::new(principal, "krb5_nt_srv_inst", &0x5f5e1000, &kvno, etype,
+            "000102030405060708090a0b0c0d0e0f").unwrap()
+    }
+
+    fn with_vno(vno8: u8, vno: Option<u32>) -> KeytabEntry {
+        let mut entry = entry("HTTP/web.example.com@EXAMPLE.COM", 0, "rc4-hmac");
+        entry.vno8 = vno8;
+        entry.vno = vno;
+        entry
+    }
+
+    #[test]
+    fn kvno_rules() {
+        assert_eq!(with_vno(5, None).kvno(), 5);
+        assert_eq!(with_vno(4, Some(260)).kvno(), 260);
+        assert_eq!(with_vno(7, Some(0)).kvno(), 7);
+
+        let mut entry = with_vno(7, None);
+        entry.set_kvno(300);
+        assert_eq!((entry.vno8, entry.vno, entry.kvno()), (44, Some(300), 300));
+        let bytes = entry.to_bytes(KeytabVersion::V2);
+        assert_eq!(bytes[bytes.len() - 4..], 300u32.to_be_bytes());
+        let read = KeytabEntry::from_bytes(&bytes, KeytabVersion::V2).unwrap();
+        assert_eq!((read.vno8, read.vno, read.kvno()), (44, Some(300), 300));
+        // the trailer goes out even when vno8 could hold the kvno
+        entry.set_kvno(3);
+        assert_eq!((entry.vno8, entry.vno), (3, Some(3)));
+
+        let read = KeytabEntry::from_bytes(&with_vno(9, None).to_bytes(KeytabVersion::V2), KeytabVersion::V2).unwrap();
+        assert_eq!((read.vno, read.kvno()), (None, 9));
+    }
+
+    fn sample() -> Keytab {
+        let mut keytab = Keytab::new(vec![
+            entry("HTTP/web.example.com@EXAMPLE.COM", 3, "rc4-hmac"),
+            entry("HTTP/web.example.com@EXAMPLE.COM", 3, "aes128-cts-hmac-sha1-96"),
+            entry("HTTP/web.example.com@EXAMPLE.COM", 2, "rc4-hmac"),
+            entry("host/web.example.com@EXAMPLE.COM", 3, "rc4-hmac"),
+        ]);
+        keytab.records.insert(1, KeytabRecord::Hole(KeytabHole { data: vec![0; 12] }));
+        keytab
+    }
+
+    fn slots(keytab: &Keytab) -> Vec<(String, u32, u16)> {
+        keytab.entries().map(|e| (e.principal(), e.kvno(), e.key.key_type)).collect()
+    }
+
+    fn entry_bytes(keytab: &Keytab) -> Vec<Vec<u8>> {
+        keytab.entries().map(|e| e.to_bytes(keytab.file_format_version)).collect()
+    }
+
+    #[test]
+    fn merge_skips_duplicates() {
+        let mut keytab = sample();
+        let before = entry_bytes(&keytab);
+        let mut duplicate = entry("HTTP/web.example.com@EXAMPLE.COM", 3, "rc4-hmac");
+        duplicate.key.key.data.reverse();
+        let added = keytab.merge(Keytab::new(vec![
+            duplicate,
+            entry("HTTP/web.example.com@EXAMPLE.COM", 4, "rc4-hmac"),
+            entry("HTTP/web.example.com@EXAMPLE.COM", 3, "aes256-cts-hmac-sha1-96"),
+        ]));
+        assert_eq!(added, 2);
+        let after = entry_bytes(&keytab);
+        // the entry already present wins and nothing before it moves
+        assert_eq!(after[..4], before[..]);
+        assert_eq!(slots(&keytab)[4..], [
+            ("HTTP/web.example.com@EXAMPLE.COM".to_string(), 4, 23),
+            ("HTTP/web.example.com@EXAMPLE.COM".to_string(), 3, 18),
+        ]);
+        assert_eq!(keytab.holes().count(), 1);
+    }
+
+    #[test]
+    fn remove_by_selector() {
+        for (principal, kvno, etype, left) in [
+            (Some("HTTP/web.example.com@EXAMPLE.COM"), None, None, vec![3]),
+            (None, Some(3), None, vec![2]),
+            (None, None, Some("rc4-hmac"), vec![1]),
+            (Some("HTTP/web.example.com@EXAMPLE.COM"), Some(3), Some("rc4-hmac"), vec![1, 2, 3]),
+            (Some("nobody@EXAMPLE.COM"), None, None, vec![0, 1, 2, 3]),
+        ] {
+            let mut keytab = sample();
+            let before = entry_bytes(&keytab);
+            let filter = EntryFilter::new(principal, kvno, etype).unwrap();
+            assert_eq!(keytab.remove(&filter), 4 - left.len());
+            let expected: Vec<Vec<u8>> = left.iter().map(|i| before[*i].clone()).collect();
+            assert_eq!(entry_bytes(&keytab), expected);
+            // holes are not entries and are left alone
+            assert_eq!(keytab.holes().count(), 1);
+        }
+        assert!(matches!(EntryFilter::new(None, None, Some("rc5")), Err(Error::UnknownEtype(_))));
+    }
+
+    #[test]
+    fn rename_keeps_other_fields() {
+        let mut keytab = sample();
+        let before: Vec<KeytabEntry> = sample().records.into_iter().filter_map(|r| match r {
+            KeytabRecord::Entry(e) => Some(e),
+            KeytabRecord::Hole(_) => None,
+        }).collect();
+        assert_eq!(keytab.rename("HTTP/web.example.com@EXAMPLE.COM", "HTTP/www@EXAMPLE.ORG").unwrap(), 3);
+        for (old, new) in before.iter().zip(keytab.entries()).take(3) {
+            assert_eq!(new.principal(), "HTTP/www@EXAMPLE.ORG");
+            assert_eq!((new.name.name_type, new.timestamp, new.vno8, new.vno, new.key.key_type),
+                (old.name.name_type, old.timestamp, old.vno8, old.vno, old.key.key_type));
+            assert_eq!(new.key.key.data, old.key.key.data);
+        }
+        assert_eq!(entry_bytes(&keytab)[3], before[3].to_bytes(KeytabVersion::V2));
+        assert_eq!(keytab.rename("nobody@EXAMPLE.COM", "somebody@EXAMPLE.COM").unwrap(), 0);
+    }
+
+    #[test]
+    fn set_kvno_writes_the_trailer() {
+        let mut keytab = sample();
+        let before = entry_bytes(&keytab);
+        let filter = EntryFilter::new(Some("HTTP/web.example.com@EXAMPLE.COM"), Some(3), None).unwrap();
+        assert_eq!(keytab.set_kvno(&filter, 256), 2);
+        let entries: Vec<&KeytabEntry> = keytab.entries().collect();
+        for e in &entries[..2] {
+            assert_eq!((e.vno8, e.vno, e.kvno()), (0, Some(256), 256));
+        }
+        let after = entry_bytes(&keytab);
+        assert_eq!(after[0][after[0].len() - 4..], 256u32.to_be_bytes());
+        assert_eq!(after[2..], before[2..]);
+        let read = Keytab::from_bytes(&keytab.to_bytes()).unwrap();
+        assert_eq!(slots(&read)[..2], [
+            ("HTTP/web.example.com@EXAMPLE.COM".to_string(), 256, 23),
+            ("HTTP/web.example.com@EXAMPLE.COM".to_string(), 256, 17),
+        ]);
+    }
+
+    #[test]
+    fn v1_layout() {
+        let mut keytab = Keytab::new(vec![entry("HTTP/web.example.com@EXAMPLE.COM", 3, "rc4-hmac")]);
+        keytab.file_format_version = KeytabVersion::V1;
+        let bytes = keytab.to_bytes();
+
+        // the version is written as two bytes, everything after it in the
+        // host's order, with the realm counted as a component and no name type
+        let mut expected = vec![0x05, 0x01];
+        expected.extend(67i32.to_ne_bytes());
+        expected.extend(3u16.to_ne_bytes());
+        for s in ["EXAMPLE.COM", "HTTP", "web.example.com"] {
+            expected.extend((s.len() as u16).to_ne_bytes());
+            expected.extend(s.as_bytes());
+        }
+        expected.extend(0x5f5e1000u32.to_ne_bytes());
+        expected.push(3);
+        expected.extend(23u16.to_ne_bytes());
+        expected.extend(16u16.to_ne_bytes());
+        expected.extend(h("000102030405060708090a0b0c0d0e0f"));
+        expected.extend(3u32.to_ne_bytes());
+        assert_eq!(bytes, expected);
+
+        let read = Keytab::from_bytes(&bytes).unwrap();
+        assert_eq!(read.file_format_version, KeytabVersion::V1);
+        let entries: Vec<&KeytabEntry> = read.entries().collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].principal(), "HTTP/web.example.com@EXAMPLE.COM");
+        assert_eq!(entries[0].name.components.len(), 2);
+        // 0x0501 has nowhere to keep the name type
+        assert_eq!(entries[0].name.name_type, 0);
+        assert_eq!((entries[0].kvno(), entries[0].key.key_type), (3, 23));
+        assert_eq!(read.to_bytes(), bytes);
+    }
+}
@@ -0,0 +1,190 @@
+use std::io;
+use crate::kerberos::reference;
+use crate::kerberos::keytab::{CsvEntry, Keytab, KeytabEntry};
+use crate::error::Result;
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+
+
+// Renderings of a keytab for scripts and for people, alongside the
+// fmt::Display dump.
+
+// Headerless rows in the layout Keytab::from_csv reads back.
+pub fn csv<W: io::Write>(keytab: &Keytab, writer: W) -> Result<()> {
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+    for entry in keytab.entries() {
+        wtr.serialize(CsvEntry::from(entry))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+pub fn json(keytab: &Keytab) -> String {
+    let entries: Vec<Value> = keytab.entries().map(json_entry).collect();
+    let value = json!({
+        "file_format_version": keytab.file_format_version.to_u16(),
+        "entries": entries,
+    });
+    // serialising a Value cannot fail
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
+fn json_entry(entry: &KeytabEntry) -> Value {
+    json!({
+        "principal": entry.principal(),
+        "realm": entry.name.realm.decode(),
+        "components": entry.name.components.iter().map(|c| c.decode()).collect::<Vec<String>>(),
+        "name_type": entry.name.name_type,
+        "name_type_name": reference::PRINCIPAL_TYPES.name(entry.name.name_type),
+        "timestamp": entry.timestamp,
+        "kvno": entry.kvno(),
+        "etype": entry.key.key_type,
+        "etype_name": reference::ENCRYPTION_TYPES.name(entry.key.key_type),
+        "key": entry.key.key.decode(),
+    })
+}
+
+pub fn table(keytab: &Keytab) -> String {
+    let header = ["KVNO", "Timestamp", "Principal", "Name Type", "Etype", "Key"];
+    let rows: Vec<[String; 6]> = keytab.entries().map(|e| [
+        e.kvno().to_string(),
+        format_timestamp(e.timestamp),
+        e.principal(),
+        reference::PRINCIPAL_TYPES.name(e.name.name_type).to_string(),
+        reference::ENCRYPTION_TYPES.name(e.key.key_type).to_string(),
+        e.key.key.decode(),
+    ]).collect();
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut out = String::new();
+    push_row(&mut out, &widths, header.iter());
+    push_row(&mut out, &widths, widths.map(|w| "-".repeat(w)).iter());
+    for row in &rows {
+        push_row(&mut out, &widths, row.iter());
+    }
+    out
+}
+
+pub(crate) fn push_row<T: AsRef<str>>(out: &mut String, widths: &[usize], cells: impl Iterator<Item = T>) {
+    let line: Vec<String> = cells.zip(widths)
+        .map(|(cell, width)| format!("{:width$}", cell.as_ref(), width = width))
+        .collect();
+    out.push_str(line.join("  ").trim_end());
+    out.push('\n');
+}
+
+// Mirrors `klist -k -t -e -K`.
+pub fn klist(keytab: &Keytab, name: &str) -> String {
+    let mut out = format!("Keytab name: FILE:{}\n", name);
+    out.push_str("KVNO Timestamp           Principal\n");
+    out.push_str("---- ------------------- ------------------------------------------------------\n");
+    for e in keytab.entries() {
+        out.push_str(&format!("{:>4} {} {} ({}) (0x{})\n",
+            e.kvno(),
+            format_timestamp(e.timestamp),
+            e.principal(),
+            reference::ENCRYPTION_TYPES.name(e.key.key_type),
+            e.key.key.decode(),
+        ));
+    }
+    out
+}
+
+pub(crate) fn format_timestamp(timestamp: u32) -> String {
+    Utc.timestamp(timestamp as i64, 0).format("%m/%d/%Y %H:%M:%S").to_string()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Keytab {
+        Keytab::new(vec![
+            KeytabEntry::new("HTTP/web.example.com@EXAMPLE.COM", "krb5_nt_srv_inst", &1700000000, &3,
+                "aes128-cts-hmac-sha1-96", "000102030405060708090a0b0c0d0e0f").unwrap(),
+            KeytabEntry::new("user@EXAMPLE.COM", "krb5_nt_principal", &0, &300,
+                "rc4-hmac", "8846f7eaee8fb117ad06bdd830b7586c").unwrap(),
+            // types the reference tables do not know go by number
+            KeytabEntry::new("odd@EXAMPLE.COM", "99", &1, &1, "99", "00").unwrap(),
+        ])
+    }
+
+    #[test]
+    fn csv_rows() {
+        let mut out = Vec::new();
+        csv(&sample(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\
+HTTP/web.example.com@EXAMPLE.COM,aes128-cts-hmac-sha1-96,000102030405060708090a0b0c0d0e0f,krb5_nt_srv_inst,1700000000,3
+user@EXAMPLE.COM,rc4-hmac,8846f7eaee8fb117ad06bdd830b7586c,krb5_nt_principal,0,300
+odd@EXAMPLE.COM,99,00,99,1,1
+");
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let keytab = sample();
+        let mut out = Vec::new();
+        csv(&keytab, &mut out).unwrap();
+        let read = Keytab::from_csv_reader(out.as_slice()).unwrap();
+        for (a, b) in keytab.entries().zip(read.entries()) {
+            assert_eq!(a.principal(), b.principal());
+            assert_eq!(a.name.components.len(), b.name.components.len());
+            assert_eq!((a.key.key_type, a.name.name_type, a.timestamp, a.kvno()),
+                (b.key.key_type, b.name.name_type, b.timestamp, b.kvno()));
+            assert_eq!(a.key.key.data, b.key.key.data);
+        }
+        assert_eq!(read.entries().count(), 3);
+        // entries made by create carry the 32 bit kvno, so nothing is lost
+        assert_eq!(read.to_bytes(), keytab.to_bytes());
+    }
+
+    #[test]
+    fn json_fields() {
+        let value: Value = serde_json::from_str(&json(&sample())).unwrap();
+        assert_eq!(value["file_format_version"], 0x0502);
+        assert_eq!(value["entries"][0], json!({
+            "principal": "HTTP/web.example.com@EXAMPLE.COM",
+            "realm": "EXAMPLE.COM",
+            "components": ["HTTP", "web.example.com"],
+            "name_type": 2,
+            "name_type_name": "krb5_nt_srv_inst",
+            "timestamp": 1700000000,
+            "kvno": 3,
+            "etype": 17,
+            "etype_name": "aes128-cts-hmac-sha1-96",
+            "key": "000102030405060708090a0b0c0d0e0f",
+        }));
+        assert_eq!(value["entries"][1]["kvno"], 300);
+        assert_eq!(value["entries"][2]["etype_name"], "unknown");
+    }
+
+    #[test]
+    fn table_rows() {
+        assert_eq!(table(&sample()), "\
+KVNO  Timestamp            Principal                         Name Type          Etype                    Key
+----  -------------------  --------------------------------  -----------------  -----------------------  --------------------------------
+3     11/14/2023 22:13:20  HTTP/web.example.com@EXAMPLE.COM  krb5_nt_srv_inst   aes128-cts-hmac-sha1-96  000102030405060708090a0b0c0d0e0f
+300   01/01/1970 00:00:00  user@EXAMPLE.COM                  krb5_nt_principal  rc4-hmac                 8846f7eaee8fb117ad06bdd830b7586c
+1     01/01/1970 00:00:01  odd@EXAMPLE.COM                   unknown            unknown                  00
+");
+    }
+
+    #[test]
+    fn klist_rows() {
+        assert_eq!(klist(&sample(), "/etc/krb5.keytab"), "\
+Keytab name: FILE:/etc/krb5.keytab
+KVNO Timestamp           Principal
+---- ------------------- ------------------------------------------------------
+   3 11/14/2023 22:13:20 HTTP/web.example.com@EXAMPLE.COM (aes128-cts-hmac-sha1-96) (0x000102030405060708090a0b0c0d0e0f)
+ 300 01/01/1970 00:00:00 user@EXAMPLE.COM (rc4-hmac) (0x8846f7eaee8fb117ad06bdd830b7586c)
+   1 01/01/1970 00:00:01 odd@EXAMPLE.COM (unknown) (0x00)
+");
+    }
+}
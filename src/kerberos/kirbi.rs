@@ -0,0 +1,247 @@
+use std::fs;
+use std::path::Path;
+use crate::kerberos::asn1::{self, Asn1, EncKrbCredPart, EncryptedData, EncryptionKey, HostAddress,
+    KerberosFlags, KrbCred, KrbCredInfo, PrincipalName, Ticket};
+use crate::kerberos::ccache::{Address, Ccache, Credential};
+use crate::kerberos::keytab::{CosKind, CountedOctetString, Keyblock, Principal};
+use crate::error::{Error, Result};
+
+
+// KRB-CRED files as Mimikatz and Rubeus write them (.kirbi). Their
+// EncKrbCredPart is "encrypted" with etype 0, meaning it is sent in the
+// clear, which is the only form these functions read or write.
+
+// Accepts raw DER or the base64 that Rubeus prints, whitespace and all.
+pub fn from_bytes(bytes: &[u8]) -> Result<KrbCred> {
+    if bytes.first() == Some(&asn1::der::application(22)) {
+        return KrbCred::from_der(bytes);
+    }
+    let text: Vec<u8> = bytes.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    let der = base64::decode(&text)
+        .map_err(|e| Error::BadBase64(e.to_string()))?;
+    KrbCred::from_der(&der)
+}
+
+pub fn from_file(path: &dyn AsRef<Path>) -> Result<KrbCred> {
+    let bytes = fs::read(path)?;
+    from_bytes(&bytes)
+}
+
+pub fn to_bytes(cred: &KrbCred, base64: bool) -> Vec<u8> {
+    let der = cred.to_der();
+    if base64 {
+        base64::encode(der).into_bytes()
+    } else {
+        der
+    }
+}
+
+pub fn to_file(cred: &KrbCred, base64: bool, path: &dyn AsRef<Path>) -> Result<()> {
+    fs::write(path, to_bytes(cred, base64))?;
+    Ok(())
+}
+
+// Pairs each ticket with its KrbCredInfo, which carries the session key and
+// everything else a ccache keeps alongside the ticket.
+pub fn credentials(cred: &KrbCred) -> Result<Vec<Credential>> {
+    if cred.enc_part.etype != 0 {
+        return Err(Error::EncryptedKrbCred(cred.enc_part.etype));
+    }
+    let part = EncKrbCredPart::from_der(&cred.enc_part.cipher)?;
+    if part.ticket_info.len() != cred.tickets.len() {
+        return Err(Error::IncompleteCredential("ticket info for every ticket"));
+    }
+    cred.tickets.iter().zip(&part.ticket_info)
+        .map(|(ticket, info)| credential(ticket, info))
+        .collect()
+}
+
+pub fn to_ccache(cred: &KrbCred) -> Result<Ccache> {
+    let credentials = credentials(cred)?;
+    let client = credentials.first()
+        .ok_or(Error::IncompleteCredential("tickets"))?
+        .client.clone();
+    let mut ccache = Ccache::new(client);
+    ccache.credentials = credentials;
+    Ok(ccache)
+}
+
+// Config entries are not tickets and are left behind, and a cache with
+// nothing else is an error. KrbCredInfo has no room for a credential's
+// authdata, is_skey flag or second ticket, so those are lost as well:
+// to_ccache gives them back empty.
+pub fn from_ccache(ccache: &Ccache) -> Result<KrbCred> {
+    let (tickets, ticket_info): (Vec<Ticket>, Vec<KrbCredInfo>) = ccache.credentials.iter()
+        .filter(|c| !c.is_config())
+        .map(|c| Ok((Ticket::from_der(&c.ticket)?, cred_info(c))))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    if tickets.is_empty() {
+        return Err(Error::IncompleteCredential("tickets"));
+    }
+    let part = EncKrbCredPart {
+        ticket_info,
+        nonce: None,
+        timestamp: None,
+        usec: None,
+        s_address: None,
+        r_address: None,
+    };
+    Ok(KrbCred {
+        pvno: asn1::PVNO,
+        msg_type: 22,
+        tickets,
+        enc_part: EncryptedData {
+            etype: 0,
+            kvno: None,
+            cipher: part.to_der(),
+        },
+    })
+}
+
+fn credential(ticket: &Ticket, info: &KrbCredInfo) -> Result<Credential> {
+    let (pname, prealm) = match (&info.pname, &info.prealm) {
+        (Some(name), Some(realm)) => (name, realm),
+        _ => return Err(Error::IncompleteCredential("client principal")),
+    };
+    // the server is optional as it is also in the ticket
+    let server = match (&info.sname, &info.srealm) {
+        (Some(name), Some(realm)) => principal(name, realm)?,
+        _ => principal(&ticket.sname, &ticket.realm)?,
+    };
+    Ok(Credential {
+        client: principal(pname, prealm)?,
+        server,
+        key: keyblock(&info.key)?,
+        authtime: ccache_time(info.authtime)?,
+        starttime: ccache_time(info.starttime)?,
+        endtime: ccache_time(info.endtime)?,
+        renew_till: ccache_time(info.renew_till)?,
+        // not in KrbCredInfo, along with authdata and the second ticket
+        is_skey: false,
+        ticket_flags: info.flags.as_ref().map_or(0, |f| f.value),
+        addresses: info.caddr.iter().flatten().map(|a| Address {
+            addr_type: a.addr_type as u16,
+            data: a.address.clone(),
+        }).collect(),
+        authdata: Vec::new(),
+        ticket: ticket.to_der(),
+        second_ticket: Vec::new(),
+    })
+}
+
+fn cred_info(c: &Credential) -> KrbCredInfo {
+    let (pname, prealm) = principal_name(&c.client);
+    let (sname, srealm) = principal_name(&c.server);
+    let nonzero = |t: u32| if t == 0 { None } else { Some(t.into()) };
+    let caddr: Vec<HostAddress> = c.addresses.iter().map(|a| HostAddress {
+        addr_type: a.addr_type.into(),
+        address: a.data.clone(),
+    }).collect();
+    KrbCredInfo {
+        key: EncryptionKey {
+            // MIT stores enctypes as signed 16 bit numbers
+            keytype: (c.key.key_type as i16).into(),
+            keyvalue: c.key.key.data.clone(),
+        },
+        prealm: Some(prealm),
+        pname: Some(pname),
+        flags: Some(KerberosFlags::new(c.ticket_flags)),
+        authtime: Some(c.authtime.into()),
+        starttime: nonzero(c.starttime),
+        endtime: Some(c.endtime.into()),
+        renew_till: nonzero(c.renew_till),
+        srealm: Some(srealm),
+        sname: Some(sname),
+        caddr: if caddr.is_empty() { None } else { Some(caddr) },
+    }
+}
+
+// Ccaches hold unsigned 32-bit times, with 0 for unset
+fn ccache_time(time: Option<i64>) -> Result<u32> {
+    time.map_or(Ok(0), |t| u32::try_from(t).map_err(|_| Error::TimeOutOfRange(t)))
+}
+
+fn principal(name: &PrincipalName, realm: &str) -> Result<Principal> {
+    Ok(Principal {
+        name_type: name.name_type as u32,
+        realm: CountedOctetString::from_string(realm)?,
+        components: name.name_string.iter()
+            .map(|c| CountedOctetString::from_string(c))
+            .collect::<Result<Vec<_>>>()?,
+    })
+}
+
+fn principal_name(principal: &Principal) -> (PrincipalName, String) {
+    let name = PrincipalName {
+        name_type: principal.name_type as i32,
+        name_string: principal.components.iter().map(|c| c.decode()).collect(),
+    };
+    (name, principal.realm.decode())
+}
+
+fn keyblock(key: &EncryptionKey) -> Result<Keyblock> {
+    Ok(Keyblock {
+        key_type: key.keytype as u16,
+        key: CountedOctetString::from_uncounted_bytes(key.keyvalue.clone(), CosKind::Hex)?,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::testing::{self, fixture};
+
+    // From fixtures/gen/kirbi.py: a TGT with full ticket info and a service
+    // ticket whose info has only the key and client.
+    #[test]
+    fn fixtures_round_trip() {
+        let cred = testing::round_trip("kirbi/user.kirbi", from_bytes, |c| to_bytes(c, false));
+        let text = testing::round_trip("kirbi/user.kirbi.b64", from_bytes, |c| to_bytes(c, true));
+        assert!(text == cred);
+    }
+
+    #[test]
+    fn fixture_to_ccache() {
+        let ccache = to_ccache(&from_bytes(&fixture("kirbi/user.kirbi")).unwrap()).unwrap();
+        assert_eq!(ccache.default_principal.to_string(), "user@EXAMPLE.COM");
+        let servers: Vec<String> = ccache.credentials.iter().map(|c| c.server.to_string()).collect();
+        assert_eq!(servers, ["krbtgt/EXAMPLE.COM@EXAMPLE.COM", "cifs/fs01.example.com@EXAMPLE.COM"]);
+        assert_eq!(ccache.credentials[0].ticket_flags, 0x40e10000);
+        // what the sparse info left out comes back as zeros, after which the
+        // conversion is stable
+        let cred = from_ccache(&ccache).unwrap();
+        assert_eq!(to_ccache(&cred).unwrap().to_bytes(), ccache.to_bytes());
+    }
+
+    #[test]
+    fn ccache_round_trip_drops_what_krb_cred_info_cannot_hold() {
+        let ccache = Ccache::from_bytes(&fixture("ccache/v4.ccache")).unwrap();
+        let converted = to_ccache(&from_ccache(&ccache).unwrap()).unwrap();
+        // the config entry is left behind
+        assert_eq!(converted.credentials.len(), ccache.credentials.len() - 1);
+        let (before, after) = (&ccache.credentials[2], &converted.credentials[1]);
+        assert_eq!(after.server.to_string(), before.server.to_string());
+        assert_eq!(after.key.key.data, before.key.key.data);
+        assert_eq!((after.endtime, after.ticket_flags), (before.endtime, before.ticket_flags));
+        assert_eq!(after.addresses[0].data, before.addresses[0].data);
+        assert_eq!(after.ticket, before.ticket);
+        assert!(before.is_skey && !before.authdata.is_empty() && !before.second_ticket.is_empty());
+        assert!(!after.is_skey && after.authdata.is_empty() && after.second_ticket.is_empty());
+    }
+
+    #[test]
+    fn config_only_ccache_is_rejected() {
+        let mut ccache = Ccache::from_bytes(&fixture("ccache/v4.ccache")).unwrap();
+        ccache.credentials.retain(|c| c.is_config());
+        assert!(!ccache.credentials.is_empty());
+        match from_ccache(&ccache) {
+            Err(Error::IncompleteCredential("tickets")) => (),
+            Err(e) => panic!("{}", e),
+            Ok(cred) => panic!("{} tickets from config entries", cred.tickets.len()),
+        }
+    }
+}
@@ -7,18 +7,34 @@ pub struct RefTable {
 pub struct RefItem {
     pub name: &'static str,
     pub shortname: Option<&'static str>,
-    pub id: u8,
+    pub id: i32,
 }
 
 impl RefTable {
-    pub fn lookup<T: ToString>(&self, query: T) -> &RefItem {
+    pub fn lookup<T: ToString>(&self, query: T) -> Option<&RefItem> {
         let query = query.to_string();
-        match &self.entries
+        self.entries
             .iter()
-            .find(|&x|x.shortname.unwrap() == query || x.name == query || x.id.to_string() == query) {
-                Some(nmatch) => nmatch, 
-                _ => panic!("{} not found", query)
-            }
+            .find(|&x|x.shortname == Some(&query) || x.name == query || x.id.to_string() == query)
+    }
+
+    pub fn name<T: ToString>(&self, query: T) -> &'static str {
+        match self.lookup(query) {
+            Some(item) => item.name,
+            None => "unknown",
+        }
+    }
+
+    // Names the set bits of KerberosFlags, for tables numbered by bit with
+    // bit 0 the most significant.
+    pub fn flag_names(&self, flags: u32) -> Vec<&'static str> {
+        (0..32)
+            .filter(|bit| flags & (0x8000_0000 >> bit) != 0)
+            .map(|bit| match self.lookup(bit) {
+                Some(item) => item.shortname.unwrap_or(item.name),
+                None => "unknown",
+            })
+            .collect()
     }
 }
 
@@ -89,7 +105,7 @@ lazy_static! {
             },
             RefItem{
                 name: "des3-cbc-sha1-kd",
-                shortname: None,
+                shortname: Some("des3"),
                 id: 16,
             },
             RefItem{
@@ -107,11 +123,31 @@ lazy_static! {
                 shortname: Some("aes256"),
                 id: 18,
             },
+            RefItem{
+                name: "aes128-cts-hmac-sha256-128",
+                shortname: Some("aes128-sha2"),
+                id: 19,
+            },
+            RefItem{
+                name: "aes256-cts-hmac-sha384-192",
+                shortname: Some("aes256-sha2"),
+                id: 20,
+            },
             RefItem{
                 name: "rc4-hmac-exp",
                 shortname: None,
                 id: 24,
             },
+            RefItem{
+                name: "camellia128-cts-cmac",
+                shortname: Some("camellia128"),
+                id: 25,
+            },
+            RefItem{
+                name: "camellia256-cts-cmac",
+                shortname: Some("camellia256"),
+                id: 26,
+            },
             RefItem{
                 name: "subkey-keymaterial",
                 shortname: None,
@@ -119,15 +155,1072 @@ lazy_static! {
             },
         ],
     };
+    // https://www.rfc-editor.org/rfc/rfc4120.html#section-6.2
     pub static ref PRINCIPAL_TYPES: RefTable = RefTable {
         entries: vec![
+            RefItem{
+                name: "krb5_nt_unknown",
+                shortname: Some("unknown"),
+                id: 0,
+            },
             RefItem{
                 name: "krb5_nt_principal",
                 shortname: Some("principal"),
                 id: 1,
             },
+            RefItem{
+                name: "krb5_nt_srv_inst",
+                shortname: Some("srv_inst"),
+                id: 2,
+            },
+            RefItem{
+                name: "krb5_nt_srv_hst",
+                shortname: Some("srv_hst"),
+                id: 3,
+            },
+            RefItem{
+                name: "krb5_nt_srv_xhst",
+                shortname: Some("srv_xhst"),
+                id: 4,
+            },
+            RefItem{
+                name: "krb5_nt_uid",
+                shortname: Some("uid"),
+                id: 5,
+            },
+            RefItem{
+                name: "krb5_nt_x500_principal",
+                shortname: Some("x500_principal"),
+                id: 6,
+            },
+            RefItem{
+                name: "krb5_nt_smtp_name",
+                shortname: Some("smtp_name"),
+                id: 7,
+            },
+            RefItem{
+                name: "krb5_nt_enterprise_principal",
+                shortname: Some("enterprise"),
+                id: 10,
+            },
+            RefItem{
+                name: "krb5_nt_wellknown",
+                shortname: Some("wellknown"),
+                id: 11,
+            },
+        ],
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc4120.html#section-7.5.1, with the
+    // GSS-API usages from RFC 4121 and the FAST ones from RFC 6113
+    pub static ref KEY_USAGES: RefTable = RefTable {
+        entries: vec![
+            RefItem{
+                name: "krb5_keyusage_as_req_pa_enc_ts",
+                shortname: Some("pa-enc-timestamp"),
+                id: 1,
+            },
+            RefItem{
+                name: "krb5_keyusage_kdc_rep_ticket",
+                shortname: Some("ticket"),
+                id: 2,
+            },
+            RefItem{
+                name: "krb5_keyusage_as_rep_encpart",
+                shortname: Some("as-rep"),
+                id: 3,
+            },
+            RefItem{
+                name: "krb5_keyusage_tgs_req_ad_sesskey",
+                shortname: Some("tgs-req-ad-sesskey"),
+                id: 4,
+            },
+            RefItem{
+                name: "krb5_keyusage_tgs_req_ad_subkey",
+                shortname: Some("tgs-req-ad-subkey"),
+                id: 5,
+            },
+            RefItem{
+                name: "krb5_keyusage_tgs_req_auth_cksum",
+                shortname: Some("tgs-req-auth-cksum"),
+                id: 6,
+            },
+            RefItem{
+                name: "krb5_keyusage_tgs_req_auth",
+                shortname: Some("tgs-req-auth"),
+                id: 7,
+            },
+            RefItem{
+                name: "krb5_keyusage_tgs_rep_encpart_sesskey",
+                shortname: Some("tgs-rep"),
+                id: 8,
+            },
+            RefItem{
+                name: "krb5_keyusage_tgs_rep_encpart_subkey",
+                shortname: Some("tgs-rep-subkey"),
+                id: 9,
+            },
+            RefItem{
+                name: "krb5_keyusage_ap_req_auth_cksum",
+                shortname: Some("ap-req-auth-cksum"),
+                id: 10,
+            },
+            RefItem{
+                name: "krb5_keyusage_ap_req_auth",
+                shortname: Some("ap-req-auth"),
+                id: 11,
+            },
+            RefItem{
+                name: "krb5_keyusage_ap_rep_encpart",
+                shortname: Some("ap-rep"),
+                id: 12,
+            },
+            RefItem{
+                name: "krb5_keyusage_krb_priv_encpart",
+                shortname: Some("krb-priv"),
+                id: 13,
+            },
+            RefItem{
+                name: "krb5_keyusage_krb_cred_encpart",
+                shortname: Some("krb-cred"),
+                id: 14,
+            },
+            RefItem{
+                name: "krb5_keyusage_krb_safe_cksum",
+                shortname: Some("krb-safe"),
+                id: 15,
+            },
+            RefItem{
+                name: "krb5_keyusage_ad_kdcissued_cksum",
+                shortname: Some("ad-kdc-issued"),
+                id: 19,
+            },
+            RefItem{
+                name: "kg_usage_acceptor_seal",
+                shortname: Some("gss-acceptor-seal"),
+                id: 22,
+            },
+            RefItem{
+                name: "kg_usage_acceptor_sign",
+                shortname: Some("gss-acceptor-sign"),
+                id: 23,
+            },
+            RefItem{
+                name: "kg_usage_initiator_seal",
+                shortname: Some("gss-initiator-seal"),
+                id: 24,
+            },
+            RefItem{
+                name: "kg_usage_initiator_sign",
+                shortname: Some("gss-initiator-sign"),
+                id: 25,
+            },
+            RefItem{
+                name: "krb5_keyusage_fast_req_chksum",
+                shortname: Some("fast-req-cksum"),
+                id: 50,
+            },
+            RefItem{
+                name: "krb5_keyusage_fast_enc",
+                shortname: Some("fast-enc"),
+                id: 51,
+            },
+            RefItem{
+                name: "krb5_keyusage_fast_rep",
+                shortname: Some("fast-rep"),
+                id: 52,
+            },
+            RefItem{
+                name: "krb5_keyusage_fast_finished",
+                shortname: Some("fast-finished"),
+                id: 53,
+            },
+            RefItem{
+                name: "krb5_keyusage_enc_challenge_client",
+                shortname: Some("enc-challenge-client"),
+                id: 54,
+            },
+            RefItem{
+                name: "krb5_keyusage_enc_challenge_kdc",
+                shortname: Some("enc-challenge-kdc"),
+                id: 55,
+            },
+        ],
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc3961.html#section-8, with the
+    // checksums of RFC 6803, RFC 8009 and the RC4 one from RFC 4757
+    pub static ref CHECKSUM_TYPES: RefTable = RefTable {
+        entries: vec![
+            RefItem{
+                name: "crc32",
+                shortname: Some("crc32"),
+                id: 1,
+            },
+            RefItem{
+                name: "rsa-md4",
+                shortname: Some("md4"),
+                id: 2,
+            },
+            RefItem{
+                name: "rsa-md4-des",
+                shortname: Some("md4-des"),
+                id: 3,
+            },
+            RefItem{
+                name: "des-mac",
+                shortname: None,
+                id: 4,
+            },
+            RefItem{
+                name: "des-mac-k",
+                shortname: None,
+                id: 5,
+            },
+            RefItem{
+                name: "rsa-md4-des-k",
+                shortname: None,
+                id: 6,
+            },
+            RefItem{
+                name: "rsa-md5",
+                shortname: Some("md5"),
+                id: 7,
+            },
+            RefItem{
+                name: "rsa-md5-des",
+                shortname: Some("md5-des"),
+                id: 8,
+            },
+            RefItem{
+                name: "rsa-md5-des3",
+                shortname: None,
+                id: 9,
+            },
+            RefItem{
+                name: "sha1",
+                shortname: None,
+                id: 10,
+            },
+            RefItem{
+                name: "hmac-sha1-des3-kd",
+                shortname: Some("hmac-sha1-des3"),
+                id: 12,
+            },
+            RefItem{
+                name: "hmac-sha1-des3",
+                shortname: None,
+                id: 13,
+            },
+            RefItem{
+                name: "sha1-unkeyed",
+                shortname: None,
+                id: 14,
+            },
+            RefItem{
+                name: "hmac-sha1-96-aes128",
+                shortname: Some("hmac-sha1-aes128"),
+                id: 15,
+            },
+            RefItem{
+                name: "hmac-sha1-96-aes256",
+                shortname: Some("hmac-sha1-aes256"),
+                id: 16,
+            },
+            RefItem{
+                name: "cmac-camellia128",
+                shortname: Some("camellia128"),
+                id: 17,
+            },
+            RefItem{
+                name: "cmac-camellia256",
+                shortname: Some("camellia256"),
+                id: 18,
+            },
+            RefItem{
+                name: "hmac-sha256-128-aes128",
+                shortname: Some("sha256-aes128"),
+                id: 19,
+            },
+            RefItem{
+                name: "hmac-sha384-192-aes256",
+                shortname: Some("sha384-aes256"),
+                id: 20,
+            },
+            RefItem{
+                name: "hmac-md5",
+                shortname: Some("rc4"),
+                id: -138,
+            },
         ],
     };
-}
 
+    // https://www.rfc-editor.org/rfc/rfc4120.html#section-7.5.7
+    pub static ref MESSAGE_TYPES: RefTable = RefTable {
+        entries: vec![
+            RefItem{
+                name: "krb5_as_req",
+                shortname: Some("as-req"),
+                id: 10,
+            },
+            RefItem{
+                name: "krb5_as_rep",
+                shortname: Some("as-rep"),
+                id: 11,
+            },
+            RefItem{
+                name: "krb5_tgs_req",
+                shortname: Some("tgs-req"),
+                id: 12,
+            },
+            RefItem{
+                name: "krb5_tgs_rep",
+                shortname: Some("tgs-rep"),
+                id: 13,
+            },
+            RefItem{
+                name: "krb5_ap_req",
+                shortname: Some("ap-req"),
+                id: 14,
+            },
+            RefItem{
+                name: "krb5_ap_rep",
+                shortname: Some("ap-rep"),
+                id: 15,
+            },
+            RefItem{
+                name: "krb5_safe",
+                shortname: Some("krb-safe"),
+                id: 20,
+            },
+            RefItem{
+                name: "krb5_priv",
+                shortname: Some("krb-priv"),
+                id: 21,
+            },
+            RefItem{
+                name: "krb5_cred",
+                shortname: Some("krb-cred"),
+                id: 22,
+            },
+            RefItem{
+                name: "krb5_error",
+                shortname: Some("krb-error"),
+                id: 30,
+            },
+        ],
+    };
 
+    // https://www.rfc-editor.org/rfc/rfc4120.html#section-7.5.2, with the
+    // registrations that followed in the IANA pre-authentication registry
+    pub static ref PADATA_TYPES: RefTable = RefTable {
+        entries: vec![
+            RefItem{
+                name: "krb5_padata_tgs_req",
+                shortname: Some("pa-tgs-req"),
+                id: 1,
+            },
+            RefItem{
+                name: "krb5_padata_enc_timestamp",
+                shortname: Some("pa-enc-timestamp"),
+                id: 2,
+            },
+            RefItem{
+                name: "krb5_padata_pw_salt",
+                shortname: Some("pa-pw-salt"),
+                id: 3,
+            },
+            RefItem{
+                name: "krb5_padata_etype_info",
+                shortname: Some("pa-etype-info"),
+                id: 11,
+            },
+            RefItem{
+                name: "krb5_padata_pk_as_req_old",
+                shortname: Some("pa-pk-as-req-old"),
+                id: 14,
+            },
+            RefItem{
+                name: "krb5_padata_pk_as_rep_old",
+                shortname: Some("pa-pk-as-rep-old"),
+                id: 15,
+            },
+            RefItem{
+                name: "krb5_padata_pk_as_req",
+                shortname: Some("pa-pk-as-req"),
+                id: 16,
+            },
+            RefItem{
+                name: "krb5_padata_pk_as_rep",
+                shortname: Some("pa-pk-as-rep"),
+                id: 17,
+            },
+            RefItem{
+                name: "krb5_padata_etype_info2",
+                shortname: Some("pa-etype-info2"),
+                id: 19,
+            },
+            RefItem{
+                name: "krb5_padata_svr_referral_info",
+                shortname: Some("pa-svr-referral-info"),
+                id: 20,
+            },
+            RefItem{
+                name: "krb5_padata_sam_challenge_2",
+                shortname: Some("pa-sam-challenge-2"),
+                id: 30,
+            },
+            RefItem{
+                name: "krb5_padata_sam_response_2",
+                shortname: Some("pa-sam-response-2"),
+                id: 31,
+            },
+            RefItem{
+                name: "krb5_padata_pac_request",
+                shortname: Some("pa-pac-request"),
+                id: 128,
+            },
+            RefItem{
+                name: "krb5_padata_for_user",
+                shortname: Some("pa-for-user"),
+                id: 129,
+            },
+            RefItem{
+                name: "krb5_padata_s4u_x509_user",
+                shortname: Some("pa-s4u-x509-user"),
+                id: 130,
+            },
+            RefItem{
+                name: "krb5_padata_as_checksum",
+                shortname: Some("pa-as-checksum"),
+                id: 132,
+            },
+            RefItem{
+                name: "krb5_padata_fx_cookie",
+                shortname: Some("pa-fx-cookie"),
+                id: 133,
+            },
+            RefItem{
+                name: "krb5_padata_fx_fast",
+                shortname: Some("pa-fx-fast"),
+                id: 136,
+            },
+            RefItem{
+                name: "krb5_padata_fx_error",
+                shortname: Some("pa-fx-error"),
+                id: 137,
+            },
+            RefItem{
+                name: "krb5_padata_encrypted_challenge",
+                shortname: Some("pa-encrypted-challenge"),
+                id: 138,
+            },
+            RefItem{
+                name: "krb5_padata_otp_challenge",
+                shortname: Some("pa-otp-challenge"),
+                id: 141,
+            },
+            RefItem{
+                name: "krb5_padata_otp_request",
+                shortname: Some("pa-otp-request"),
+                id: 142,
+            },
+            RefItem{
+                name: "krb5_padata_req_enc_pa_rep",
+                shortname: Some("pa-req-enc-pa-rep"),
+                id: 149,
+            },
+            RefItem{
+                name: "krb5_padata_as_freshness",
+                shortname: Some("pa-as-freshness"),
+                id: 150,
+            },
+            RefItem{
+                name: "krb5_padata_spake",
+                shortname: Some("pa-spake"),
+                id: 151,
+            },
+            RefItem{
+                name: "krb5_padata_supported_enctypes",
+                shortname: Some("pa-supported-enctypes"),
+                id: 165,
+            },
+            RefItem{
+                name: "krb5_padata_pac_options",
+                shortname: Some("pa-pac-options"),
+                id: 167,
+            },
+        ],
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc4120.html#section-7.5.9, with the
+    // PKINIT and FAST codes of RFC 4556 and RFC 6113
+    pub static ref ERROR_CODES: RefTable = RefTable {
+        entries: vec![
+            RefItem{
+                name: "kdc_err_none",
+                shortname: None,
+                id: 0,
+            },
+            RefItem{
+                name: "kdc_err_name_exp",
+                shortname: None,
+                id: 1,
+            },
+            RefItem{
+                name: "kdc_err_service_exp",
+                shortname: None,
+                id: 2,
+            },
+            RefItem{
+                name: "kdc_err_bad_pvno",
+                shortname: None,
+                id: 3,
+            },
+            RefItem{
+                name: "kdc_err_c_old_mast_kvno",
+                shortname: None,
+                id: 4,
+            },
+            RefItem{
+                name: "kdc_err_s_old_mast_kvno",
+                shortname: None,
+                id: 5,
+            },
+            RefItem{
+                name: "kdc_err_c_principal_unknown",
+                shortname: None,
+                id: 6,
+            },
+            RefItem{
+                name: "kdc_err_s_principal_unknown",
+                shortname: None,
+                id: 7,
+            },
+            RefItem{
+                name: "kdc_err_principal_not_unique",
+                shortname: None,
+                id: 8,
+            },
+            RefItem{
+                name: "kdc_err_null_key",
+                shortname: None,
+                id: 9,
+            },
+            RefItem{
+                name: "kdc_err_cannot_postdate",
+                shortname: None,
+                id: 10,
+            },
+            RefItem{
+                name: "kdc_err_never_valid",
+                shortname: None,
+                id: 11,
+            },
+            RefItem{
+                name: "kdc_err_policy",
+                shortname: None,
+                id: 12,
+            },
+            RefItem{
+                name: "kdc_err_badoption",
+                shortname: None,
+                id: 13,
+            },
+            RefItem{
+                name: "kdc_err_etype_nosupp",
+                shortname: None,
+                id: 14,
+            },
+            RefItem{
+                name: "kdc_err_sumtype_nosupp",
+                shortname: None,
+                id: 15,
+            },
+            RefItem{
+                name: "kdc_err_padata_type_nosupp",
+                shortname: None,
+                id: 16,
+            },
+            RefItem{
+                name: "kdc_err_trtype_nosupp",
+                shortname: None,
+                id: 17,
+            },
+            RefItem{
+                name: "kdc_err_client_revoked",
+                shortname: None,
+                id: 18,
+            },
+            RefItem{
+                name: "kdc_err_service_revoked",
+                shortname: None,
+                id: 19,
+            },
+            RefItem{
+                name: "kdc_err_tgt_revoked",
+                shortname: None,
+                id: 20,
+            },
+            RefItem{
+                name: "kdc_err_client_notyet",
+                shortname: None,
+                id: 21,
+            },
+            RefItem{
+                name: "kdc_err_service_notyet",
+                shortname: None,
+                id: 22,
+            },
+            RefItem{
+                name: "kdc_err_key_expired",
+                shortname: None,
+                id: 23,
+            },
+            RefItem{
+                name: "kdc_err_preauth_failed",
+                shortname: None,
+                id: 24,
+            },
+            RefItem{
+                name: "kdc_err_preauth_required",
+                shortname: None,
+                id: 25,
+            },
+            RefItem{
+                name: "kdc_err_server_nomatch",
+                shortname: None,
+                id: 26,
+            },
+            RefItem{
+                name: "kdc_err_must_use_user2user",
+                shortname: None,
+                id: 27,
+            },
+            RefItem{
+                name: "kdc_err_path_not_accepted",
+                shortname: None,
+                id: 28,
+            },
+            RefItem{
+                name: "kdc_err_svc_unavailable",
+                shortname: None,
+                id: 29,
+            },
+            RefItem{
+                name: "krb_ap_err_bad_integrity",
+                shortname: None,
+                id: 31,
+            },
+            RefItem{
+                name: "krb_ap_err_tkt_expired",
+                shortname: None,
+                id: 32,
+            },
+            RefItem{
+                name: "krb_ap_err_tkt_nyv",
+                shortname: None,
+                id: 33,
+            },
+            RefItem{
+                name: "krb_ap_err_repeat",
+                shortname: None,
+                id: 34,
+            },
+            RefItem{
+                name: "krb_ap_err_not_us",
+                shortname: None,
+                id: 35,
+            },
+            RefItem{
+                name: "krb_ap_err_badmatch",
+                shortname: None,
+                id: 36,
+            },
+            RefItem{
+                name: "krb_ap_err_skew",
+                shortname: None,
+                id: 37,
+            },
+            RefItem{
+                name: "krb_ap_err_badaddr",
+                shortname: None,
+                id: 38,
+            },
+            RefItem{
+                name: "krb_ap_err_badversion",
+                shortname: None,
+                id: 39,
+            },
+            RefItem{
+                name: "krb_ap_err_msg_type",
+                shortname: None,
+                id: 40,
+            },
+            RefItem{
+                name: "krb_ap_err_modified",
+                shortname: None,
+                id: 41,
+            },
+            RefItem{
+                name: "krb_ap_err_badorder",
+                shortname: None,
+                id: 42,
+            },
+            RefItem{
+                name: "krb_ap_err_badkeyver",
+                shortname: None,
+                id: 44,
+            },
+            RefItem{
+                name: "krb_ap_err_nokey",
+                shortname: None,
+                id: 45,
+            },
+            RefItem{
+                name: "krb_ap_err_mut_fail",
+                shortname: None,
+                id: 46,
+            },
+            RefItem{
+                name: "krb_ap_err_baddirection",
+                shortname: None,
+                id: 47,
+            },
+            RefItem{
+                name: "krb_ap_err_method",
+                shortname: None,
+                id: 48,
+            },
+            RefItem{
+                name: "krb_ap_err_badseq",
+                shortname: None,
+                id: 49,
+            },
+            RefItem{
+                name: "krb_ap_err_inapp_cksum",
+                shortname: None,
+                id: 50,
+            },
+            RefItem{
+                name: "krb_ap_path_not_accepted",
+                shortname: None,
+                id: 51,
+            },
+            RefItem{
+                name: "krb_err_response_too_big",
+                shortname: None,
+                id: 52,
+            },
+            RefItem{
+                name: "krb_err_generic",
+                shortname: None,
+                id: 60,
+            },
+            RefItem{
+                name: "krb_err_field_toolong",
+                shortname: None,
+                id: 61,
+            },
+            RefItem{
+                name: "kdc_error_client_not_trusted",
+                shortname: None,
+                id: 62,
+            },
+            RefItem{
+                name: "kdc_error_kdc_not_trusted",
+                shortname: None,
+                id: 63,
+            },
+            RefItem{
+                name: "kdc_error_invalid_sig",
+                shortname: None,
+                id: 64,
+            },
+            RefItem{
+                name: "kdc_err_key_too_weak",
+                shortname: None,
+                id: 65,
+            },
+            RefItem{
+                name: "kdc_err_certificate_mismatch",
+                shortname: None,
+                id: 66,
+            },
+            RefItem{
+                name: "krb_ap_err_no_tgt",
+                shortname: None,
+                id: 67,
+            },
+            RefItem{
+                name: "kdc_err_wrong_realm",
+                shortname: None,
+                id: 68,
+            },
+            RefItem{
+                name: "krb_ap_err_user_to_user_required",
+                shortname: None,
+                id: 69,
+            },
+            RefItem{
+                name: "kdc_err_cant_verify_certificate",
+                shortname: None,
+                id: 70,
+            },
+            RefItem{
+                name: "kdc_err_invalid_certificate",
+                shortname: None,
+                id: 71,
+            },
+            RefItem{
+                name: "kdc_err_revoked_certificate",
+                shortname: None,
+                id: 72,
+            },
+            RefItem{
+                name: "kdc_err_revocation_status_unknown",
+                shortname: None,
+                id: 73,
+            },
+            RefItem{
+                name: "kdc_err_revocation_status_unavailable",
+                shortname: None,
+                id: 74,
+            },
+            RefItem{
+                name: "kdc_err_client_name_mismatch",
+                shortname: None,
+                id: 75,
+            },
+            RefItem{
+                name: "kdc_err_kdc_name_mismatch",
+                shortname: None,
+                id: 76,
+            },
+            RefItem{
+                name: "kdc_err_preauth_expired",
+                shortname: None,
+                id: 90,
+            },
+            RefItem{
+                name: "kdc_err_more_preauth_data_required",
+                shortname: None,
+                id: 91,
+            },
+            RefItem{
+                name: "kdc_err_preauth_bad_authentication_set",
+                shortname: None,
+                id: 92,
+            },
+            RefItem{
+                name: "kdc_err_unknown_critical_fast_options",
+                shortname: None,
+                id: 93,
+            },
+        ],
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc4120.html#section-5.3, numbered by
+    // bit with bit 0 the most significant
+    pub static ref TICKET_FLAGS: RefTable = RefTable {
+        entries: vec![
+            RefItem{
+                name: "tkt_flg_reserved",
+                shortname: Some("reserved"),
+                id: 0,
+            },
+            RefItem{
+                name: "tkt_flg_forwardable",
+                shortname: Some("forwardable"),
+                id: 1,
+            },
+            RefItem{
+                name: "tkt_flg_forwarded",
+                shortname: Some("forwarded"),
+                id: 2,
+            },
+            RefItem{
+                name: "tkt_flg_proxiable",
+                shortname: Some("proxiable"),
+                id: 3,
+            },
+            RefItem{
+                name: "tkt_flg_proxy",
+                shortname: Some("proxy"),
+                id: 4,
+            },
+            RefItem{
+                name: "tkt_flg_may_postdate",
+                shortname: Some("may-postdate"),
+                id: 5,
+            },
+            RefItem{
+                name: "tkt_flg_postdated",
+                shortname: Some("postdated"),
+                id: 6,
+            },
+            RefItem{
+                name: "tkt_flg_invalid",
+                shortname: Some("invalid"),
+                id: 7,
+            },
+            RefItem{
+                name: "tkt_flg_renewable",
+                shortname: Some("renewable"),
+                id: 8,
+            },
+            RefItem{
+                name: "tkt_flg_initial",
+                shortname: Some("initial"),
+                id: 9,
+            },
+            RefItem{
+                name: "tkt_flg_pre_authent",
+                shortname: Some("pre-authent"),
+                id: 10,
+            },
+            RefItem{
+                name: "tkt_flg_hw_authent",
+                shortname: Some("hw-authent"),
+                id: 11,
+            },
+            RefItem{
+                name: "tkt_flg_transited_policy_checked",
+                shortname: Some("transited-policy-checked"),
+                id: 12,
+            },
+            RefItem{
+                name: "tkt_flg_ok_as_delegate",
+                shortname: Some("ok-as-delegate"),
+                id: 13,
+            },
+            RefItem{
+                name: "tkt_flg_anonymous",
+                shortname: Some("anonymous"),
+                id: 14,
+            },
+            RefItem{
+                name: "tkt_flg_enc_pa_rep",
+                shortname: Some("enc-pa-rep"),
+                id: 15,
+            },
+        ],
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc4120.html#section-7.5.4, with the
+    // registrations that followed and those of MS-KILE
+    pub static ref AUTHORIZATION_DATA_TYPES: RefTable = RefTable {
+        entries: vec![
+            RefItem{
+                name: "krb5_authdata_if_relevant",
+                shortname: Some("ad-if-relevant"),
+                id: 1,
+            },
+            RefItem{
+                name: "krb5_authdata_intended_for_server",
+                shortname: Some("ad-intended-for-server"),
+                id: 2,
+            },
+            RefItem{
+                name: "krb5_authdata_intended_for_application_class",
+                shortname: Some("ad-intended-for-application-class"),
+                id: 3,
+            },
+            RefItem{
+                name: "krb5_authdata_kdc_issued",
+                shortname: Some("ad-kdc-issued"),
+                id: 4,
+            },
+            RefItem{
+                name: "krb5_authdata_and_or",
+                shortname: Some("ad-and-or"),
+                id: 5,
+            },
+            RefItem{
+                name: "krb5_authdata_mandatory_ticket_extensions",
+                shortname: Some("ad-mandatory-ticket-extensions"),
+                id: 6,
+            },
+            RefItem{
+                name: "krb5_authdata_in_ticket_extensions",
+                shortname: Some("ad-in-ticket-extensions"),
+                id: 7,
+            },
+            RefItem{
+                name: "krb5_authdata_mandatory_for_kdc",
+                shortname: Some("ad-mandatory-for-kdc"),
+                id: 8,
+            },
+            RefItem{
+                name: "krb5_authdata_initial_verified_cas",
+                shortname: Some("ad-initial-verified-cas"),
+                id: 9,
+            },
+            RefItem{
+                name: "krb5_authdata_osf_dce",
+                shortname: Some("osf-dce"),
+                id: 64,
+            },
+            RefItem{
+                name: "krb5_authdata_sesame",
+                shortname: Some("sesame"),
+                id: 65,
+            },
+            RefItem{
+                name: "krb5_authdata_osf_dce_pki_certid",
+                shortname: Some("ad-osf-dce-pki-certid"),
+                id: 66,
+            },
+            RefItem{
+                name: "krb5_authdata_fx_armor",
+                shortname: Some("ad-fx-armor"),
+                id: 71,
+            },
+            RefItem{
+                name: "krb5_authdata_cammac",
+                shortname: Some("ad-cammac"),
+                id: 96,
+            },
+            RefItem{
+                name: "krb5_authdata_auth_indicator",
+                shortname: Some("ad-authentication-indicator"),
+                id: 97,
+            },
+            RefItem{
+                name: "krb5_authdata_win2k_pac",
+                shortname: Some("ad-win2k-pac"),
+                id: 128,
+            },
+            RefItem{
+                name: "krb5_authdata_etype_negotiation",
+                shortname: Some("ad-etype-negotiation"),
+                id: 129,
+            },
+            RefItem{
+                name: "krb5_authdata_token_restrictions",
+                shortname: Some("kerb-auth-data-token-restrictions"),
+                id: 141,
+            },
+            RefItem{
+                name: "krb5_authdata_kerb_local",
+                shortname: Some("kerb-local"),
+                id: 142,
+            },
+            RefItem{
+                name: "krb5_authdata_ap_options",
+                shortname: Some("ad-auth-data-ap-options"),
+                id: 143,
+            },
+            RefItem{
+                name: "krb5_authdata_client_target",
+                shortname: Some("kerb-auth-data-client-target"),
+                id: 144,
+            },
+            RefItem{
+                name: "krb5_authdata_signticket",
+                shortname: Some("ad-signticket"),
+                id: 512,
+            },
+        ],
+    };
+}
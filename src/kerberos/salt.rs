@@ -0,0 +1,112 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaltMode {
+    Mit,
+    AdUser,
+    AdComputer,
+    Afs3,
+}
+
+
+// Computes the default string-to-key salt for a principal of the form
+// name/instance@REALM.
+pub fn salt(principal: &str, mode: SaltMode) -> String {
+    let (name, realm) = principal.rsplit_once('@').unwrap_or((principal, ""));
+    match mode {
+        SaltMode::Mit => mit_salt(name, realm),
+        SaltMode::AdUser => ad_user_salt(name, realm),
+        SaltMode::AdComputer => ad_computer_salt(name, realm),
+        SaltMode::Afs3 => afs3_salt(realm),
+    }
+}
+
+// MIT: the realm followed by each principal component, as given
+fn mit_salt(name: &str, realm: &str) -> String {
+    let mut salt = realm.to_string();
+    salt.extend(name.split('/'));
+    salt
+}
+
+// AD users: the upper-cased realm followed by the account name. Enterprise
+// principals carry the whole UPN as their name, so only the part before the
+// '@' is used.
+fn ad_user_salt(name: &str, realm: &str) -> String {
+    let user = match name.split_once('@') {
+        Some((user, _)) => user,
+        None => name,
+    };
+    format!("{}{}", realm.to_uppercase(), user)
+}
+
+// AD computers: the upper-cased realm, "host" and the lower-cased FQDN. The
+// FQDN comes from a host/fqdn principal or from the account name with its
+// trailing '$' dropped and the realm appended.
+fn ad_computer_salt(name: &str, realm: &str) -> String {
+    let host = match name.split_once('/') {
+        Some((_, host)) => host,
+        None => name.trim_end_matches('$'),
+    };
+    let mut host = host.to_lowercase();
+    if !host.contains('.') && !realm.is_empty() {
+        host.push('.');
+        host.push_str(&realm.to_lowercase());
+    }
+    format!("{}host{}", realm.to_uppercase(), host)
+}
+
+// AFS: the cell name, which is taken to be the realm. It feeds the afs3
+// string-to-key rather than the usual DES one.
+fn afs3_salt(realm: &str) -> String {
+    realm.to_string()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mit_salts() {
+        for (principal, expected) in [
+            ("raeburn@ATHENA.MIT.EDU", "ATHENA.MIT.EDUraeburn"),
+            ("HTTP/web.example.com@EXAMPLE.COM", "EXAMPLE.COMHTTPweb.example.com"),
+            // MIT takes the realm and name exactly as given
+            ("Admin@example.com", "example.comAdmin"),
+            ("WKS01$@EXAMPLE.COM", "EXAMPLE.COMWKS01$"),
+            ("user", "user"),
+        ] {
+            assert_eq!(salt(principal, SaltMode::Mit), expected, "{}", principal);
+        }
+    }
+
+    #[test]
+    fn ad_user_salts() {
+        for (principal, expected) in [
+            ("Administrator@EXAMPLE.COM", "EXAMPLE.COMAdministrator"),
+            // the realm is upper-cased, the account name is not
+            ("jSmith@example.com", "EXAMPLE.COMjSmith"),
+            // an enterprise principal salts with the name before its '@'
+            ("jsmith@corp.example.com@EXAMPLE.COM", "EXAMPLE.COMjsmith"),
+        ] {
+            assert_eq!(salt(principal, SaltMode::AdUser), expected, "{}", principal);
+        }
+    }
+
+    #[test]
+    fn ad_computer_salts() {
+        for principal in [
+            "WKS01$@EXAMPLE.COM",
+            "wks01$@example.com",
+            "host/WKS01.Example.COM@EXAMPLE.COM",
+            "host/wks01.example.com@example.com",
+        ] {
+            assert_eq!(salt(principal, SaltMode::AdComputer), "EXAMPLE.COMhostwks01.example.com", "{}", principal);
+        }
+        // without a '$' the name is still taken as the host
+        assert_eq!(salt("FS01@EXAMPLE.COM", SaltMode::AdComputer), "EXAMPLE.COMhostfs01.example.com");
+    }
+
+    #[test]
+    fn afs3_salts() {
+        assert_eq!(salt("user@GRAND.CENTRAL.ORG", SaltMode::Afs3), "GRAND.CENTRAL.ORG");
+    }
+}
@@ -0,0 +1,156 @@
+use std::fs;
+use std::path::Path;
+use crate::crypto::etype;
+use crate::kerberos::asn1::{Asn1, ApReq, EncTicketPart, KrbCred, Ticket};
+use crate::kerberos::asn1::der::{self, Reader};
+use crate::kerberos::ccache::{Ccache, CcacheVersion};
+use crate::kerberos::keytab::{EntryFilter, Keyblock, Keytab, KeytabEntry};
+use crate::kerberos::{kirbi, reference};
+use crate::error::{Error, Result};
+
+
+// Tickets as they are captured: from a ccache, a KRB-CRED, or an AP-REQ
+// copied out of Wireshark, and the service side of reading them.
+
+// The ticket's enc-part is encrypted under this usage with the service key
+const KEY_USAGE_TICKET: u32 = 2;
+
+// Takes a ccache, a KRB-CRED as DER or base64, or an AP-REQ or Ticket as
+// DER or a hex dump.
+pub fn from_bytes(bytes: &[u8]) -> Result<Vec<Ticket>> {
+    if bytes.len() >= 2 && CcacheVersion::from_u16(u16::from_be_bytes([bytes[0], bytes[1]])).is_some() {
+        return Ccache::from_bytes(bytes)?.credentials.iter()
+            .filter(|c| !c.is_config())
+            .map(|c| Ticket::from_der(&c.ticket))
+            .collect();
+    }
+    if let Some(tickets) = from_der(bytes) {
+        return tickets;
+    }
+    let text: String = String::from_utf8_lossy(bytes).split_whitespace().collect();
+    if let Some(tickets) = hex::decode(&text).ok().and_then(|der| from_der(&der)) {
+        return tickets;
+    }
+    Ok(kirbi::from_bytes(bytes)?.tickets)
+}
+
+pub fn from_file(path: &dyn AsRef<Path>) -> Result<Vec<Ticket>> {
+    let bytes = fs::read(path)?;
+    from_bytes(&bytes)
+}
+
+// None when the bytes do not start like a message carrying tickets.
+fn from_der(bytes: &[u8]) -> Option<Result<Vec<Ticket>>> {
+    let tag = *bytes.first()?;
+    if tag == der::application(1) {
+        Some(Ticket::from_der(bytes).map(|t| vec![t]))
+    } else if tag == der::application(14) {
+        Some(ApReq::from_der(bytes).map(|r| vec![r.ticket]))
+    } else if tag == der::application(22) {
+        Some(KrbCred::from_der(bytes).map(|c| c.tickets))
+    } else {
+        None
+    }
+}
+
+pub fn service(ticket: &Ticket) -> String {
+    format!("{}@{}", ticket.sname, ticket.realm)
+}
+
+// The keytab entries that could have encrypted the ticket: the service's,
+// for the ticket's etype, at its kvno when it gives one.
+pub fn matching_keys<'a>(keytab: &'a Keytab, ticket: &Ticket) -> Vec<&'a KeytabEntry> {
+    let filter = EntryFilter {
+        principal: Some(service(ticket)),
+        kvno: ticket.enc_part.kvno,
+        // MIT stores enctypes as signed 16 bit numbers
+        etype: Some(ticket.enc_part.etype as u16),
+    };
+    keytab.entries().filter(|e| filter.matches(e)).collect()
+}
+
+pub fn decrypt(ticket: &Ticket, key: &Keyblock) -> Result<EncTicketPart> {
+    let etype = etype::by_id(ticket.enc_part.etype).ok_or_else(||
+        Error::UnsupportedEtype(reference::ENCRYPTION_TYPES.name(ticket.enc_part.etype).to_string()))?;
+    let plaintext = etype.decrypt(&key.key.data, KEY_USAGE_TICKET, &ticket.enc_part.cipher)?;
+    // the DES etypes pad to their block size, so trailing bytes are left alone
+    EncTicketPart::read(&mut Reader::new(&plaintext, "EncTicketPart"))
+}
+
+// Tries each matching entry in turn, as without a kvno there may be several.
+pub fn decrypt_with<'a>(keytab: &'a Keytab, ticket: &Ticket) -> Result<(&'a KeytabEntry, EncTicketPart)> {
+    let mut error = Error::NoMatchingKey {
+        principal: service(ticket),
+        kvno: ticket.enc_part.kvno,
+        etype: reference::ENCRYPTION_TYPES.name(ticket.enc_part.etype),
+    };
+    for entry in matching_keys(keytab, ticket) {
+        match decrypt(ticket, &entry.key) {
+            Ok(part) => return Ok((entry, part)),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::fixture;
+
+    // fixtures/gen/ticket.py: a keytab with the aes256, rc4 and des3 keys
+    // of HTTP/web.example.com at kvno 3, and two tickets carrying
+    // ticket/enc-ticket-part.der, one as aes256 at kvno 3 in an AP-REQ hex
+    // dump and one as rc4 without a kvno.
+    fn keytab() -> Keytab {
+        Keytab::from_bytes(&fixture("ticket/http.keytab")).unwrap()
+    }
+
+    fn ticket(name: &str) -> Ticket {
+        let mut tickets = from_bytes(&fixture(name)).unwrap();
+        assert_eq!(tickets.len(), 1);
+        tickets.remove(0)
+    }
+
+    fn enc_ticket_part() -> EncTicketPart {
+        EncTicketPart::from_der(&fixture("ticket/enc-ticket-part.der")).unwrap()
+    }
+
+    #[test]
+    fn ap_req_decrypts() {
+        let keytab = keytab();
+        let ticket = ticket("ticket/ap-req.hex");
+        assert_eq!(service(&ticket), "HTTP/web.example.com@EXAMPLE.COM");
+        let keys = matching_keys(&keytab, &ticket);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].key.key_type, 18);
+        let (entry, part) = decrypt_with(&keytab, &ticket).unwrap();
+        assert_eq!(entry.kvno(), 3);
+        assert_eq!(entry.key.key_type, 18);
+        assert_eq!(part, enc_ticket_part());
+    }
+
+    #[test]
+    fn ticket_without_kvno_decrypts() {
+        let keytab = keytab();
+        let ticket = ticket("ticket/rc4.ticket");
+        assert_eq!(ticket.enc_part.kvno, None);
+        let (entry, part) = decrypt_with(&keytab, &ticket).unwrap();
+        assert_eq!(entry.key.key_type, 23);
+        assert_eq!(part, enc_ticket_part());
+    }
+
+    #[test]
+    fn wrong_key_fails() {
+        let keytab = keytab();
+        let ticket = ticket("ticket/ap-req.hex");
+        let mut key = matching_keys(&keytab, &ticket)[0].key.clone();
+        key.key.data[0] ^= 1;
+        assert!(matches!(decrypt(&ticket, &key), Err(Error::IntegrityCheckFailed)));
+
+        let mut ticket = ticket;
+        ticket.enc_part.kvno = Some(4);
+        assert!(matching_keys(&keytab, &ticket).is_empty());
+        assert!(matches!(decrypt_with(&keytab, &ticket), Err(Error::NoMatchingKey { kvno: Some(4), .. })));
+    }
+}
@@ -0,0 +1,12 @@
+#[macro_use]
+extern crate lazy_static;
+
+#[macro_use]
+extern crate log;
+
+pub mod error;
+pub mod kerberos;
+pub mod crypto;
+pub mod utils;
+
+pub use crate::error::{Error, Result};
@@ -1,61 +1,108 @@
-#[macro_use]
-extern crate lazy_static;
+mod args;
 
-#[macro_use]
-extern crate log;
-
-mod kerberos;
-mod utils;
-mod crypto;
-
-use crate::kerberos::keytab;
-use crate::utils::args::*;
-use crate::crypto::{aes, des, lanman, crypto::*};
-use std::error::Error;
+use metaros::kerberos::{ccache, keytab, kirbi, reference, salt, ticket};
+use metaros::kerberos::keytab::format;
+use metaros::crypto::{checksum, etype, lanman, KrbChecksum, KrbEtype, UsageKeys, WinHash};
+use metaros::utils::utils::timestamp_now;
+use metaros::{Error, Result};
+use crate::args::*;
+use std::{io, process};
 use clap::Parser;
-use loggerv;
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
 
     let cli = Cli::parse();
-    loggerv::Logger::new()
+    if let Err(e) = loggerv::Logger::new()
         .verbosity(cli.verbose)
         .module_path(false)
-        .init()
-        .unwrap();
+        .init() {
+        eprintln!("warning: could not initialise logging: {}", e);
+    }
+    if let Err(e) = run(&cli) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(cli: &Cli) -> Result<()> {
     match &cli.command {
         Commands::Keytab(c) => {
             match c {
                 KeytabCommands::Create(x) => {
-                    let kt: keytab::Keytab;
-                    match &x.infile {
-                        Some(f) => {
-                            println!("Input File:{}",f.display());
-                            kt = keytab::Keytab::from_csv(f).unwrap();
-                        },
+                    let mut kt = match &x.infile {
+                        Some(f) => keytab::Keytab::from_csv(f)?,
                         None => {
-                            println!("no infile");
-                            kt = keytab::Keytab::new(
-                                vec![keytab::KeytabEntry::new(
-                                    &x.principal.as_ref().unwrap(),
-                                    &x.name_type.as_ref().unwrap(),
-                                    &x.timestamp.unwrap(),
-                                    &x.version.unwrap().try_into().unwrap(),
-                                    &x.etype.as_ref().unwrap(),
-                                    &x.key.as_ref().unwrap(),
-                                    &x.version.unwrap(),
-                                )]
-                            );
+                            let principal = x.principal.as_ref()
+                                .ok_or(Error::MissingArgument("principal"))?;
+                            let name_type = x.name_type.as_ref()
+                                .ok_or(Error::MissingArgument("name-type"))?;
+                            let timestamp = x.timestamp.unwrap_or_else(timestamp_now);
+                            let kvno = x.version.unwrap_or(0);
+                            let entries = match &x.password {
+                                Some(password) =>
+                                    password_entries(x, principal, name_type, timestamp, kvno, password)?,
+                                None => vec![keytab::KeytabEntry::new(
+                                    principal,
+                                    name_type,
+                                    &timestamp,
+                                    &kvno,
+                                    x.etype.as_ref().ok_or(Error::MissingArgument("etype"))?,
+                                    x.key.as_ref().ok_or(Error::MissingArgument("key"))?,
+                                )?],
+                            };
+                            keytab::Keytab::new(entries)
                         },
-                    }
+                    };
+                    kt.file_format_version = match x.format_version {
+                        KeytabFormat::V1 => keytab::KeytabVersion::V1,
+                        KeytabFormat::V2 => keytab::KeytabVersion::V2,
+                    };
                     kt.to_file(&x.outfile)?;
                 },
                 KeytabCommands::Read(x) => {
-                    println!("Reading: {}", x.infile.display());
-                    println!(
-                        "{}", 
-                        keytab::Keytab::from_file(&x.infile).unwrap()
-                    );
+                    let kt = keytab::Keytab::from_file(&x.infile)?;
+                    match x.format {
+                        ReadFormat::Text => {
+                            println!("Reading: {}", x.infile.display());
+                            println!("{}", kt);
+                        },
+                        ReadFormat::Json => println!("{}", format::json(&kt)),
+                        ReadFormat::Csv => format::csv(&kt, io::stdout())?,
+                        ReadFormat::Table => print!("{}", format::table(&kt)),
+                        ReadFormat::Klist => print!("{}", format::klist(&kt, &x.infile.display().to_string())),
+                    }
+                },
+                KeytabCommands::Compact(x) => {
+                    let mut kt = keytab::Keytab::from_file(&x.infile)?;
+                    println!("Removing {} holes from: {}", kt.holes().count(), x.infile.display());
+                    kt.compact();
+                    kt.to_file(&x.outfile)?;
+                },
+                KeytabCommands::Merge(x) => {
+                    let mut kt = keytab::Keytab::from_file(&x.infiles[0])?;
+                    for f in &x.infiles[1..] {
+                        let added = kt.merge(keytab::Keytab::from_file(f)?);
+                        println!("Merged {} entries from: {}", added, f.display());
+                    }
+                    kt.to_file(&x.outfile)?;
+                },
+                KeytabCommands::Remove(x) => {
+                    let mut kt = keytab::Keytab::from_file(&x.infile)?;
+                    let removed = kt.remove(&entry_filter(&x.select)?);
+                    println!("Removed {} entries from: {}", removed, x.infile.display());
+                    kt.to_file(&x.outfile)?;
+                },
+                KeytabCommands::Rename(x) => {
+                    let mut kt = keytab::Keytab::from_file(&x.infile)?;
+                    let renamed = kt.rename(&x.principal, &x.to)?;
+                    println!("Renamed {} entries in: {}", renamed, x.infile.display());
+                    kt.to_file(&x.outfile)?;
+                },
+                KeytabCommands::SetKvno(x) => {
+                    let mut kt = keytab::Keytab::from_file(&x.infile)?;
+                    let changed = kt.set_kvno(&entry_filter(&x.select)?, x.to);
+                    println!("Set kvno {} on {} entries in: {}", x.to, changed, x.infile.display());
+                    kt.to_file(&x.outfile)?;
                 },
             }
 
@@ -63,100 +110,117 @@ fn main() -> Result<(), Box<dyn Error>> {
         Commands::Key(c) => {
             match c {
                 KeyCommands::Derive(sc) => {
+                    let salt = match (&sc.principal, &sc.salt) {
+                        // AFS keys are salted with the cell, whatever the salt mode
+                        (Some(principal), None) if sc.afs3 =>
+                            salt::salt(principal, salt::SaltMode::Afs3),
+                        _ => resolve_salt(&sc.salt, sc.principal.as_deref(), &sc.salt_mode),
+                    };
                     if sc.all {
-                        println!("lets do all");
-                        println!("[AES128] {}",
-                            hex::encode_upper(
-                                aes::KrbAes128::string_to_key(
-                                    &sc.password,
-                                    &sc.salt.as_ref().unwrap() 
-                                )
-                            )
-                        );
-                        println!("[AES256] {}", 
-                            hex::encode_upper(
-                                aes::KrbAes256::string_to_key(
-                                    &sc.password,
-                                    &sc.salt.as_ref().unwrap()
-                                )
-                            )
-                        );
-                        println!("[DES] {}", 
-                            hex::encode_upper(
-                                des::KrbDes::string_to_key(
-                                    &sc.password,
-                                    &sc.salt.as_ref().unwrap() 
-                                )
-                            )
-                        );
-                        println!("[RC4/NTLM] {}", 
-                            hex::encode_upper(
-                                lanman::NTLanMan::from_string(
-                                    &sc.password,
-                                )
-                            )
-                        );
-                        println!("[LM] {}",
-                            hex::encode_upper(
-                                lanman::LanMan::from_string(
-                                    &sc.password,
-                                )
-                            )
-                        );
-                    }
-                    else {
-                        match &sc.etype.as_ref().unwrap() {
-                            Etypes::Aes128 => {
-                                println!("[AES128] {}", 
-                                    hex::encode_upper(
-                                        aes::KrbAes128::string_to_key(
-                                            &sc.password,
-                                            &sc.salt.as_ref().unwrap() 
-                                        )
-                                    )
-                                );
-                            }
-                            Etypes::Aes256 => {
-                                println!("[AES256] {}", 
-                                    hex::encode_upper(
-                                        aes::KrbAes256::string_to_key(
-                                            &sc.password,
-                                            &sc.salt.as_ref().unwrap() 
-                                        )
-                                    )
-                                );
-                            }
-                            Etypes::Des => {
-                                println!("[DES] {}", 
-                                    hex::encode_upper(
-                                        des::KrbDes::string_to_key(
-                                            &sc.password,
-                                            &sc.salt.as_ref().unwrap() 
-                                        )
-                                    )
-                                );
-                            }
-                            Etypes::Rc4 | Etypes::Ntlm => {
-                                println!("[RC4/NTLM] {}", 
-                                    hex::encode_upper(
-                                        lanman::NTLanMan::from_string(
-                                            &sc.password,
-                                        )
-                                    )
-                                );
-                            }
-                            Etypes::Lm => {
-                                println!("[LM] {}",
-                                    hex::encode_upper(
-                                        lanman::LanMan::from_string(
-                                            &sc.password,
-                                        )
-                                    )
-                                );
+                        for etype in etype::all() {
+                            print_key(etype.name(), &derive_key(etype, sc, &salt)?);
+                        }
+                        print_key("lm", &lanman::LanMan::from_string(&sc.password)?);
+                    } else {
+                        let query = sc.etype.as_deref().ok_or(Error::MissingArgument("etype"))?;
+                        // the NT and LM hashes are not Kerberos etypes
+                        match query {
+                            "ntlm" => print_key(query, &lanman::NTLanMan::from_string(&sc.password)?),
+                            "lm" => print_key(query, &lanman::LanMan::from_string(&sc.password)?),
+                            _ => {
+                                let etype = etype::lookup(query)?;
+                                print_key(etype.name(), &derive_key(etype, sc, &salt)?);
                             }
                         }
                     }
                 }
+                KeyCommands::Usage(x) => {
+                    let (etype, usage, keys) = usage_keys(x)?;
+                    println!("Etype: {} ({})", etype.name(), etype.id());
+                    println!("Usage: {} ({})", usage, reference::KEY_USAGES.name(usage));
+                    println!("[Kc] {}", hex::encode_upper(keys.kc));
+                    println!("[Ke] {}", hex::encode_upper(keys.ke));
+                    println!("[Ki] {}", hex::encode_upper(keys.ki));
+                }
+            }
+        }
+        Commands::Checksum(c) => {
+            match c {
+                ChecksumCommands::Compute(x) => {
+                    let c = checksum_input(&x.input)?;
+                    println!("[{}] {}",
+                        c.cksumtype.name(),
+                        hex::encode_upper(c.cksumtype.compute(&c.key, c.usage, &c.data)?)
+                    );
+                }
+                ChecksumCommands::Verify(x) => {
+                    let c = checksum_input(&x.input)?;
+                    c.cksumtype.verify(&c.key, c.usage, &c.data, &decode_hex(&x.checksum)?)?;
+                    println!("[{}] checksum OK", c.cksumtype.name());
+                }
+            }
+        }
+        Commands::Ccache(c) => {
+            match c {
+                CcacheCommands::Read(x) => {
+                    let cc = ccache::Ccache::from_file(&x.infile)?;
+                    match x.format {
+                        ReadFormat::Text => {
+                            println!("Reading: {}", x.infile.display());
+                            println!("{}", cc);
+                        },
+                        ReadFormat::Json => println!("{}", ccache::format::json(&cc)),
+                        ReadFormat::Csv => ccache::format::csv(&cc, io::stdout())?,
+                        ReadFormat::Table => print!("{}", ccache::format::table(&cc)),
+                        ReadFormat::Klist => print!("{}", ccache::format::klist(&cc, &x.infile.display().to_string())),
+                    }
+                }
+            }
+        }
+        Commands::Ticket(c) => {
+            match c {
+                TicketCommands::Convert(x) => {
+                    let bytes = std::fs::read(&x.infile)?;
+                    // a ccache starts with its version, which no KRB-CRED or base64 does
+                    let is_ccache = bytes.len() >= 2
+                        && ccache::CcacheVersion::from_u16(u16::from_be_bytes([bytes[0], bytes[1]])).is_some();
+                    if is_ccache {
+                        let cred = kirbi::from_ccache(&ccache::Ccache::from_bytes(&bytes)?)?;
+                        println!("Converted {} tickets from ccache: {}", cred.tickets.len(), x.infile.display());
+                        kirbi::to_file(&cred, x.base64, &x.outfile)?;
+                    } else {
+                        let cc = kirbi::to_ccache(&kirbi::from_bytes(&bytes)?)?;
+                        println!("Converted {} tickets from KRB-CRED: {}", cc.credentials.len(), x.infile.display());
+                        cc.to_file(&x.outfile)?;
+                    }
+                }
+                TicketCommands::Decrypt(x) => {
+                    let kt = keytab::Keytab::from_file(&x.keytab)?;
+                    let tickets = ticket::from_file(&x.infile)?;
+                    println!("Reading: {}", x.infile.display());
+                    let mut decrypted = 0;
+                    let mut failure = None;
+                    for (i, t) in tickets.iter().enumerate() {
+                        println!("Ticket[{}]", i + 1);
+                        println!("Service: {}", ticket::service(t));
+                        println!("Etype: {}", reference::ENCRYPTION_TYPES.name(t.enc_part.etype));
+                        match ticket::decrypt_with(&kt, t) {
+                            Ok((entry, part)) => {
+                                println!("Keytab Entry: {} (kvno {})", entry.principal(), entry.kvno());
+                                println!("{}", part);
+                                decrypted += 1;
+                            },
+                            Err(e) => {
+                                println!("Not decrypted: {}\n", e);
+                                failure = Some(e);
+                            },
+                        }
+                    }
+                    // a ccache usually holds a TGT too, which a service keytab cannot read
+                    if let (0, Some(e)) = (decrypted, failure) {
+                        return Err(e);
+                    }
+                }
             }
         }
     }
@@ -164,3 +228,230 @@ fn main() -> Result<(), Box<dyn Error>> {
 }
 
 
+
+// One entry per etype, all sharing the salt and kvno: the AES and RC4 keys
+// Windows and MIT both use, plus DES if asked for, unless an etype is given.
+fn password_entries(create: &KeytabCreate, principal: &str, name_type: &str,
+        timestamp: u32, kvno: u32, password: &str) -> Result<Vec<keytab::KeytabEntry>> {
+    let salt = resolve_salt(&create.salt, Some(principal), &create.salt_mode);
+    let etypes = match &create.etype {
+        Some(etype) => vec![etype.as_str()],
+        None if create.des => vec!["aes256-cts-hmac-sha1-96", "aes128-cts-hmac-sha1-96", "rc4-hmac", "des-cbc-md5"],
+        None => vec!["aes256-cts-hmac-sha1-96", "aes128-cts-hmac-sha1-96", "rc4-hmac"],
+    };
+    etypes.iter().map(|etype| keytab::KeytabEntry::from_password(
+        principal,
+        name_type,
+        &timestamp,
+        &kvno,
+        etype,
+        password,
+        &salt,
+    )).collect()
+}
+
+// An explicit salt wins; otherwise it is computed from the principal.
+fn resolve_salt(explicit: &Option<String>, principal: Option<&str>, mode: &SaltModes) -> String {
+    if let Some(salt) = explicit {
+        return salt.clone();
+    }
+    let mode = match mode {
+        SaltModes::Mit => salt::SaltMode::Mit,
+        SaltModes::AdUser => salt::SaltMode::AdUser,
+        SaltModes::AdComputer => salt::SaltMode::AdComputer,
+    };
+    principal.map(|p| salt::salt(p, mode)).unwrap_or_default()
+}
+
+fn decode_hex(data: &str) -> Result<Vec<u8>> {
+    hex::decode(data).map_err(|_| Error::BadHex(data.to_string()))
+}
+
+struct ChecksumJob {
+    cksumtype: &'static dyn KrbChecksum,
+    key: Vec<u8>,
+    usage: u32,
+    data: Vec<u8>,
+}
+
+// Resolves the checksum type, key, usage and data, insisting on a key for
+// the keyed types.
+fn checksum_input(input: &ChecksumInput) -> Result<ChecksumJob> {
+    let cksumtype = checksum::lookup(&input.cksumtype)?;
+    let (key, usage) = match (&input.key, &input.usage) {
+        (Some(key), Some(usage)) => (decode_hex(key)?, key_usage(usage)?),
+        _ if cksumtype.keyed() => return Err(Error::MissingKey(cksumtype.name())),
+        _ => (Vec::new(), 0),
+    };
+    Ok(ChecksumJob { cksumtype, key, usage, data: decode_hex(&input.data)? })
+}
+
+// A name from reference::KEY_USAGES, or any usage number
+fn key_usage(query: &str) -> Result<u32> {
+    reference::KEY_USAGES.lookup(query)
+        .map(|item| item.id as u32)
+        .or_else(|| query.parse().ok())
+        .ok_or_else(|| Error::UnknownKeyUsage(query.to_string()))
+}
+
+fn usage_keys(x: &KeyUsage) -> Result<(&'static dyn KrbEtype, u32, UsageKeys)> {
+    let etype = etype::lookup(&x.etype)?;
+    let usage = key_usage(&x.usage)?;
+    let keys = etype.usage_keys(&decode_hex(&x.key)?, usage)?;
+    Ok((etype, usage, keys))
+}
+
+fn entry_filter(select: &EntrySelect) -> Result<keytab::EntryFilter> {
+    keytab::EntryFilter::new(
+        select.principal.as_deref(),
+        select.kvno,
+        select.etype.as_deref(),
+    )
+}
+
+// The PBKDF2 iteration count and the AFS string-to-key go to the etype as
+// the s2kparams they are on the wire.
+fn derive_key(etype: &dyn KrbEtype, derive: &KeyDerive, salt: &str) -> Result<Vec<u8>> {
+    if etype.salted() && derive.salt.is_none() && derive.principal.is_none() {
+        return Err(Error::MissingArgument("salt"));
+    }
+    let params = if derive.afs3 {
+        Some(vec![1])
+    } else {
+        // only the PBKDF2 etypes take an iteration count, as four bytes
+        derive.iterations
+            .filter(|_| etype.default_s2k_params().len() == 4)
+            .map(|i| i.to_be_bytes().to_vec())
+    };
+    etype.string_to_key(&derive.password, salt, params.as_deref())
+}
+
+fn print_key(label: &str, key: &[u8]) {
+    println!("[{}] {}", label, hex::encode_upper(key));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn derive(args: &[&str]) -> Result<Vec<u8>> {
+        let cli = Cli::try_parse_from([&["metaros", "key", "derive"], args].concat()).unwrap();
+        match &cli.command {
+            Commands::Key(KeyCommands::Derive(sc)) => {
+                let salt = resolve_salt(&sc.salt, sc.principal.as_deref(), &sc.salt_mode);
+                derive_key(etype::lookup(sc.etype.as_deref().unwrap())?, sc, &salt)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn create(args: &[&str]) -> Result<Vec<keytab::KeytabEntry>> {
+        let cli = Cli::try_parse_from([&["metaros", "keytab", "create", "-o", "out.kt"], args].concat()).unwrap();
+        match &cli.command {
+            Commands::Keytab(KeytabCommands::Create(x)) => password_entries(x,
+                x.principal.as_deref().unwrap(), x.name_type.as_deref().unwrap(),
+                x.timestamp.unwrap(), x.version.unwrap(), x.password.as_deref().unwrap()),
+            _ => unreachable!(),
+        }
+    }
+
+    // The MIT salt of raeburn@ATHENA.MIT.EDU is the RFC 3961 and RFC 3962
+    // test salt. DES is RFC 3961 appendix A.2 and RC4 the NT hash of
+    // "password"; AES is RFC 3962's PBKDF2 at its default 4096 iterations,
+    // checked with Python 3.11's hashlib.pbkdf2_hmac and the DK of RFC 3961
+    // section 5.1.
+    #[test]
+    fn create_from_password() {
+        let args = ["-p", "raeburn@ATHENA.MIT.EDU", "-n", "krb5_nt_principal", "-t", "1700000000",
+            "--version", "300", "--password", "password"];
+        let entries = create(&[&args[..], &["--des"]].concat()).unwrap();
+        let keys: Vec<(u16, String)> = entries.iter()
+            .map(|e| (e.key.key_type, e.key.key.decode()))
+            .collect();
+        assert_eq!(keys, [
+            (18, "01b897121d933ab44b47eb5494db15e50eb74530dbdae9b634d65020ff5d88c1".to_string()),
+            (17, "fca822951813fb252154c883f5ee1cf4".to_string()),
+            (23, "8846f7eaee8fb117ad06bdd830b7586c".to_string()),
+            (3, "cbc22fae235298e3".to_string()),
+        ]);
+        for e in &entries {
+            assert_eq!(e.principal(), "raeburn@ATHENA.MIT.EDU");
+            assert_eq!((e.kvno(), e.vno8, e.timestamp), (300, 44, 1700000000));
+        }
+        // an explicit salt replaces the computed one
+        let entries = create(&["-p", "user@EXAMPLE.COM", "-n", "krb5_nt_principal", "-t", "0",
+            "--version", "1", "--password", "password", "-s", "ATHENA.MIT.EDUraeburn", "-e", "des-cbc-md5"]).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key.key.decode(), "cbc22fae235298e3");
+        let entries = create(&["-p", "danny@WHITEHOUSE.GOV", "-n", "krb5_nt_principal", "-t", "0",
+            "--version", "1", "--password", "potatoe", "-e", "des-cbc-md5"]).unwrap();
+        assert_eq!(entries[0].key.key.decode(), "df3d32a74fd92a01");
+    }
+
+    #[test]
+    fn create_etype_conflicts_with_des() {
+        let args = ["metaros", "keytab", "create", "-o", "out.kt", "-p", "user@EXAMPLE.COM",
+            "-n", "krb5_nt_principal", "--password", "password", "--des", "-e", "rc4-hmac"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    fn usage(args: &[&str]) -> Result<(i32, u32, [String; 3])> {
+        let cli = Cli::try_parse_from([&["metaros", "key", "usage"], args].concat()).unwrap();
+        match &cli.command {
+            Commands::Key(KeyCommands::Usage(x)) => {
+                let (etype, usage, keys) = usage_keys(x)?;
+                Ok((etype.id(), usage, [keys.kc, keys.ke, keys.ki].map(hex::encode)))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn key_usage_names() {
+        assert_eq!(key_usage("ticket").unwrap(), 2);
+        assert_eq!(key_usage("krb5_keyusage_kdc_rep_ticket").unwrap(), 2);
+        assert_eq!(key_usage("2").unwrap(), 2);
+        // numbers outside the table are still usages
+        assert_eq!(key_usage("1026").unwrap(), 1026);
+        assert!(matches!(key_usage("tikcet"), Err(Error::UnknownKeyUsage(_))));
+        assert!(matches!(key_usage("-1"), Err(Error::UnknownKeyUsage(_))));
+    }
+
+    // The des3 keys for usage 2 under the RFC 3961 appendix A.4 key are
+    // from fixtures/gen/des3.py; the usage 1 Ki is RFC 3961 appendix A.3's
+    // first DK.
+    #[test]
+    fn key_usage_keys() {
+        let des3 = ["-e", "des3", "-k", "850bb51358548cd05e86768c313e3bfef7511937dcf72c3e", "-u"];
+        let keys = [
+            "f78c496d16e6c2dae0e0b6c24057a84c0426aeef26fd6dce".to_string(),
+            "5b5723d0b634cb684c3eba5264e9a70d52e683231ad3c4ce".to_string(),
+            "a77c94980e9b7345a81525c423a737ce67f4cd91b6b3da45".to_string(),
+        ];
+        assert_eq!(usage(&[&des3[..], &["ticket"]].concat()).unwrap(), (16, 2, keys.clone()));
+        assert_eq!(usage(&[&des3[..], &["2"]].concat()).unwrap(), (16, 2, keys));
+        let (_, _, [_, _, ki]) = usage(&["-e", "des3-cbc-sha1-kd", "-k",
+            "dce06b1f64c857a11c3db57c51899b2cc1791008ce973b92", "-u", "1"]).unwrap();
+        assert_eq!(ki, "925179d04591a79b5d3192c4a7e9c289b049c71f6ee604cd");
+        assert!(matches!(usage(&["-e", "des-cbc-md5", "-k", "cbc22fae235298e3", "-u", "2"]),
+            Err(Error::NoUsageKeys(_))));
+        assert!(matches!(usage(&["-e", "aes128", "-k", "00", "-u", "2"]),
+            Err(Error::BadKeyLength { expected: 16, actual: 1 })));
+    }
+
+    // RFC 3962 appendix B
+    #[test]
+    fn derive_iterations() {
+        let args = ["-p", "password", "-s", "ATHENA.MIT.EDUraeburn", "-e"];
+        assert_eq!(hex::encode(derive(&[&args[..], &["aes128", "--iterations", "1200"]].concat()).unwrap()),
+            "4c01cd46d632d01e6dbe230a01ed642a");
+        assert_eq!(hex::encode(derive(&[&args[..], &["aes256", "--iterations", "2"]].concat()).unwrap()),
+            "a2e16d16b36069c135d5e9d2e25f896102685618b95914b467c67622225824ff");
+        // etypes without an iteration count ignore it
+        assert_eq!(hex::encode(derive(&[&args[..], &["des3", "--iterations", "2"]].concat()).unwrap()),
+            "850bb51358548cd05e86768c313e3bfef7511937dcf72c3e");
+        assert!(matches!(derive(&[&args[..], &["aes128", "--iterations", "0"]].concat()),
+            Err(Error::BadS2kParams { .. })));
+    }
+}
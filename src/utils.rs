@@ -1,2 +1,4 @@
-pub mod args;
+#[allow(clippy::module_inception)]
 pub mod utils;
+#[cfg(test)]
+pub(crate) mod testing;
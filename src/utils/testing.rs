@@ -0,0 +1,18 @@
+// Helpers shared by the test modules that read files under fixtures/
+use crate::error::Result;
+use std::fs;
+use std::path::Path;
+
+// The contents of fixtures/<path>
+pub fn fixture(path: &str) -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(path))
+        .unwrap_or_else(|e| panic!("fixtures/{}: {}", path, e))
+}
+
+// Parses fixtures/<path> and checks that writing it back gives the same bytes
+pub fn round_trip<T>(path: &str, parse: impl FnOnce(&[u8]) -> Result<T>, write: impl FnOnce(&T) -> Vec<u8>) -> T {
+    let bytes = fixture(path);
+    let value = parse(&bytes).unwrap_or_else(|e| panic!("fixtures/{}: {}", path, e));
+    assert_eq!(write(&value), bytes, "fixtures/{}", path);
+    value
+}
@@ -1,3 +1,5 @@
+use chrono::Utc;
+
 
 pub fn lcm(n1: &usize, n2: &usize) -> usize {
     n1 * n2 / gcd(n1, n2)
@@ -25,3 +27,13 @@ pub fn gcd(n1: &usize, n2: &usize) -> usize {
 }
 
 
+
+pub fn timestamp_now() -> u32 {
+    u32::try_from(Utc::now().timestamp()).unwrap_or(u32::MAX)
+}
+
+
+// Compares without short-circuiting, for checking integrity tags.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}